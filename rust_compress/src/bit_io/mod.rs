@@ -0,0 +1,339 @@
+/// Buffered MSB-first bit writer: the first bit written becomes the highest
+/// bit of the first byte.
+///
+/// Bits collect in a 64-bit register and are flushed four bytes at a time,
+/// so writing a field costs a shift and an or rather than a loop over bits.
+#[derive(Debug, Clone, Default)]
+pub struct BitWriter {
+    bytes: Vec<u8>,
+    /// Pending bits, right-aligned; fewer than 32 between calls.
+    buffer: u64,
+    len: u32,
+}
+
+impl BitWriter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn write_bit(&mut self, bit: bool) {
+        self.write_bits(bit as u64, 1);
+    }
+
+    /// Writes the low `num_bits` bits of `value`, most significant first.
+    ///
+    /// # Panics
+    /// Panics if `num_bits` is above 64.
+    pub fn write_bits(&mut self, value: u64, num_bits: u32) {
+        assert!(num_bits <= 64, "cannot write more than 64 bits at once");
+        if num_bits > 32 {
+            self.write_bits(value >> 32, num_bits - 32);
+            self.write_bits(value & u64::from(u32::MAX), 32);
+            return;
+        }
+        if num_bits == 0 {
+            return;
+        }
+        let value = value & (u64::MAX >> (64 - num_bits));
+        self.buffer = (self.buffer << num_bits) | value;
+        self.len += num_bits;
+        if self.len >= 32 {
+            self.len -= 32;
+            let word = (self.buffer >> self.len) as u32;
+            self.bytes.extend_from_slice(&word.to_be_bytes());
+        }
+    }
+
+    /// Writes `q` in unary: `q` zero bits followed by a one.
+    pub fn write_unary(&mut self, q: u64) {
+        let mut zeros = q;
+        while zeros >= 32 {
+            self.write_bits(0, 32);
+            zeros -= 32;
+        }
+        self.write_bits(1, zeros as u32 + 1);
+    }
+
+    /// Writes `value < range` in truncated binary: with `c = ceil(log2 range)`,
+    /// the first `2^c - range` values take `c - 1` bits and the rest take `c`.
+    /// A range of one takes no bits.
+    pub fn write_truncated(&mut self, value: u64, range: u64) {
+        let (short_bits, cutoff) = truncated_params(range);
+        if value < cutoff {
+            self.write_bits(value, short_bits);
+        } else {
+            self.write_bits(value + cutoff, short_bits + 1);
+        }
+    }
+
+    /// Number of bits written so far.
+    pub fn bit_len(&self) -> usize {
+        self.bytes.len() * 8 + self.len as usize
+    }
+
+    /// Pads the last byte with zero bits and returns the bytes.
+    pub fn finish(mut self) -> Vec<u8> {
+        let pad = (8 - self.len % 8) % 8;
+        self.buffer <<= pad;
+        self.len += pad;
+        while self.len > 0 {
+            self.len -= 8;
+            self.bytes.push((self.buffer >> self.len) as u8);
+        }
+        self.bytes
+    }
+}
+
+/// Buffered MSB-first bit reader over a borrowed slice, the counterpart of
+/// [`BitWriter`].
+///
+/// Refills load eight bytes at once where the input allows, and unary codes
+/// are decoded with `leading_zeros` instead of a loop over bits. Reads past
+/// the end of the input return `None`.
+#[derive(Debug, Clone)]
+pub struct BitReader<'a> {
+    bytes: &'a [u8],
+    /// Next byte to load into `buffer`.
+    pos: usize,
+    /// Unread bits, left-aligned; the bits below them are zero.
+    buffer: u64,
+    available: u32,
+}
+
+impl<'a> BitReader<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        BitReader {
+            bytes,
+            pos: 0,
+            buffer: 0,
+            available: 0,
+        }
+    }
+
+    /// Loads whole bytes until at least 57 bits are buffered or the input
+    /// is exhausted.
+    fn refill(&mut self) {
+        if let Some(chunk) = self.bytes.get(self.pos..self.pos + 8) {
+            let word = u64::from_be_bytes(chunk.try_into().unwrap());
+            let take = (64 - self.available) / 8;
+            let filled = self.available + take * 8;
+            let keep = if filled == 64 {
+                u64::MAX
+            } else {
+                !(u64::MAX >> filled)
+            };
+            self.buffer |= (word >> self.available) & keep;
+            self.available = filled;
+            self.pos += take as usize;
+            return;
+        }
+        while self.available <= 56 && self.pos < self.bytes.len() {
+            self.buffer |= (self.bytes[self.pos] as u64) << (56 - self.available);
+            self.available += 8;
+            self.pos += 1;
+        }
+    }
+
+    fn consume(&mut self, num_bits: u32) {
+        self.buffer = if num_bits == 64 {
+            0
+        } else {
+            self.buffer << num_bits
+        };
+        self.available -= num_bits;
+    }
+
+    pub fn read_bit(&mut self) -> Option<bool> {
+        self.read_bits(1).map(|bit| bit == 1)
+    }
+
+    /// Reads `num_bits` bits, most significant first.
+    ///
+    /// # Panics
+    /// Panics if `num_bits` is above 64.
+    pub fn read_bits(&mut self, num_bits: u32) -> Option<u64> {
+        assert!(num_bits <= 64, "cannot read more than 64 bits at once");
+        if num_bits > 32 {
+            let high = self.read_bits(num_bits - 32)?;
+            let low = self.read_bits(32)?;
+            return Some((high << 32) | low);
+        }
+        if num_bits == 0 {
+            return Some(0);
+        }
+        if self.available < num_bits {
+            self.refill();
+            if self.available < num_bits {
+                return None;
+            }
+        }
+        let value = self.buffer >> (64 - num_bits);
+        self.consume(num_bits);
+        Some(value)
+    }
+
+    /// Reads a unary code written by [`BitWriter::write_unary`], returning
+    /// the number of zero bits before the terminating one.
+    pub fn read_unary(&mut self) -> Option<u64> {
+        let mut q = 0;
+        loop {
+            if self.available == 0 {
+                self.refill();
+                if self.available == 0 {
+                    return None;
+                }
+            }
+            // Bits below `available` are zero, so a count past it means
+            // every buffered bit is a zero
+            let zeros = self.buffer.leading_zeros();
+            if zeros < self.available {
+                self.consume(zeros + 1);
+                return Some(q + zeros as u64);
+            }
+            q += self.available as u64;
+            self.consume(self.available);
+        }
+    }
+
+    /// Reads a value written by [`BitWriter::write_truncated`] with the same
+    /// `range`. The result is always below `range`.
+    pub fn read_truncated(&mut self, range: u64) -> Option<u64> {
+        let (short_bits, cutoff) = truncated_params(range);
+        let prefix = self.read_bits(short_bits)?;
+        if prefix < cutoff {
+            return Some(prefix);
+        }
+        let last = self.read_bits(1)?;
+        Some(((prefix << 1) | last) - cutoff)
+    }
+
+    /// Number of bits read so far.
+    pub fn bits_consumed(&self) -> usize {
+        self.pos * 8 - self.available as usize
+    }
+
+    /// Number of bytes touched so far, counting a partially read byte.
+    pub fn bytes_consumed(&self) -> usize {
+        self.bits_consumed().div_ceil(8)
+    }
+}
+
+/// Short code length and number of short codes of the truncated binary code
+/// for `range` values.
+fn truncated_params(range: u64) -> (u32, u64) {
+    if range <= 1 {
+        return (0, 1);
+    }
+    let bits = u64::BITS - (range - 1).leading_zeros();
+    let cutoff = ((1u128 << bits) - range as u128) as u64;
+    (bits - 1, cutoff)
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::Rng;
+
+    use super::*;
+
+    #[test]
+    fn test_msb_first_layout() {
+        let mut writer = BitWriter::new();
+        writer.write_bit(true);
+        writer.write_bits(0b010, 3);
+        writer.write_unary(2);
+        assert_eq!(writer.bit_len(), 7);
+        assert_eq!(writer.finish(), vec![0b1010_0010]);
+
+        let mut writer = BitWriter::new();
+        writer.write_bits(0xABCD, 16);
+        writer.write_bits(0x1, 4);
+        assert_eq!(writer.finish(), vec![0xAB, 0xCD, 0x10]);
+    }
+
+    #[test]
+    fn test_random_fields_roundtrip() {
+        let mut rng = rand::thread_rng();
+        let fields: Vec<(u64, u32)> = (0..5000)
+            .map(|_| {
+                let width = rng.gen_range(0..=64);
+                let value = if width == 0 {
+                    0
+                } else {
+                    rng.r#gen::<u64>() >> (64 - width)
+                };
+                (value, width)
+            })
+            .collect();
+
+        let mut writer = BitWriter::new();
+        for &(value, width) in &fields {
+            writer.write_bits(value, width);
+        }
+        let total_bits = writer.bit_len();
+        let bytes = writer.finish();
+        assert_eq!(bytes.len(), total_bits.div_ceil(8));
+
+        let mut reader = BitReader::new(&bytes);
+        for &(value, width) in &fields {
+            assert_eq!(reader.read_bits(width), Some(value));
+        }
+        assert_eq!(reader.bits_consumed(), total_bits);
+    }
+
+    #[test]
+    fn test_unary_roundtrip() {
+        let quotients = [0, 1, 31, 32, 33, 63, 64, 65, 200, 1000, 0, 7];
+        let mut writer = BitWriter::new();
+        for &q in &quotients {
+            writer.write_unary(q);
+            writer.write_bits(5, 3);
+        }
+        let bytes = writer.finish();
+
+        let mut reader = BitReader::new(&bytes);
+        for &q in &quotients {
+            assert_eq!(reader.read_unary(), Some(q));
+            assert_eq!(reader.read_bits(3), Some(5));
+        }
+    }
+
+    #[test]
+    fn test_reads_past_end() {
+        let bytes = [0xFF, 0x00];
+        let mut reader = BitReader::new(&bytes);
+        assert_eq!(reader.read_bits(12), Some(0xFF0));
+        assert_eq!(reader.read_bits(5), None);
+        assert_eq!(reader.read_unary(), None);
+
+        let mut reader = BitReader::new(&[]);
+        assert_eq!(reader.read_bit(), None);
+        assert_eq!(reader.bytes_consumed(), 0);
+    }
+
+    #[test]
+    fn test_truncated_binary() {
+        // Range 5: 0..3 take 2 bits, 3 and 4 take 3 bits
+        let mut writer = BitWriter::new();
+        for value in 0..5 {
+            writer.write_truncated(value, 5);
+        }
+        writer.write_truncated(0, 1);
+        assert_eq!(writer.bit_len(), 2 * 3 + 3 * 2);
+        assert_eq!(writer.finish(), vec![0b0001_1011, 0b0111_0000]);
+
+        let ranges = [1, 2, 3, 7, 8, 1000, 1 << 32, u64::MAX];
+        let mut writer = BitWriter::new();
+        for &range in &ranges {
+            for value in [0, range / 3, range - 1] {
+                writer.write_truncated(value, range);
+            }
+        }
+        let bytes = writer.finish();
+        let mut reader = BitReader::new(&bytes);
+        for &range in &ranges {
+            for value in [0, range / 3, range - 1] {
+                assert_eq!(reader.read_truncated(range), Some(value), "range {range}");
+            }
+        }
+    }
+}
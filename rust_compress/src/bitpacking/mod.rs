@@ -0,0 +1,360 @@
+use crate::codec::{IntegerCodec, read_count, write_count};
+use crate::error::DecodeError;
+use crate::p_for_delta::{COMPRESSORS, DECOMPRESSORS};
+use crate::stream;
+
+/// Number of values per block when none is given.
+pub const DEFAULT_BLOCK_SIZE: usize = 128;
+
+/// Number of bits needed to store every value of `values`: 0 when they are
+/// all zero, 32 when one has its top bit set.
+pub fn max_bits(values: &[u32]) -> u32 {
+    let max = values.iter().fold(0, |acc, &v| acc | v);
+    u32::BITS - max.leading_zeros()
+}
+
+/// Number of bytes [`pack`] writes for `count` values of `bits` bits.
+pub fn packed_len(count: usize, bits: u32) -> usize {
+    (count * bits as usize).div_ceil(8)
+}
+
+/// Packs `values` with `bits` bits each, LSB first, appending
+/// [`packed_len`] bytes to `output`. Bits above `bits` are dropped.
+///
+/// Groups of 32 values go through the unrolled kernels shared with
+/// [`p_for_delta`](crate::p_for_delta); a shorter last group is padded with
+/// zeros and only the bytes holding its values are written, so any length
+/// is accepted and the layout is one continuous bit stream.
+///
+/// # Panics
+/// Panics if `bits` is above 32.
+pub fn pack(values: &[u32], bits: u32, output: &mut Vec<u8>) {
+    assert!(bits <= 32, "cannot pack more than 32 bits per value");
+    if bits == 0 {
+        return;
+    }
+    let bits = bits as usize;
+    let compress = COMPRESSORS[bits - 1];
+
+    let mut words = [0u32; 32];
+    let mut groups = values.chunks_exact(32);
+    for group in &mut groups {
+        compress(group, &mut words[..bits]);
+        for word in &words[..bits] {
+            output.extend_from_slice(&word.to_le_bytes());
+        }
+    }
+
+    let tail = groups.remainder();
+    if !tail.is_empty() {
+        let mut padded = [0u32; 32];
+        padded[..tail.len()].copy_from_slice(tail);
+        compress(&padded, &mut words[..bits]);
+        let bytes = words[..bits].iter().flat_map(|word| word.to_le_bytes());
+        output.extend(bytes.take(packed_len(tail.len(), bits as u32)));
+    }
+}
+
+/// Unpacks `output.len()` values of `bits` bits written by [`pack`] and
+/// returns the number of bytes read.
+///
+/// # Panics
+/// Panics if `bits` is above 32.
+pub fn unpack(input: &[u8], bits: u32, output: &mut [u32]) -> Result<usize, DecodeError> {
+    assert!(bits <= 32, "cannot unpack more than 32 bits per value");
+    let len = packed_len(output.len(), bits);
+    let input = input.get(..len).ok_or(DecodeError::Truncated)?;
+    if bits == 0 {
+        output.fill(0);
+        return Ok(0);
+    }
+    let bits = bits as usize;
+    let decompress = DECOMPRESSORS[bits - 1];
+
+    let mut words = [0u32; 32];
+    for (group, bytes) in output.chunks_mut(32).zip(input.chunks(4 * bits)) {
+        // The last group may stop mid-word; its missing bytes are padding
+        let mut padded = [0u8; 128];
+        padded[..bytes.len()].copy_from_slice(bytes);
+        for (word, chunk) in words.iter_mut().zip(padded[..4 * bits].chunks_exact(4)) {
+            *word = u32::from_le_bytes(chunk.try_into().unwrap());
+        }
+        if group.len() == 32 {
+            decompress(&words[..bits], group);
+        } else {
+            let mut full = [0u32; 32];
+            decompress(&words[..bits], &mut full);
+            group.copy_from_slice(&full[..group.len()]);
+        }
+    }
+    Ok(len)
+}
+
+/// Compresses u32 numbers with frame-of-reference bit packing in blocks of
+/// [`DEFAULT_BLOCK_SIZE`] values, see [`compress_with_block_size`].
+pub fn compress(numbers: &[u32]) -> Vec<u8> {
+    compress_with_block_size(numbers, DEFAULT_BLOCK_SIZE)
+}
+
+/// Compresses u32 numbers with frame-of-reference bit packing.
+///
+/// Each block stores its minimum and packs the differences to it with the
+/// width of the largest one, so there are no exceptions to patch: one
+/// outlier widens its whole block. Blocks of 32, 128 or 256 values keep
+/// every group on the unrolled kernels; other sizes work too.
+///
+/// The stream holds the value count and the block size (4 bytes LE each),
+/// then per block the minimum (4 bytes LE), the bit width (1 byte) and the
+/// packed values. The last block may be shorter.
+///
+/// # Panics
+/// Panics if `block_size` is 0 or does not fit in 32 bits.
+pub fn compress_with_block_size(numbers: &[u32], block_size: usize) -> Vec<u8> {
+    assert!(block_size > 0, "block size must be at least 1");
+    assert!(
+        u32::try_from(block_size).is_ok(),
+        "block size must fit in 32 bits"
+    );
+
+    let mut output = Vec::new();
+    write_count(&mut output, numbers.len());
+    write_count(&mut output, block_size);
+
+    let mut offsets = Vec::with_capacity(block_size.min(numbers.len()));
+    for block in numbers.chunks(block_size) {
+        let min = *block.iter().min().unwrap();
+        offsets.clear();
+        offsets.extend(block.iter().map(|&v| v - min));
+        let bits = max_bits(&offsets);
+
+        output.extend_from_slice(&min.to_le_bytes());
+        output.push(bits as u8);
+        pack(&offsets, bits, &mut output);
+    }
+    output
+}
+
+/// Decompresses data produced by [`compress`] or
+/// [`compress_with_block_size`].
+pub fn decompress(encoded: &[u8]) -> Result<Vec<u32>, DecodeError> {
+    let mut numbers = Vec::new();
+    decode_append(encoded, &mut numbers)?;
+    Ok(numbers)
+}
+
+/// Decodes a stream produced by [`compress`], appending the values to
+/// `output`. Returns the number of values appended.
+pub fn decode_append(encoded: &[u8], output: &mut Vec<u32>) -> Result<usize, DecodeError> {
+    let (count, block_size, blocks) = read_header(encoded)?;
+    let start = output.len();
+    output.resize(start + count, 0);
+    if let Err(err) = decode_blocks(blocks, block_size, &mut output[start..]) {
+        output.truncate(start);
+        return Err(err);
+    }
+    Ok(count)
+}
+
+/// Decodes a stream produced by [`compress`] into `output` without
+/// allocating, and returns the number of values written. Fails with
+/// [`DecodeError::OutputTooSmall`] if the header announces more values than
+/// `output` can hold.
+pub fn decode_into(encoded: &[u8], output: &mut [u32]) -> Result<usize, DecodeError> {
+    let (count, block_size, blocks) = read_header(encoded)?;
+    let output = output.get_mut(..count).ok_or(DecodeError::OutputTooSmall)?;
+    decode_blocks(blocks, block_size, output)?;
+    Ok(count)
+}
+
+/// Splits off the value count and block size, and checks that the input
+/// holds at least a header for every block before anything is allocated.
+fn read_header(encoded: &[u8]) -> Result<(usize, usize, &[u8]), DecodeError> {
+    let (count, rest) = read_count(encoded)?;
+    let (block_size, blocks) = read_count(rest)?;
+    if block_size == 0 {
+        return Err(DecodeError::InvalidHeader);
+    }
+    if count.div_ceil(block_size) * 5 > blocks.len() {
+        return Err(DecodeError::Truncated);
+    }
+    Ok((count, block_size, blocks))
+}
+
+fn decode_blocks(
+    mut blocks: &[u8],
+    block_size: usize,
+    output: &mut [u32],
+) -> Result<(), DecodeError> {
+    for block in output.chunks_mut(block_size) {
+        let (header, rest) = blocks.split_at_checked(5).ok_or(DecodeError::Truncated)?;
+        let min = u32::from_le_bytes(header[..4].try_into().unwrap());
+        let bits = header[4] as u32;
+        if bits > 32 {
+            return Err(DecodeError::InvalidHeader);
+        }
+
+        let read = unpack(rest, bits, block)?;
+        for value in block.iter_mut() {
+            *value = value.checked_add(min).ok_or(DecodeError::Overflow)?;
+        }
+        blocks = &rest[read..];
+    }
+    if !blocks.is_empty() {
+        return Err(DecodeError::TrailingBytes);
+    }
+    Ok(())
+}
+
+/// Frame-of-reference bit packing as an [`IntegerCodec`], see
+/// [`compress_with_block_size`].
+#[derive(Debug, Clone, Copy)]
+pub struct BitPacking {
+    pub block_size: usize,
+}
+
+impl Default for BitPacking {
+    fn default() -> Self {
+        BitPacking {
+            block_size: DEFAULT_BLOCK_SIZE,
+        }
+    }
+}
+
+impl IntegerCodec for BitPacking {
+    type Value = u32;
+
+    fn encode(&self, input: &[u32], output: &mut Vec<u8>) {
+        output.extend_from_slice(&compress_with_block_size(input, self.block_size));
+    }
+
+    fn decode(&self, input: &[u8], output: &mut Vec<u32>) -> Result<usize, DecodeError> {
+        decode_append(input, output)
+    }
+}
+
+/// Streaming bit-packing encoder over a [`std::io::Write`], see [`stream::Encoder`].
+pub type Encoder<W> = stream::Encoder<W, BitPacking>;
+
+/// Streaming bit-packing decoder over a [`std::io::Read`], see [`stream::Decoder`].
+pub type Decoder<R> = stream::Decoder<R, BitPacking>;
+
+#[cfg(test)]
+mod tests {
+    use rand::Rng;
+
+    use super::*;
+
+    #[test]
+    fn test_pack_roundtrip_every_width_and_length() {
+        let mut rng = rand::thread_rng();
+        for bits in 0..=32 {
+            for len in [0, 1, 5, 31, 32, 33, 100, 128, 256, 257] {
+                let values: Vec<u32> = (0..len)
+                    .map(|_| match bits {
+                        0 => 0,
+                        32 => rng.r#gen(),
+                        _ => rng.gen_range(0..1u32 << bits),
+                    })
+                    .collect();
+                let mut packed = Vec::new();
+                pack(&values, bits, &mut packed);
+                assert_eq!(packed.len(), packed_len(len, bits));
+
+                let mut unpacked = vec![u32::MAX; len];
+                assert_eq!(unpack(&packed, bits, &mut unpacked), Ok(packed.len()));
+                assert_eq!(unpacked, values, "bits {bits}, len {len}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_pack_is_one_lsb_first_bit_stream() {
+        let mut packed = Vec::new();
+        pack(&[1, 2, 3, 0, 7], 3, &mut packed);
+        // 001 010 011 000 111 from the low bits up
+        assert_eq!(packed, vec![0b1101_0001, 0b0111_0000]);
+    }
+
+    #[test]
+    fn test_max_bits() {
+        assert_eq!(max_bits(&[]), 0);
+        assert_eq!(max_bits(&[0, 0]), 0);
+        assert_eq!(max_bits(&[1, 4, 2]), 3);
+        assert_eq!(max_bits(&[u32::MAX]), 32);
+    }
+
+    #[test]
+    fn test_frame_of_reference_roundtrip() {
+        let numbers: Vec<u32> = (0..1000u32)
+            .map(|i| 1_000_000 + (i.wrapping_mul(2_654_435_761) >> (20 + i % 12)))
+            .chain([0, u32::MAX, 5])
+            .collect();
+        for block_size in [1, 32, 100, 128, 256, 5000] {
+            let encoded = compress_with_block_size(&numbers, block_size);
+            assert_eq!(decompress(&encoded).unwrap(), numbers, "block {block_size}");
+        }
+        assert!(decompress(&compress(&[])).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_minimum_is_subtracted() {
+        // 128 values within 15 of a large base pack in 4 bits each
+        let numbers: Vec<u32> = (0..128).map(|i| 3_000_000_000 + i % 16).collect();
+        let encoded = compress(&numbers);
+        assert_eq!(encoded.len(), 8 + 5 + 128 * 4 / 8);
+
+        let constant = compress(&[42; 300]);
+        assert_eq!(constant.len(), 8 + 3 * 5);
+        assert_eq!(decompress(&constant).unwrap(), vec![42; 300]);
+    }
+
+    #[test]
+    fn test_rejects_malformed_streams() {
+        assert_eq!(decompress(&[]), Err(DecodeError::Truncated));
+
+        let numbers: Vec<u32> = (0..300).map(|i| i * 7).collect();
+        let encoded = compress(&numbers);
+        assert_eq!(
+            decompress(&encoded[..encoded.len() - 1]),
+            Err(DecodeError::Truncated)
+        );
+
+        let mut extra = encoded.clone();
+        extra.push(0);
+        assert_eq!(decompress(&extra), Err(DecodeError::TrailingBytes));
+
+        let mut zero_block = encoded.clone();
+        zero_block[4..8].copy_from_slice(&[0; 4]);
+        assert_eq!(decompress(&zero_block), Err(DecodeError::InvalidHeader));
+
+        let mut wide = encoded.clone();
+        wide[12] = 33;
+        assert_eq!(decompress(&wide), Err(DecodeError::InvalidHeader));
+
+        // A minimum that pushes the packed offsets past u32::MAX
+        let mut high_min = encoded.clone();
+        high_min[8..12].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert_eq!(decompress(&high_min), Err(DecodeError::Overflow));
+
+        // A huge count with no blocks behind it
+        let mut huge = encoded[..8].to_vec();
+        huge[..4].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert_eq!(decompress(&huge), Err(DecodeError::Truncated));
+    }
+
+    #[test]
+    fn test_decode_into_caller_buffer() {
+        let numbers = vec![3, 17, 0, 250, 9];
+        let encoded = compress_with_block_size(&numbers, 2);
+
+        let mut output = [0u32; 8];
+        assert_eq!(decode_into(&encoded, &mut output), Ok(5));
+        assert_eq!(&output[..5], &numbers[..]);
+
+        let mut short = [0u32; 4];
+        assert_eq!(
+            decode_into(&encoded, &mut short),
+            Err(DecodeError::OutputTooSmall)
+        );
+    }
+}
@@ -0,0 +1,170 @@
+use crate::error::DecodeError;
+
+/// Common interface implemented by every codec in the crate.
+///
+/// Each module exposes a small type (`Simple9`, `Simple16`, `Simple8b`,
+/// `Rice`, `AdaptiveRice`, `Golomb`, `Universal`, `VarByte`, `GroupVarint`,
+/// `StreamVByte`, `PForDelta`, `BitPacking`, `SimdBp128`) implementing this
+/// trait, so index code can be written once and swap or benchmark codecs
+/// generically.
+///
+/// The byte stream produced by `encode` is self-describing: `decode`
+/// yields exactly the values that were encoded, with no padding. Streams
+/// store their value count in 32 bits, so encoding more than `u32::MAX`
+/// values panics rather than producing a stream of the wrong length.
+pub trait IntegerCodec {
+    /// Integer type accepted by the codec.
+    type Value: Copy;
+
+    /// Encodes `input` and appends the bytes to `output`.
+    fn encode(&self, input: &[Self::Value], output: &mut Vec<u8>);
+
+    /// Decodes a stream produced by `encode`, appending the values to
+    /// `output`. Returns the number of values appended.
+    ///
+    /// On error `output` may hold a partially decoded prefix.
+    fn decode(&self, input: &[u8], output: &mut Vec<Self::Value>) -> Result<usize, DecodeError>;
+
+    /// Number of bytes `encode` appends for `input`.
+    fn encoded_len(&self, input: &[Self::Value]) -> usize {
+        let mut scratch = Vec::new();
+        self.encode(input, &mut scratch);
+        scratch.len()
+    }
+}
+
+/// Appends the value count used by the word-aligned codecs to frame their
+/// output (4 bytes, little endian).
+pub(crate) fn write_count(output: &mut Vec<u8>, count: usize) {
+    output.extend_from_slice(&count_field(count).to_le_bytes());
+}
+
+/// Checks that a value count fits the 32-bit field stream headers store it
+/// in.
+///
+/// # Panics
+/// Panics if `count` exceeds `u32::MAX`.
+pub(crate) fn count_field(count: usize) -> u32 {
+    u32::try_from(count).expect("cannot encode more than u32::MAX values")
+}
+
+/// Splits the 4-byte value count written by `write_count` off `input`.
+pub(crate) fn read_count(input: &[u8]) -> Result<(usize, &[u8]), DecodeError> {
+    let (count, rest) = input.split_at_checked(4).ok_or(DecodeError::Truncated)?;
+    Ok((u32::from_le_bytes(count.try_into().unwrap()) as usize, rest))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bitpacking::BitPacking;
+    use crate::golomb::Golomb;
+    use crate::group_varint::GroupVarint;
+    use crate::p_for_delta::PForDelta;
+    use crate::rice::{AdaptiveRice, Rice};
+    use crate::simple8b::Simple8b;
+    use crate::simple9::Simple9;
+    use crate::simd_bp128::SimdBp128;
+    use crate::simple16::Simple16;
+    use crate::stream_vbyte::StreamVByte;
+    use crate::universal::{Universal, UniversalCode};
+    use crate::var_byte::VarByte;
+
+    fn roundtrip<C: IntegerCodec>(codec: &C, values: &[C::Value]) -> Vec<C::Value> {
+        let mut encoded = Vec::new();
+        codec.encode(values, &mut encoded);
+        assert_eq!(codec.encoded_len(values), encoded.len());
+
+        let mut decoded = Vec::new();
+        let count = codec.decode(&encoded, &mut decoded).unwrap();
+        assert_eq!(count, decoded.len());
+        decoded
+    }
+
+    #[test]
+    fn test_all_u32_codecs_roundtrip() {
+        let values: Vec<u32> = vec![5, 267, 128, 1000, 42, 0, 1, 3, 77];
+        assert_eq!(roundtrip(&Simple9, &values), values);
+        assert_eq!(roundtrip(&Simple16, &values), values);
+        assert_eq!(roundtrip(&VarByte, &values), values);
+        assert_eq!(roundtrip(&GroupVarint, &values), values);
+        assert_eq!(roundtrip(&StreamVByte, &values), values);
+        assert_eq!(roundtrip(&BitPacking::default(), &values), values);
+        assert_eq!(roundtrip(&BitPacking { block_size: 4 }, &values), values);
+        let blocks: Vec<u32> = (0..300).map(|i| i * 31 % 1000).collect();
+        assert_eq!(roundtrip(&SimdBp128, &values), values);
+        assert_eq!(roundtrip(&SimdBp128, &blocks), blocks);
+        assert_eq!(roundtrip(&Rice::default(), &values), values);
+        assert_eq!(roundtrip(&Rice { k: Some(3) }, &values), values);
+        assert_eq!(roundtrip(&AdaptiveRice::default(), &values), values);
+        assert_eq!(roundtrip(&AdaptiveRice { block_size: 4 }, &values), values);
+        assert_eq!(roundtrip(&Golomb::default(), &values), values);
+        assert_eq!(roundtrip(&Golomb { b: Some(3) }, &values), values);
+        for code in [UniversalCode::Gamma, UniversalCode::Delta, UniversalCode::ExpGolomb(2)] {
+            assert_eq!(roundtrip(&Universal { code }, &values), values);
+        }
+    }
+
+    #[test]
+    fn test_simple8b_roundtrip() {
+        let values: Vec<u64> = vec![1, 2, 3, 1 << 40, 7, 9];
+        assert_eq!(roundtrip(&Simple8b, &values), values);
+    }
+
+    #[test]
+    fn test_p_for_delta_roundtrip() {
+        let values: Vec<u32> = (0..300)
+            .map(|i| if i % 20 == 0 { 5000 } else { i % 7 })
+            .collect();
+        assert_eq!(roundtrip(&PForDelta, &values), values);
+        assert_eq!(roundtrip(&PForDelta, &values[..5]), &values[..5]);
+    }
+
+    #[test]
+    fn test_decode_appends_to_existing_output() {
+        let mut encoded = Vec::new();
+        Simple16.encode(&[4, 5, 6], &mut encoded);
+
+        let mut decoded = vec![1, 2, 3];
+        assert_eq!(Simple16.decode(&encoded, &mut decoded), Ok(3));
+        assert_eq!(decoded, vec![1, 2, 3, 4, 5, 6]);
+    }
+
+    #[test]
+    fn test_empty_input() {
+        assert!(roundtrip(&Simple9, &[]).is_empty());
+        assert!(roundtrip(&Simple8b, &[]).is_empty());
+        assert!(roundtrip(&VarByte, &[]).is_empty());
+        assert!(roundtrip(&Rice::default(), &[]).is_empty());
+        assert!(roundtrip(&PForDelta, &[]).is_empty());
+    }
+
+    #[test]
+    fn test_decode_rejects_truncated_streams() {
+        let values: Vec<u32> = (0..50).collect();
+        let mut encoded = Vec::new();
+        Simple9.encode(&values, &mut encoded);
+
+        let mut decoded = Vec::new();
+        assert_eq!(
+            Simple9.decode(&encoded[..2], &mut decoded),
+            Err(DecodeError::Truncated)
+        );
+        assert_eq!(
+            Simple9.decode(&encoded[..encoded.len() - 4], &mut decoded),
+            Err(DecodeError::Truncated)
+        );
+        assert_eq!(
+            Simple9.decode(&encoded[..encoded.len() - 1], &mut decoded),
+            Err(DecodeError::Truncated)
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    #[cfg(target_pointer_width = "64")]
+    fn test_count_field_rejects_oversize_counts() {
+        assert_eq!(count_field(u32::MAX as usize), u32::MAX);
+        count_field(u32::MAX as usize + 1);
+    }
+}
@@ -0,0 +1,546 @@
+use std::fmt::Debug;
+use std::marker::PhantomData;
+
+use crate::error::{DecodeError, EncodeError};
+
+pub mod partitioned;
+
+pub use partitioned::PartitionedEliasFano;
+
+/// Every `SELECT_SAMPLE`-th one (and zero) of the upper bitmap has its
+/// position stored, so a select scans at most that many bits' worth of words.
+const SELECT_SAMPLE: usize = 256;
+
+/// Integer types an [`EliasFano`] sequence can hold.
+pub trait SortedValue: Copy + Debug + Ord {
+    fn to_u64(self) -> u64;
+
+    /// Converts back a value that was produced by [`SortedValue::to_u64`].
+    fn from_u64(value: u64) -> Self;
+
+    /// Whether `value` is representable in this type.
+    fn fits(value: u64) -> bool;
+}
+
+impl SortedValue for u32 {
+    fn to_u64(self) -> u64 {
+        self as u64
+    }
+
+    fn from_u64(value: u64) -> Self {
+        value as u32
+    }
+
+    fn fits(value: u64) -> bool {
+        value <= u32::MAX as u64
+    }
+}
+
+impl SortedValue for u64 {
+    fn to_u64(self) -> u64 {
+        self
+    }
+
+    fn from_u64(value: u64) -> Self {
+        value
+    }
+
+    fn fits(_: u64) -> bool {
+        true
+    }
+}
+
+/// Elias-Fano representation of a non-decreasing sequence.
+///
+/// Each value is split into `low_bits` low bits, packed verbatim, and the
+/// remaining high part, stored in unary in the upper bitmap: value `i` sets
+/// bit `(v >> low_bits) + i`, so every zero closes one high bucket. With
+/// `low_bits = floor(log2(universe / len))` this takes at most
+/// `2 + ceil(log2(universe / len))` bits per value.
+///
+/// Sampled select positions give [`get`](EliasFano::get) in constant
+/// expected time, and [`next_geq`](EliasFano::next_geq) jumps straight to
+/// the bucket of its target through the zeros of the upper bitmap.
+#[derive(Debug, Clone)]
+pub struct EliasFano<T: SortedValue> {
+    lower: Vec<u64>,
+    upper: Vec<u64>,
+    /// Positions of every `SELECT_SAMPLE`-th one of `upper`.
+    one_samples: Vec<usize>,
+    /// Positions of every `SELECT_SAMPLE`-th zero of `upper`.
+    zero_samples: Vec<usize>,
+    low_bits: u32,
+    len: usize,
+    /// The largest value, or 0 when empty.
+    universe: u64,
+    _marker: PhantomData<T>,
+}
+
+impl<T: SortedValue> EliasFano<T> {
+    /// Encodes a non-decreasing sequence.
+    ///
+    /// Fails with [`EncodeError::Unsorted`] at the first value smaller than
+    /// its predecessor.
+    pub fn new(values: &[T]) -> Result<Self, EncodeError> {
+        if let Some(index) = values.windows(2).position(|pair| pair[1] < pair[0]) {
+            return Err(EncodeError::Unsorted { index: index + 1 });
+        }
+
+        let len = values.len();
+        let universe = values.last().map_or(0, |&v| v.to_u64());
+        let low_bits = low_bits_for(len, universe);
+        let upper_bits = upper_bits_for(len, universe, low_bits)
+            .expect("an in-memory sequence has a bitmap that fits in memory");
+
+        let mut lower = vec![0u64; (len * low_bits as usize).div_ceil(64)];
+        let mut upper = vec![0u64; upper_bits.div_ceil(64)];
+        for (i, &value) in values.iter().enumerate() {
+            let value = value.to_u64();
+            write_low(&mut lower, i, low_bits, value);
+            let bit = (value >> low_bits) as usize + i;
+            upper[bit / 64] |= 1 << (bit % 64);
+        }
+
+        Ok(Self::from_parts(lower, upper, low_bits, len, universe))
+    }
+
+    fn from_parts(
+        lower: Vec<u64>,
+        upper: Vec<u64>,
+        low_bits: u32,
+        len: usize,
+        universe: u64,
+    ) -> Self {
+        let zeros = upper.len() * 64 - len;
+        EliasFano {
+            one_samples: sample_positions(&upper, len, true),
+            zero_samples: sample_positions(&upper, zeros, false),
+            lower,
+            upper,
+            low_bits,
+            len,
+            universe,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Number of values in the sequence.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Number of low bits stored verbatim per value.
+    pub fn low_bits(&self) -> u32 {
+        self.low_bits
+    }
+
+    /// Size of the encoded bitmaps in bytes, without the select samples.
+    pub fn size_in_bytes(&self) -> usize {
+        (self.lower.len() + self.upper.len()) * 8
+    }
+
+    /// Returns the `index`-th value, or `None` if it is out of bounds.
+    pub fn get(&self, index: usize) -> Option<T> {
+        if index >= self.len {
+            return None;
+        }
+        let high = (self.select(index, true) - index) as u64;
+        Some(T::from_u64(self.value(index, high)))
+    }
+
+    /// Returns the position and value of the first value greater than or
+    /// equal to `target`, or `None` if every value is smaller.
+    pub fn next_geq(&self, target: T) -> Option<(usize, T)> {
+        let target = target.to_u64();
+        if self.len == 0 || target > self.universe {
+            return None;
+        }
+
+        // The bucket of `target` starts right after the zero closing the
+        // previous bucket, and every one before it is a smaller value
+        let bucket = (target >> self.low_bits) as usize;
+        let start = match bucket {
+            0 => 0,
+            _ => self.select(bucket - 1, false) + 1,
+        };
+        for (index, bit) in (start - bucket..).zip(OnesFrom::new(&self.upper, start)) {
+            let value = self.value(index, (bit - index) as u64);
+            if value >= target {
+                return Some((index, T::from_u64(value)));
+            }
+        }
+        unreachable!("the last value is the universe, which is not below the target")
+    }
+
+    /// Iterates over the values in order.
+    pub fn iter(&self) -> impl Iterator<Item = T> + '_ {
+        OnesFrom::new(&self.upper, 0)
+            .take(self.len)
+            .enumerate()
+            .map(|(index, bit)| T::from_u64(self.value(index, (bit - index) as u64)))
+    }
+
+    /// Serializes the sequence: the length and the universe (u64 LE each),
+    /// then the lower and upper bitmaps as u64 LE words. The select samples
+    /// are rebuilt on load.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(16 + self.size_in_bytes());
+        bytes.extend_from_slice(&(self.len as u64).to_le_bytes());
+        bytes.extend_from_slice(&self.universe.to_le_bytes());
+        for word in self.lower.iter().chain(&self.upper) {
+            bytes.extend_from_slice(&word.to_le_bytes());
+        }
+        bytes
+    }
+
+    /// Loads a sequence written by [`to_bytes`](EliasFano::to_bytes).
+    ///
+    /// The bitmaps are checked against the header, so a sequence that loads
+    /// successfully answers every query without panicking.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let mut words = bytes.chunks(8).map(|chunk| {
+            <[u8; 8]>::try_from(chunk)
+                .map(u64::from_le_bytes)
+                .map_err(|_| DecodeError::Truncated)
+        });
+        let mut next_word = || words.next().unwrap_or(Err(DecodeError::Truncated));
+
+        let len = next_word()?;
+        let universe = next_word()?;
+        if !T::fits(universe) {
+            return Err(DecodeError::Overflow);
+        }
+        // Every value takes at least one bit of the upper bitmap, so this
+        // bounds the length before it is used in any size computation
+        if len > (bytes.len() as u64).saturating_mul(8) || (len == 0 && universe != 0) {
+            return Err(DecodeError::InvalidHeader);
+        }
+        let len = len as usize;
+        let low_bits = low_bits_for(len, universe);
+        let upper_bits =
+            upper_bits_for(len, universe, low_bits).ok_or(DecodeError::InvalidHeader)?;
+        let lower_words = (len * low_bits as usize).div_ceil(64);
+        let upper_words = upper_bits.div_ceil(64);
+
+        let expected = 16 + (lower_words + upper_words) * 8;
+        if bytes.len() < expected {
+            return Err(DecodeError::Truncated);
+        }
+        if bytes.len() > expected {
+            return Err(DecodeError::TrailingBytes);
+        }
+
+        let lower: Vec<u64> = (0..lower_words)
+            .map(|_| next_word())
+            .collect::<Result<_, _>>()?;
+        let upper: Vec<u64> = (0..upper_words)
+            .map(|_| next_word())
+            .collect::<Result<_, _>>()?;
+
+        // Exactly `len` ones, none in the padding, and the last value must
+        // be the universe, which `next_geq` relies on
+        let ones: usize = upper.iter().map(|w| w.count_ones() as usize).sum();
+        let padding = upper_words * 64 - upper_bits;
+        let padding_clear = padding == 0 || upper[upper_words - 1] >> (64 - padding) == 0;
+        if ones != len || !padding_clear {
+            return Err(DecodeError::InvalidHeader);
+        }
+
+        let sequence = Self::from_parts(lower, upper, low_bits, len, universe);
+        if len > 0 && sequence.get(len - 1).map(T::to_u64) != Some(universe) {
+            return Err(DecodeError::InvalidHeader);
+        }
+        Ok(sequence)
+    }
+
+    /// Combines the high part of value `index` with its low bits.
+    fn value(&self, index: usize, high: u64) -> u64 {
+        (high << self.low_bits) | read_low(&self.lower, index, self.low_bits)
+    }
+
+    /// Position of the `rank`-th one (or zero) of the upper bitmap, which
+    /// must exist.
+    fn select(&self, rank: usize, ones: bool) -> usize {
+        let samples = if ones {
+            &self.one_samples
+        } else {
+            &self.zero_samples
+        };
+        let start = samples[rank / SELECT_SAMPLE];
+        let mut remaining = rank % SELECT_SAMPLE;
+
+        let mut word_idx = start / 64;
+        let mut word = polarity(self.upper[word_idx], ones) & (u64::MAX << (start % 64));
+        loop {
+            let count = word.count_ones() as usize;
+            if remaining < count {
+                return word_idx * 64 + select_in_word(word, remaining);
+            }
+            remaining -= count;
+            word_idx += 1;
+            word = polarity(self.upper[word_idx], ones);
+        }
+    }
+}
+
+/// `floor(log2(universe / len))`, or 0 when the ratio is below 2.
+fn low_bits_for(len: usize, universe: u64) -> u32 {
+    if len == 0 {
+        return 0;
+    }
+    let ratio = universe / len as u64;
+    if ratio == 0 {
+        0
+    } else {
+        63 - ratio.leading_zeros()
+    }
+}
+
+/// Length of the upper bitmap: one bit per value and one zero closing each
+/// bucket up to the universe's.
+fn upper_bits_for(len: usize, universe: u64, low_bits: u32) -> Option<usize> {
+    let buckets = usize::try_from(universe >> low_bits).ok()?.checked_add(1)?;
+    len.checked_add(buckets)
+}
+
+fn write_low(lower: &mut [u64], index: usize, low_bits: u32, value: u64) {
+    if low_bits == 0 {
+        return;
+    }
+    let value = value & ((1 << low_bits) - 1);
+    let bit = index * low_bits as usize;
+    let (word, offset) = (bit / 64, bit % 64);
+    lower[word] |= value << offset;
+    if offset + low_bits as usize > 64 {
+        lower[word + 1] |= value >> (64 - offset);
+    }
+}
+
+fn read_low(lower: &[u64], index: usize, low_bits: u32) -> u64 {
+    if low_bits == 0 {
+        return 0;
+    }
+    let bit = index * low_bits as usize;
+    let (word, offset) = (bit / 64, bit % 64);
+    let mut value = lower[word] >> offset;
+    if offset + low_bits as usize > 64 {
+        value |= lower[word + 1] << (64 - offset);
+    }
+    value & ((1 << low_bits) - 1)
+}
+
+/// The word itself when selecting ones, its complement for zeros.
+fn polarity(word: u64, ones: bool) -> u64 {
+    if ones { word } else { !word }
+}
+
+/// Position of the `rank`-th set bit of `word`, which must exist.
+fn select_in_word(mut word: u64, rank: usize) -> usize {
+    for _ in 0..rank {
+        word &= word - 1;
+    }
+    word.trailing_zeros() as usize
+}
+
+/// Positions of every `SELECT_SAMPLE`-th one (or zero) among the first
+/// `total` of them.
+fn sample_positions(words: &[u64], total: usize, ones: bool) -> Vec<usize> {
+    let mut samples = Vec::with_capacity(total.div_ceil(SELECT_SAMPLE));
+    let mut seen = 0;
+    for (word_idx, &word) in words.iter().enumerate() {
+        let word = polarity(word, ones);
+        let count = word.count_ones() as usize;
+        let mut next = samples.len() * SELECT_SAMPLE;
+        while next < total && next < seen + count {
+            samples.push(word_idx * 64 + select_in_word(word, next - seen));
+            next += SELECT_SAMPLE;
+        }
+        seen += count;
+    }
+    samples
+}
+
+/// Positions of the set bits of a bitmap, starting at a given bit.
+struct OnesFrom<'a> {
+    words: &'a [u64],
+    word_idx: usize,
+    word: u64,
+}
+
+impl<'a> OnesFrom<'a> {
+    fn new(words: &'a [u64], start: usize) -> Self {
+        let word_idx = start / 64;
+        let word = words
+            .get(word_idx)
+            .map_or(0, |&w| w & (u64::MAX << (start % 64)));
+        OnesFrom {
+            words,
+            word_idx,
+            word,
+        }
+    }
+}
+
+impl Iterator for OnesFrom<'_> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        while self.word == 0 {
+            self.word_idx += 1;
+            self.word = *self.words.get(self.word_idx)?;
+        }
+        let bit = self.word.trailing_zeros() as usize;
+        self.word &= self.word - 1;
+        Some(self.word_idx * 64 + bit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::Rng;
+
+    use super::*;
+
+    fn random_sorted(len: usize, max_gap: u32) -> Vec<u32> {
+        let mut rng = rand::thread_rng();
+        let mut value = 0u32;
+        (0..len)
+            .map(|_| {
+                value += rng.gen_range(0..=max_gap);
+                value
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_get_and_iter() {
+        for (len, max_gap) in [(1, 0), (10, 3), (1000, 1), (5000, 40), (3000, 100_000)] {
+            let values = random_sorted(len, max_gap);
+            let ef = EliasFano::new(&values).unwrap();
+            assert_eq!(ef.len(), len);
+            for (i, &value) in values.iter().enumerate() {
+                assert_eq!(ef.get(i), Some(value));
+            }
+            assert_eq!(ef.get(len), None);
+            assert_eq!(ef.iter().collect::<Vec<_>>(), values);
+        }
+    }
+
+    #[test]
+    fn test_next_geq_matches_linear_search() {
+        let values = random_sorted(4000, 20);
+        let ef = EliasFano::new(&values).unwrap();
+        let last = *values.last().unwrap();
+        for target in (0..=last + 2).step_by(3) {
+            let index = values.partition_point(|&v| v < target);
+            let expected = values.get(index).map(|&v| (index, v));
+            assert_eq!(ef.next_geq(target), expected, "target {target}");
+        }
+    }
+
+    #[test]
+    fn test_duplicates_and_dense_runs() {
+        let mut values = vec![7u32; 600];
+        values.extend(700..1500);
+        values.extend([1_000_000; 3]);
+        let ef = EliasFano::new(&values).unwrap();
+        assert_eq!(ef.iter().collect::<Vec<_>>(), values);
+        assert_eq!(ef.next_geq(0), Some((0, 7)));
+        assert_eq!(ef.next_geq(8), Some((600, 700)));
+        assert_eq!(ef.next_geq(1500), Some((1400, 1_000_000)));
+        assert_eq!(ef.next_geq(1_000_001), None);
+    }
+
+    #[test]
+    fn test_space_bound() {
+        // At most 2 + ceil(log2(u / n)) bits per value, plus word padding
+        let values: Vec<u32> = (0..10_000).map(|i| i * 1000).collect();
+        let ef = EliasFano::new(&values).unwrap();
+        assert_eq!(ef.low_bits(), 9);
+        assert!(ef.size_in_bytes() * 8 <= values.len() * (2 + 10) + 128);
+    }
+
+    #[test]
+    fn test_u64_extremes() {
+        let values = vec![0, 1, 1 << 40, u64::MAX - 1, u64::MAX];
+        let ef = EliasFano::new(&values).unwrap();
+        assert_eq!(ef.iter().collect::<Vec<_>>(), values);
+        assert_eq!(ef.next_geq(2), Some((2, 1 << 40)));
+        assert_eq!(ef.next_geq(u64::MAX), Some((4, u64::MAX)));
+
+        let empty = EliasFano::<u64>::new(&[]).unwrap();
+        assert!(empty.is_empty());
+        assert_eq!(empty.get(0), None);
+        assert_eq!(empty.next_geq(0), None);
+    }
+
+    #[test]
+    fn test_rejects_unsorted() {
+        assert_eq!(
+            EliasFano::new(&[1u32, 5, 4, 8]).unwrap_err(),
+            EncodeError::Unsorted { index: 2 }
+        );
+    }
+
+    #[test]
+    fn test_bytes_roundtrip() {
+        let values = random_sorted(3000, 50);
+        let ef = EliasFano::new(&values).unwrap();
+        let bytes = ef.to_bytes();
+        let loaded = EliasFano::<u32>::from_bytes(&bytes).unwrap();
+        assert_eq!(loaded.iter().collect::<Vec<_>>(), values);
+        assert_eq!(loaded.next_geq(values[1234]), ef.next_geq(values[1234]));
+
+        let empty = EliasFano::<u32>::new(&[]).unwrap().to_bytes();
+        assert!(EliasFano::<u32>::from_bytes(&empty).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_from_bytes_rejects_malformed_input() {
+        let values: Vec<u32> = (0..100).map(|i| i * 7).collect();
+        let bytes = EliasFano::new(&values).unwrap().to_bytes();
+
+        assert_eq!(
+            EliasFano::<u32>::from_bytes(&bytes[..10]).unwrap_err(),
+            DecodeError::Truncated
+        );
+        assert_eq!(
+            EliasFano::<u32>::from_bytes(&bytes[..bytes.len() - 8]).unwrap_err(),
+            DecodeError::Truncated
+        );
+
+        let mut extra = bytes.clone();
+        extra.extend_from_slice(&[0; 8]);
+        assert_eq!(
+            EliasFano::<u32>::from_bytes(&extra).unwrap_err(),
+            DecodeError::TrailingBytes
+        );
+
+        // A stray one in the upper bitmap
+        let mut flipped = bytes.clone();
+        let last = flipped.len() - 1;
+        flipped[last] ^= 0x80;
+        assert_eq!(
+            EliasFano::<u32>::from_bytes(&flipped).unwrap_err(),
+            DecodeError::InvalidHeader
+        );
+
+        // A universe that does not fit the value type
+        let wide = EliasFano::new(&[1u64 << 40]).unwrap().to_bytes();
+        assert_eq!(
+            EliasFano::<u32>::from_bytes(&wide).unwrap_err(),
+            DecodeError::Overflow
+        );
+
+        let mut huge_len = bytes.clone();
+        huge_len[..8].copy_from_slice(&u64::MAX.to_le_bytes());
+        assert_eq!(
+            EliasFano::<u32>::from_bytes(&huge_len).unwrap_err(),
+            DecodeError::InvalidHeader
+        );
+    }
+}
@@ -0,0 +1,665 @@
+use std::marker::PhantomData;
+
+use super::{EliasFano, OnesFrom, SortedValue, low_bits_for, select_in_word};
+use crate::error::{DecodeError, EncodeError};
+
+/// Bits charged to every chunk for its header and its entries in the upper
+/// level, which keeps the partition from splitting into tiny chunks.
+const CHUNK_COST_BITS: u64 = 64;
+
+/// Approximation parameters of the partition search: windows costing more
+/// than `CHUNK_COST_BITS / EPS1` are never split further, and the window
+/// cost bounds grow by a factor of `1 + EPS2`.
+const EPS1: f64 = 0.03;
+const EPS2: f64 = 0.3;
+
+/// A dense chunk keeps the number of ones before every block of this many
+/// words.
+const RANK_BLOCK_WORDS: usize = 8;
+
+/// Partitioned Elias-Fano representation of a non-decreasing sequence
+/// (Ottaviano and Venturini, SIGIR 2014).
+///
+/// The sequence is split into chunks, each stored relative to the last
+/// value of the previous chunk in whichever of three encodings is smallest:
+/// a run of consecutive values (no payload), a dense bitmap over the chunk's
+/// range, or plain [`EliasFano`]. The last value and the end position of
+/// every chunk form the upper level, itself two Elias-Fano sequences.
+///
+/// Chunk boundaries come from the approximate dynamic program of the paper,
+/// which finds a partition within a factor `(1 + EPS1)(1 + EPS2)` of the
+/// smallest one in linear time.
+#[derive(Debug, Clone)]
+pub struct PartitionedEliasFano<T: SortedValue> {
+    chunks: Vec<Chunk>,
+    /// Last value of every chunk.
+    maxes: EliasFano<u64>,
+    /// Position one past the last value of every chunk.
+    ends: EliasFano<u64>,
+    len: usize,
+    _marker: PhantomData<T>,
+}
+
+#[derive(Debug, Clone)]
+enum Chunk {
+    /// The values are consecutive and end at the chunk's maximum.
+    Run,
+    /// One bit per value of the chunk's range, relative to its base.
+    Dense(Bitmap),
+    /// Values relative to the chunk's base.
+    Sparse(EliasFano<u64>),
+}
+
+/// Position and value range of a chunk.
+struct Bounds {
+    start: usize,
+    len: usize,
+    /// Last value of the previous chunk, or 0 for the first chunk.
+    base: u64,
+    max: u64,
+}
+
+impl<T: SortedValue> PartitionedEliasFano<T> {
+    /// Encodes a non-decreasing sequence.
+    ///
+    /// Fails with [`EncodeError::Unsorted`] at the first value smaller than
+    /// its predecessor.
+    pub fn new(values: &[T]) -> Result<Self, EncodeError> {
+        if let Some(index) = values.windows(2).position(|pair| pair[1] < pair[0]) {
+            return Err(EncodeError::Unsorted { index: index + 1 });
+        }
+        let values: Vec<u64> = values.iter().map(|&v| v.to_u64()).collect();
+        let costs = ChunkCosts::new(&values);
+
+        let mut chunks = Vec::new();
+        let mut maxes = Vec::new();
+        let mut start = 0;
+        let ends = optimal_partition(&costs);
+        for &end in &ends {
+            let base = if start == 0 { 0 } else { values[start - 1] };
+            let relative: Vec<u64> = values[start..end].iter().map(|&v| v - base).collect();
+            chunks.push(match costs.best(start, end).0 {
+                ChunkKind::Run => Chunk::Run,
+                ChunkKind::Dense => Chunk::Dense(Bitmap::from_values(&relative)),
+                ChunkKind::Sparse => Chunk::Sparse(EliasFano::new(&relative).unwrap()),
+            });
+            maxes.push(values[end - 1]);
+            start = end;
+        }
+
+        let ends: Vec<u64> = ends.iter().map(|&end| end as u64).collect();
+        Ok(PartitionedEliasFano {
+            chunks,
+            maxes: EliasFano::new(&maxes).unwrap(),
+            ends: EliasFano::new(&ends).unwrap(),
+            len: values.len(),
+            _marker: PhantomData,
+        })
+    }
+
+    /// Number of values in the sequence.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Number of chunks the sequence was split into.
+    pub fn num_chunks(&self) -> usize {
+        self.chunks.len()
+    }
+
+    /// Returns the `index`-th value, or `None` if it is out of bounds.
+    pub fn get(&self, index: usize) -> Option<T> {
+        if index >= self.len {
+            return None;
+        }
+        let (chunk, _) = self.ends.next_geq(index as u64 + 1).unwrap();
+        let bounds = self.bounds(chunk);
+        Some(T::from_u64(self.value(
+            chunk,
+            &bounds,
+            index - bounds.start,
+        )))
+    }
+
+    /// Returns the position and value of the first value greater than or
+    /// equal to `target`, or `None` if every value is smaller.
+    ///
+    /// The upper level finds the only chunk that can hold the answer, so
+    /// just one chunk is searched.
+    pub fn next_geq(&self, target: T) -> Option<(usize, T)> {
+        let target = target.to_u64();
+        let (chunk, _) = self.maxes.next_geq(target)?;
+        let bounds = self.bounds(chunk);
+        let relative = target.saturating_sub(bounds.base);
+
+        let (local, value) = match &self.chunks[chunk] {
+            Chunk::Run => {
+                let first = bounds.max - (bounds.len as u64 - 1);
+                let local = target.saturating_sub(first);
+                (local as usize, first + local)
+            }
+            Chunk::Dense(bitmap) => {
+                let bit = OnesFrom::new(&bitmap.words, relative as usize)
+                    .next()
+                    .unwrap();
+                (bitmap.rank(bit), bounds.base + bit as u64)
+            }
+            Chunk::Sparse(ef) => {
+                let (local, value) = ef.next_geq(relative).unwrap();
+                (local, bounds.base + value)
+            }
+        };
+        Some((bounds.start + local, T::from_u64(value)))
+    }
+
+    /// Iterates over the values in order.
+    pub fn iter(&self) -> impl Iterator<Item = T> + '_ {
+        (0..self.chunks.len()).flat_map(move |chunk| {
+            let bounds = self.bounds(chunk);
+            (0..bounds.len).map(move |local| T::from_u64(self.value(chunk, &bounds, local)))
+        })
+    }
+
+    /// Serializes the sequence: the length and the number of chunks (u64 LE
+    /// each), the two upper-level sequences, then each chunk as a kind byte
+    /// (0 run, 1 dense, 2 sparse) and its payload. Nested Elias-Fano
+    /// sequences are prefixed by their size in bytes (u64 LE); dense bitmaps
+    /// are sized by their chunk's range.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(&(self.len as u64).to_le_bytes());
+        bytes.extend_from_slice(&(self.chunks.len() as u64).to_le_bytes());
+        write_nested(&mut bytes, &self.maxes);
+        write_nested(&mut bytes, &self.ends);
+        for chunk in &self.chunks {
+            match chunk {
+                Chunk::Run => bytes.push(0),
+                Chunk::Dense(bitmap) => {
+                    bytes.push(1);
+                    for word in &bitmap.words {
+                        bytes.extend_from_slice(&word.to_le_bytes());
+                    }
+                }
+                Chunk::Sparse(ef) => {
+                    bytes.push(2);
+                    write_nested(&mut bytes, ef);
+                }
+            }
+        }
+        bytes
+    }
+
+    /// Loads a sequence written by [`to_bytes`](PartitionedEliasFano::to_bytes).
+    ///
+    /// Every chunk is checked against the upper level, so a sequence that
+    /// loads successfully answers every query without panicking.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let mut input = Input { bytes, pos: 0 };
+        let len = input.u64()?;
+        let num_chunks = input.u64()?;
+        // Every chunk takes at least its kind byte
+        if num_chunks > bytes.len() as u64 || len > usize::MAX as u64 {
+            return Err(DecodeError::InvalidHeader);
+        }
+        let (len, num_chunks) = (len as usize, num_chunks as usize);
+
+        let maxes = EliasFano::<u64>::from_bytes(input.nested()?)?;
+        let ends = EliasFano::<u64>::from_bytes(input.nested()?)?;
+        let ends_valid = ends
+            .iter()
+            .try_fold(0, |prev, end| (end > prev).then_some(end));
+        if maxes.len() != num_chunks
+            || ends.len() != num_chunks
+            || ends_valid.unwrap_or(u64::MAX) != len as u64
+        {
+            return Err(DecodeError::InvalidHeader);
+        }
+        if !T::fits(maxes.universe) {
+            return Err(DecodeError::Overflow);
+        }
+
+        let mut sequence = PartitionedEliasFano {
+            chunks: Vec::with_capacity(num_chunks),
+            maxes,
+            ends,
+            len,
+            _marker: PhantomData,
+        };
+        for chunk in 0..num_chunks {
+            let bounds = sequence.bounds(chunk);
+            let universe = bounds.max - bounds.base;
+            let valid_chunk = match input.u8()? {
+                0 => (universe >= bounds.len as u64 - 1).then_some(Chunk::Run),
+                1 => {
+                    let num_words = universe / 64 + 1;
+                    if num_words > (bytes.len() / 8) as u64 {
+                        return Err(DecodeError::Truncated);
+                    }
+                    let words = (0..num_words)
+                        .map(|_| input.u64())
+                        .collect::<Result<_, _>>()?;
+                    let bitmap = Bitmap::new(words);
+                    bitmap
+                        .is_valid(bounds.len, universe)
+                        .then_some(Chunk::Dense(bitmap))
+                }
+                2 => {
+                    let ef = EliasFano::<u64>::from_bytes(input.nested()?)?;
+                    (ef.len() == bounds.len && ef.universe == universe).then_some(Chunk::Sparse(ef))
+                }
+                _ => None,
+            };
+            sequence
+                .chunks
+                .push(valid_chunk.ok_or(DecodeError::InvalidHeader)?);
+        }
+
+        if input.pos != bytes.len() {
+            return Err(DecodeError::TrailingBytes);
+        }
+        Ok(sequence)
+    }
+
+    fn bounds(&self, chunk: usize) -> Bounds {
+        let (start, base) = match chunk {
+            0 => (0, 0),
+            _ => (
+                self.ends.get(chunk - 1).unwrap() as usize,
+                self.maxes.get(chunk - 1).unwrap(),
+            ),
+        };
+        Bounds {
+            start,
+            len: self.ends.get(chunk).unwrap() as usize - start,
+            base,
+            max: self.maxes.get(chunk).unwrap(),
+        }
+    }
+
+    /// The `local`-th value of `chunk`.
+    fn value(&self, chunk: usize, bounds: &Bounds, local: usize) -> u64 {
+        match &self.chunks[chunk] {
+            Chunk::Run => bounds.max - (bounds.len - 1 - local) as u64,
+            Chunk::Dense(bitmap) => bounds.base + bitmap.select(local) as u64,
+            Chunk::Sparse(ef) => bounds.base + ef.get(local).unwrap(),
+        }
+    }
+}
+
+/// Bit set with sampled ranks, for dense chunks.
+#[derive(Debug, Clone)]
+struct Bitmap {
+    words: Vec<u64>,
+    /// Ones before every block of `RANK_BLOCK_WORDS` words.
+    ranks: Vec<usize>,
+}
+
+impl Bitmap {
+    fn new(words: Vec<u64>) -> Self {
+        let ranks = words
+            .chunks(RANK_BLOCK_WORDS)
+            .scan(0, |ones, block| {
+                let before = *ones;
+                *ones += block.iter().map(|w| w.count_ones() as usize).sum::<usize>();
+                Some(before)
+            })
+            .collect();
+        Bitmap { words, ranks }
+    }
+
+    /// Bitmap of strictly increasing `values`.
+    fn from_values(values: &[u64]) -> Self {
+        let mut words = vec![0u64; (*values.last().unwrap() / 64 + 1) as usize];
+        for &value in values {
+            words[value as usize / 64] |= 1 << (value % 64);
+        }
+        Self::new(words)
+    }
+
+    /// Whether the bitmap holds `len` values ending at `universe`.
+    fn is_valid(&self, len: usize, universe: u64) -> bool {
+        let ones: usize = self.words.iter().map(|w| w.count_ones() as usize).sum();
+        let last = self.words[self.words.len() - 1];
+        ones == len && 63 - last.leading_zeros() as u64 == universe % 64 && last != 0
+    }
+
+    /// Position of the `rank`-th one, which must exist.
+    fn select(&self, rank: usize) -> usize {
+        let block = self.ranks.partition_point(|&before| before <= rank) - 1;
+        let mut remaining = rank - self.ranks[block];
+        for (word_idx, &word) in self.words.iter().enumerate().skip(block * RANK_BLOCK_WORDS) {
+            let count = word.count_ones() as usize;
+            if remaining < count {
+                return word_idx * 64 + select_in_word(word, remaining);
+            }
+            remaining -= count;
+        }
+        unreachable!("rank is below the number of ones")
+    }
+
+    /// Number of ones before position `bit`.
+    fn rank(&self, bit: usize) -> usize {
+        let (word_idx, offset) = (bit / 64, bit % 64);
+        let block_start = word_idx / RANK_BLOCK_WORDS * RANK_BLOCK_WORDS;
+        let full: usize = self.words[block_start..word_idx]
+            .iter()
+            .map(|w| w.count_ones() as usize)
+            .sum();
+        let partial = (self.words[word_idx] & ((1 << offset) - 1)).count_ones() as usize;
+        self.ranks[word_idx / RANK_BLOCK_WORDS] + full + partial
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ChunkKind {
+    Run,
+    Dense,
+    Sparse,
+}
+
+/// Size of any candidate chunk in constant time.
+struct ChunkCosts<'a> {
+    values: &'a [u64],
+    /// `repeats[i]` counts the positions `j < i` holding the same value as
+    /// position `j - 1`, so a range has no repeated values when the count
+    /// does not change inside it.
+    repeats: Vec<usize>,
+}
+
+impl<'a> ChunkCosts<'a> {
+    fn new(values: &'a [u64]) -> Self {
+        let mut repeats = vec![0; values.len() + 1];
+        for i in 1..values.len() {
+            repeats[i + 1] = repeats[i] + (values[i] == values[i - 1]) as usize;
+        }
+        ChunkCosts { values, repeats }
+    }
+
+    /// Smallest encoding of `values[start..end]` and its size in bits,
+    /// including `CHUNK_COST_BITS`.
+    fn best(&self, start: usize, end: usize) -> (ChunkKind, u64) {
+        let len = (end - start) as u64;
+        let base = if start == 0 {
+            0
+        } else {
+            self.values[start - 1]
+        };
+        let universe = self.values[end - 1] - base;
+        let distinct = self.repeats[end] == self.repeats[start + 1];
+
+        if distinct && self.values[end - 1] - self.values[start] == len - 1 {
+            return (ChunkKind::Run, CHUNK_COST_BITS);
+        }
+        let low_bits = low_bits_for(len as usize, universe) as u64;
+        let sparse = (len * (low_bits + 1))
+            .saturating_add(universe >> low_bits)
+            .saturating_add(1);
+        let dense = (universe / 64 + 1).saturating_mul(64);
+        if distinct && dense <= sparse {
+            (ChunkKind::Dense, CHUNK_COST_BITS + dense)
+        } else {
+            (ChunkKind::Sparse, CHUNK_COST_BITS.saturating_add(sparse))
+        }
+    }
+
+    fn cost(&self, start: usize, end: usize) -> u64 {
+        self.best(start, end).1
+    }
+}
+
+/// End positions of the chunks of an approximately smallest partition.
+///
+/// Shortest path over the DAG whose edges are candidate chunks, pruned as
+/// in the paper: from every start position, only the longest chunk whose
+/// cost stays under each of a geometric series of bounds is followed, plus
+/// every shorter chunk met while a window grows. The windows only move
+/// forward, so the whole search takes `O(n log(1 / EPS1) / EPS2)` time.
+fn optimal_partition(costs: &ChunkCosts) -> Vec<usize> {
+    let n = costs.values.len();
+    if n == 0 {
+        return Vec::new();
+    }
+    let whole = costs.cost(0, n);
+    let mut min_cost = vec![u64::MAX; n + 1];
+    let mut previous = vec![0; n + 1];
+    min_cost[0] = 0;
+    min_cost[n] = whole;
+
+    let mut bounds = Vec::new();
+    let mut bound = CHUNK_COST_BITS as f64;
+    while bound < CHUNK_COST_BITS as f64 / EPS1 {
+        bounds.push(bound as u64);
+        if bound >= whole as f64 {
+            break;
+        }
+        bound *= 1.0 + EPS2;
+    }
+
+    let mut window_ends = vec![0; bounds.len()];
+    for start in 0..n {
+        let mut last_end = start + 1;
+        for (end, &bound) in window_ends.iter_mut().zip(&bounds) {
+            *end = (*end).max(last_end);
+            loop {
+                let chunk_cost = costs.cost(start, *end);
+                let cost = min_cost[start].saturating_add(chunk_cost);
+                if cost < min_cost[*end] {
+                    min_cost[*end] = cost;
+                    previous[*end] = start;
+                }
+                last_end = *end;
+                if *end == n || chunk_cost >= bound {
+                    break;
+                }
+                *end += 1;
+            }
+        }
+    }
+
+    let mut ends = Vec::new();
+    let mut end = n;
+    while end > 0 {
+        ends.push(end);
+        end = previous[end];
+    }
+    ends.reverse();
+    ends
+}
+
+fn write_nested(bytes: &mut Vec<u8>, ef: &EliasFano<u64>) {
+    let nested = ef.to_bytes();
+    bytes.extend_from_slice(&(nested.len() as u64).to_le_bytes());
+    bytes.extend_from_slice(&nested);
+}
+
+/// Cursor over the serialized bytes.
+struct Input<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Input<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], DecodeError> {
+        let end = self.pos.checked_add(len).ok_or(DecodeError::Truncated)?;
+        let taken = self
+            .bytes
+            .get(self.pos..end)
+            .ok_or(DecodeError::Truncated)?;
+        self.pos = end;
+        Ok(taken)
+    }
+
+    fn u8(&mut self) -> Result<u8, DecodeError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u64(&mut self) -> Result<u64, DecodeError> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    /// A size-prefixed nested sequence.
+    fn nested(&mut self) -> Result<&'a [u8], DecodeError> {
+        let len = self.u64()?;
+        self.take(usize::try_from(len).map_err(|_| DecodeError::Truncated)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::Rng;
+
+    use super::*;
+
+    /// Dense stretches, consecutive runs and long gaps, so every chunk kind
+    /// shows up.
+    fn clustered(len: usize) -> Vec<u32> {
+        let mut rng = rand::thread_rng();
+        let mut value = 0u32;
+        (0..len)
+            .map(|i| {
+                value += match (i / 500) % 3 {
+                    0 => 1,
+                    1 => rng.gen_range(1..4),
+                    _ => rng.gen_range(1..5000),
+                };
+                value
+            })
+            .collect()
+    }
+
+    fn kinds<T: SortedValue>(pef: &PartitionedEliasFano<T>) -> [usize; 3] {
+        let mut counts = [0; 3];
+        for chunk in &pef.chunks {
+            counts[match chunk {
+                Chunk::Run => 0,
+                Chunk::Dense(_) => 1,
+                Chunk::Sparse(_) => 2,
+            }] += 1;
+        }
+        counts
+    }
+
+    #[test]
+    fn test_get_and_iter() {
+        let values = clustered(6000);
+        let pef = PartitionedEliasFano::new(&values).unwrap();
+        assert_eq!(pef.len(), values.len());
+        assert_eq!(kinds(&pef).map(|count| count > 0), [true; 3]);
+        for (i, &value) in values.iter().enumerate() {
+            assert_eq!(pef.get(i), Some(value), "index {i}");
+        }
+        assert_eq!(pef.get(values.len()), None);
+        assert_eq!(pef.iter().collect::<Vec<_>>(), values);
+    }
+
+    #[test]
+    fn test_next_geq_matches_linear_search() {
+        let values = clustered(3000);
+        let pef = PartitionedEliasFano::new(&values).unwrap();
+        let last = *values.last().unwrap();
+        for target in (0..=last + 2).step_by(7) {
+            let index = values.partition_point(|&v| v < target);
+            let expected = values.get(index).map(|&v| (index, v));
+            assert_eq!(pef.next_geq(target), expected, "target {target}");
+        }
+    }
+
+    #[test]
+    fn test_smaller_than_single_elias_fano() {
+        let values = clustered(30_000);
+        let pef = PartitionedEliasFano::new(&values).unwrap();
+        let ef = EliasFano::new(&values).unwrap();
+        assert!(pef.to_bytes().len() < ef.to_bytes().len() * 3 / 4);
+    }
+
+    #[test]
+    fn test_partition_close_to_single_chunk_costs() {
+        // A uniform list gains nothing from splitting, and the search must
+        // not make it noticeably worse than one chunk
+        let values: Vec<u64> = (0..20_000).map(|i| i * 37).collect();
+        let costs = ChunkCosts::new(&values);
+        let ends = optimal_partition(&costs);
+        let mut start = 0;
+        let total: u64 = ends
+            .iter()
+            .map(|&end| {
+                let cost = costs.cost(start, end);
+                start = end;
+                cost
+            })
+            .sum();
+        assert!(total <= costs.cost(0, values.len()));
+    }
+
+    #[test]
+    fn test_duplicates_and_extremes() {
+        let mut values = vec![0u64, 0, 5, 5, 5, 6, 7, 8, 9];
+        values.extend(1000..1200);
+        values.extend([u64::MAX - 1, u64::MAX, u64::MAX]);
+        let pef = PartitionedEliasFano::new(&values).unwrap();
+        assert_eq!(pef.iter().collect::<Vec<_>>(), values);
+        assert_eq!(pef.next_geq(1), Some((2, 5)));
+        assert_eq!(pef.next_geq(1200), Some((209, u64::MAX - 1)));
+        assert_eq!(pef.next_geq(u64::MAX), Some((210, u64::MAX)));
+
+        let empty = PartitionedEliasFano::<u32>::new(&[]).unwrap();
+        assert!(empty.is_empty());
+        assert_eq!(empty.get(0), None);
+        assert_eq!(empty.next_geq(0), None);
+        assert_eq!(
+            PartitionedEliasFano::new(&[3u32, 2]).unwrap_err(),
+            EncodeError::Unsorted { index: 1 }
+        );
+    }
+
+    #[test]
+    fn test_bytes_roundtrip() {
+        let values = clustered(5000);
+        let pef = PartitionedEliasFano::new(&values).unwrap();
+        let loaded = PartitionedEliasFano::<u32>::from_bytes(&pef.to_bytes()).unwrap();
+        assert_eq!(loaded.num_chunks(), pef.num_chunks());
+        assert_eq!(loaded.iter().collect::<Vec<_>>(), values);
+        assert_eq!(loaded.next_geq(values[4321]), pef.next_geq(values[4321]));
+
+        let empty = PartitionedEliasFano::<u32>::new(&[]).unwrap().to_bytes();
+        assert!(
+            PartitionedEliasFano::<u32>::from_bytes(&empty)
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_from_bytes_rejects_malformed_input() {
+        let values = clustered(2000);
+        let bytes = PartitionedEliasFano::new(&values).unwrap().to_bytes();
+
+        for cut in [4, 20, bytes.len() / 2, bytes.len() - 1] {
+            assert!(PartitionedEliasFano::<u32>::from_bytes(&bytes[..cut]).is_err());
+        }
+
+        let mut extra = bytes.clone();
+        extra.push(0);
+        assert_eq!(
+            PartitionedEliasFano::<u32>::from_bytes(&extra).unwrap_err(),
+            DecodeError::TrailingBytes
+        );
+
+        let mut wrong_len = bytes.clone();
+        wrong_len[0] ^= 1;
+        assert_eq!(
+            PartitionedEliasFano::<u32>::from_bytes(&wrong_len).unwrap_err(),
+            DecodeError::InvalidHeader
+        );
+
+        let wide = PartitionedEliasFano::new(&[1u64 << 40]).unwrap().to_bytes();
+        assert_eq!(
+            PartitionedEliasFano::<u32>::from_bytes(&wide).unwrap_err(),
+            DecodeError::Overflow
+        );
+    }
+}
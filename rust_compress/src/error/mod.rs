@@ -0,0 +1,65 @@
+use std::fmt;
+
+/// Reasons a decoder can reject its input.
+///
+/// Decoders never panic on malformed bytes; they report the first problem
+/// they find instead.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecodeError {
+    /// The input ended in the middle of a header, word or value.
+    Truncated,
+    /// A word carries a selector the codec does not define.
+    InvalidSelector(u8),
+    /// A header field holds a value the encoder never writes.
+    InvalidHeader,
+    /// Bytes are left over after the last encoded value.
+    TrailingBytes,
+    /// A decoded value does not fit in the output integer type.
+    Overflow,
+    /// The caller-provided output buffer cannot hold every decoded value.
+    OutputTooSmall,
+    /// A value is encoded with more bytes than its shortest encoding.
+    NonCanonical,
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::Truncated => write!(f, "input is truncated"),
+            DecodeError::InvalidSelector(selector) => write!(f, "invalid selector {selector}"),
+            DecodeError::InvalidHeader => write!(f, "invalid header"),
+            DecodeError::TrailingBytes => write!(f, "trailing bytes after encoded values"),
+            DecodeError::Overflow => write!(f, "decoded value overflows the output type"),
+            DecodeError::OutputTooSmall => write!(f, "output buffer is too small"),
+            DecodeError::NonCanonical => write!(f, "value is not in its shortest encoding"),
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+/// Reasons an encoder can reject its input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EncodeError {
+    /// `value`, found at position `index`, exceeds the largest value the
+    /// codec can store.
+    ValueTooLarge { index: usize, value: u64 },
+    /// The value at `index` is smaller than the one before it (or than the
+    /// base, for the first value) in an input that must be sorted.
+    Unsorted { index: usize },
+}
+
+impl fmt::Display for EncodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EncodeError::ValueTooLarge { index, value } => {
+                write!(f, "value {value} at index {index} is too large to encode")
+            }
+            EncodeError::Unsorted { index } => {
+                write!(f, "value at index {index} breaks the sorted order")
+            }
+        }
+    }
+}
+
+impl std::error::Error for EncodeError {}
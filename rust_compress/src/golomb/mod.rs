@@ -0,0 +1,328 @@
+use crate::bit_io::{BitReader, BitWriter};
+use crate::codec::{IntegerCodec, count_field};
+use crate::error::DecodeError;
+use crate::rice::ESCAPE_QUOTIENT;
+use crate::stream;
+
+/// Golomb divisor minimising the expected code length for `numbers`,
+/// treating them as geometrically distributed with their mean.
+///
+/// For a geometric distribution with success probability `p`, the optimal
+/// divisor is the smallest `b` with `(1 - p)^b + (1 - p)^(b + 1) <= 1`,
+/// i.e. `b = ceil(log(2 - p) / -log(1 - p))`, where `p = 1 / (mean + 1)`.
+pub fn optimal_divisor(numbers: &[u32]) -> u32 {
+    if numbers.is_empty() {
+        return 1;
+    }
+    let sum: u64 = numbers.iter().map(|&n| n as u64).sum();
+    let mean = sum as f64 / numbers.len() as f64;
+    if mean == 0.0 {
+        return 1;
+    }
+    let p = 1.0 / (mean + 1.0);
+    let b = ((2.0 - p).ln() / -(1.0 - p).ln()).ceil();
+    b.clamp(1.0, u32::MAX as f64) as u32
+}
+
+/// Compresses u32 numbers using Golomb coding with divisor `b`.
+///
+/// Each value `n` is written as `n / b` in unary followed by `n % b` in
+/// truncated binary, so divisors that are not powers of two cost no more
+/// than `ceil(log2(b))` remainder bits. Quotients of [`ESCAPE_QUOTIENT`] or
+/// more are escaped as in Rice coding: a unary [`ESCAPE_QUOTIENT`], then the
+/// value in 32 bits. With `b = 2^k` the value bits are therefore identical
+/// to [`rice::compress`](crate::rice::compress).
+///
+/// The header is the Rice header, the 5-bit `k = floor(log2(b))` and the
+/// value count in 32 bits, MSB first, followed by the low k bits of `b`.
+/// `None` picks the divisor with [`optimal_divisor`].
+///
+/// # Panics
+/// Panics if `b` is `Some(0)`.
+pub fn compress(numbers: &[u32], b: Option<u32>) -> Vec<u8> {
+    let b = b.unwrap_or_else(|| optimal_divisor(numbers));
+    assert!(b > 0, "Golomb divisor must be at least 1");
+
+    let k = b.ilog2();
+    let mut writer = BitWriter::new();
+    writer.write_bits(k as u64, 5);
+    writer.write_bits(count_field(numbers.len()) as u64, 32);
+    writer.write_bits(b as u64, k);
+
+    for &n in numbers {
+        let q = n / b;
+        if q < ESCAPE_QUOTIENT {
+            writer.write_unary(q as u64);
+            writer.write_truncated((n % b) as u64, b as u64);
+        } else {
+            writer.write_unary(ESCAPE_QUOTIENT as u64);
+            writer.write_bits(n as u64, 32);
+        }
+    }
+
+    writer.finish()
+}
+
+/// Decompresses Golomb-coded data produced by [`compress`].
+pub fn decompress(encoded: &[u8]) -> Result<Vec<u32>, DecodeError> {
+    let mut numbers = Vec::new();
+    decode_append(encoded, &mut numbers)?;
+    Ok(numbers)
+}
+
+/// Decodes a stream produced by [`compress`], appending the values to
+/// `output`. Returns the number of values appended.
+pub fn decode_append(encoded: &[u8], output: &mut Vec<u32>) -> Result<usize, DecodeError> {
+    let mut reader = BitReader::new(encoded);
+    let (b, count) = read_header(&mut reader)?;
+
+    // Every value takes at least one bit, so a corrupt count cannot make us
+    // reserve more than the input could possibly hold
+    output.reserve(count.min(encoded.len() * 8));
+    for _ in 0..count {
+        output.push(read_value(&mut reader, b)?);
+    }
+
+    check_consumed(&reader, encoded)?;
+    Ok(count)
+}
+
+/// Decodes a stream produced by [`compress`] into `output` without
+/// allocating, and returns the number of values written. Fails with
+/// [`DecodeError::OutputTooSmall`] if the header announces more values than
+/// `output` can hold.
+pub fn decode_into(encoded: &[u8], output: &mut [u32]) -> Result<usize, DecodeError> {
+    let mut reader = BitReader::new(encoded);
+    let (b, count) = read_header(&mut reader)?;
+
+    let output = output.get_mut(..count).ok_or(DecodeError::OutputTooSmall)?;
+    for n in output {
+        *n = read_value(&mut reader, b)?;
+    }
+
+    check_consumed(&reader, encoded)?;
+    Ok(count)
+}
+
+/// Reads the divisor and the number of values.
+fn read_header(reader: &mut BitReader) -> Result<(u32, usize), DecodeError> {
+    let k = reader.read_bits(5).ok_or(DecodeError::Truncated)? as u32;
+    let count = reader.read_bits(32).ok_or(DecodeError::Truncated)? as usize;
+    let low = reader.read_bits(k).ok_or(DecodeError::Truncated)? as u32;
+    Ok((1 << k | low, count))
+}
+
+/// Reads one Golomb-coded value with divisor `b`.
+fn read_value(reader: &mut BitReader, b: u32) -> Result<u32, DecodeError> {
+    let q = reader.read_unary().ok_or(DecodeError::Truncated)?;
+    if q == ESCAPE_QUOTIENT as u64 {
+        let n = reader.read_bits(32).ok_or(DecodeError::Truncated)? as u32;
+        if n / b < ESCAPE_QUOTIENT {
+            return Err(DecodeError::NonCanonical);
+        }
+        return Ok(n);
+    }
+    // Longer prefixes are never written
+    if q > ESCAPE_QUOTIENT as u64 {
+        return Err(DecodeError::Overflow);
+    }
+    let r = reader.read_truncated(b as u64).ok_or(DecodeError::Truncated)?;
+    q.checked_mul(b as u64)
+        .and_then(|n| n.checked_add(r))
+        .and_then(|n| u32::try_from(n).ok())
+        .ok_or(DecodeError::Overflow)
+}
+
+/// Rejects input left over after the last value.
+fn check_consumed(reader: &BitReader, encoded: &[u8]) -> Result<(), DecodeError> {
+    if reader.bytes_consumed() != encoded.len() {
+        return Err(DecodeError::TrailingBytes);
+    }
+    Ok(())
+}
+
+/// Golomb coding as an [`IntegerCodec`]. `b` is the divisor passed to
+/// [`compress`]; `None` derives it from the input.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Golomb {
+    pub b: Option<u32>,
+}
+
+impl IntegerCodec for Golomb {
+    type Value = u32;
+
+    fn encode(&self, input: &[u32], output: &mut Vec<u8>) {
+        output.extend_from_slice(&compress(input, self.b));
+    }
+
+    fn decode(&self, input: &[u8], output: &mut Vec<u32>) -> Result<usize, DecodeError> {
+        decode_append(input, output)
+    }
+}
+
+/// Streaming Golomb encoder over a [`std::io::Write`], see [`stream::Encoder`].
+pub type Encoder<W> = stream::Encoder<W, Golomb>;
+
+/// Streaming Golomb decoder over a [`std::io::Read`], see [`stream::Decoder`].
+pub type Decoder<R> = stream::Decoder<R, Golomb>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rice;
+
+    #[test]
+    fn test_truncated_binary_remainders() {
+        // b = 5: remainders 0..3 take 2 bits, 3 and 4 take 3 bits
+        let encoded = compress(&[0, 2, 3, 4, 7], Some(5));
+        let mut reader = BitReader::new(&encoded);
+        assert_eq!(reader.read_bits(5), Some(2));
+        assert_eq!(reader.read_bits(32), Some(5));
+        assert_eq!(reader.read_bits(2), Some(1));
+        let mut codes = vec![];
+        for bits in [3, 3, 4, 4, 4] {
+            codes.push(reader.read_bits(bits).unwrap());
+        }
+        assert_eq!(codes, vec![0b100, 0b110, 0b1110, 0b1111, 0b0110]);
+
+        // b = 1 is plain unary: 1 + 2 + 3 bits after the 37-bit header
+        let encoded = compress(&[0, 1, 2], Some(1));
+        assert_eq!(encoded.len(), 6);
+        let mut reader = BitReader::new(&encoded);
+        reader.read_bits(37).unwrap();
+        assert_eq!(reader.read_bits(6), Some(0b10_1001));
+    }
+
+    #[test]
+    fn test_roundtrip_various_divisors() {
+        let numbers: Vec<u32> = (0..500).map(|i| (i * 7919) % 1000).collect();
+        for b in [1, 2, 3, 5, 7, 10, 64, 100, 1000, 65_537] {
+            let encoded = compress(&numbers, Some(b));
+            assert_eq!(decompress(&encoded).unwrap(), numbers, "b = {b}");
+        }
+    }
+
+    #[test]
+    fn test_power_of_two_matches_rice_payload() {
+        let numbers = vec![0, 5, 17, 100, 3, 64];
+        let golomb = compress(&numbers, Some(8));
+        let rice = rice::compress(&numbers, Some(3));
+
+        // The Rice header, then the three zero low bits of the divisor, then
+        // the same value bits
+        let mut golomb_reader = BitReader::new(&golomb);
+        let mut rice_reader = BitReader::new(&rice);
+        assert_eq!(golomb_reader.read_bits(37), rice_reader.read_bits(37));
+        assert_eq!(golomb_reader.read_bits(3), Some(0));
+        for _ in &numbers {
+            assert_eq!(golomb_reader.read_unary(), rice_reader.read_unary());
+            assert_eq!(golomb_reader.read_bits(3), rice_reader.read_bits(3));
+        }
+    }
+
+    #[test]
+    fn test_optimal_divisor() {
+        assert_eq!(optimal_divisor(&[]), 1);
+        assert_eq!(optimal_divisor(&[0, 0, 0]), 1);
+        // Mean 10: p = 1/11, and b = 7 is where the code lengths balance
+        assert_eq!(optimal_divisor(&[10; 20]), 7);
+
+        // Geometric-looking gaps compress better with the chosen divisor
+        let gaps: Vec<u32> = (0..1000).map(|i| [1, 3, 6, 12, 30][i % 5]).collect();
+        let auto = compress(&gaps, None);
+        assert_eq!(decompress(&auto).unwrap(), gaps);
+        assert!(auto.len() <= compress(&gaps, Some(1)).len());
+        assert!(auto.len() <= compress(&gaps, Some(64)).len());
+    }
+
+    #[test]
+    fn test_large_quotients_are_escaped() {
+        // Without the escape this would be four billion zero bits
+        let encoded = compress(&[u32::MAX], Some(1));
+        assert_eq!(encoded.len(), (37 + 33 + 32usize).div_ceil(8));
+        assert_eq!(decompress(&encoded).unwrap(), vec![u32::MAX]);
+
+        // The escape takes over exactly at quotient 32, as in Rice coding
+        for b in [1, 3, 8, 1000] {
+            let numbers = vec![32 * b - 1, 32 * b, 32 * b + 1, 0];
+            let encoded = compress(&numbers, Some(b));
+            assert_eq!(decompress(&encoded).unwrap(), numbers, "b = {b}");
+        }
+
+        // Escaped values keep the power-of-two payload identical to Rice
+        let numbers = vec![255, 256, 4096, u32::MAX, 7];
+        let golomb = compress(&numbers, Some(8));
+        let rice = rice::compress(&numbers, Some(3));
+        let mut golomb_reader = BitReader::new(&golomb);
+        let mut rice_reader = BitReader::new(&rice);
+        golomb_reader.read_bits(40).unwrap();
+        rice_reader.read_bits(37).unwrap();
+        while let Some(bit) = rice_reader.read_bits(1) {
+            assert_eq!(golomb_reader.read_bits(1), Some(bit));
+        }
+    }
+
+    #[test]
+    fn test_extreme_values() {
+        let numbers = vec![u32::MAX, 0, u32::MAX - 1];
+        for b in [100_003, 1 << 20, 1 << 31, u32::MAX] {
+            let encoded = compress(&numbers, Some(b));
+            assert_eq!(decompress(&encoded).unwrap(), numbers);
+        }
+    }
+
+    #[test]
+    fn test_rejects_malformed_streams() {
+        assert_eq!(decompress(&[]), Err(DecodeError::Truncated));
+
+        let numbers = vec![100, 200, 300, 400];
+        let encoded = compress(&numbers, Some(6));
+        assert_eq!(
+            decompress(&encoded[..encoded.len() - 3]),
+            Err(DecodeError::Truncated)
+        );
+
+        let mut extra = encoded.clone();
+        extra.push(0xFF);
+        assert_eq!(decompress(&extra), Err(DecodeError::TrailingBytes));
+
+        // A quotient of 2 times a divisor of 2^31 does not fit a u32
+        let mut writer = BitWriter::new();
+        writer.write_bits(31, 5);
+        writer.write_bits(1, 32);
+        writer.write_bits(0, 31);
+        writer.write_unary(2);
+        writer.write_bits(0, 31);
+        assert_eq!(decompress(&writer.finish()), Err(DecodeError::Overflow));
+
+        // An escaped value short of the escape quotient, and a prefix
+        // longer than the escape
+        let mut writer = BitWriter::new();
+        writer.write_bits(0, 5);
+        writer.write_bits(1, 32);
+        writer.write_unary(ESCAPE_QUOTIENT as u64);
+        writer.write_bits(31, 32);
+        assert_eq!(decompress(&writer.finish()), Err(DecodeError::NonCanonical));
+        let mut writer = BitWriter::new();
+        writer.write_bits(0, 5);
+        writer.write_bits(1, 32);
+        writer.write_unary(ESCAPE_QUOTIENT as u64 + 1);
+        assert_eq!(decompress(&writer.finish()), Err(DecodeError::Overflow));
+    }
+
+    #[test]
+    fn test_decode_into_caller_buffer() {
+        let numbers = vec![3, 17, 0, 250, 9];
+        let encoded = compress(&numbers, Some(6));
+
+        let mut output = [0u32; 8];
+        assert_eq!(decode_into(&encoded, &mut output), Ok(5));
+        assert_eq!(&output[..5], &numbers[..]);
+
+        let mut short = [0u32; 4];
+        assert_eq!(
+            decode_into(&encoded, &mut short),
+            Err(DecodeError::OutputTooSmall)
+        );
+    }
+}
@@ -0,0 +1,271 @@
+use crate::codec::{IntegerCodec, read_count, write_count};
+use crate::error::DecodeError;
+use crate::stream;
+
+const GROUP_SIZE: usize = 4;
+
+/// Decoding plan for one tag byte: where each of the four values starts,
+/// how many bytes it takes and the mask that trims a 4-byte load to it.
+#[derive(Debug, Clone, Copy)]
+struct TagEntry {
+    offsets: [u8; GROUP_SIZE],
+    lengths: [u8; GROUP_SIZE],
+    masks: [u32; GROUP_SIZE],
+    len: u8,
+}
+
+static TAG_TABLE: [TagEntry; 256] = build_tag_table();
+
+const fn build_tag_table() -> [TagEntry; 256] {
+    let mut table = [TagEntry {
+        offsets: [0; GROUP_SIZE],
+        lengths: [0; GROUP_SIZE],
+        masks: [0; GROUP_SIZE],
+        len: 0,
+    }; 256];
+    let mut tag = 0;
+    while tag < 256 {
+        let mut offset = 0;
+        let mut i = 0;
+        while i < GROUP_SIZE {
+            let len = ((tag >> (2 * i)) & 3) as u8 + 1;
+            table[tag].offsets[i] = offset;
+            table[tag].lengths[i] = len;
+            table[tag].masks[i] = u32::MAX >> (8 * (4 - len as u32));
+            offset += len;
+            i += 1;
+        }
+        table[tag].len = offset;
+        tag += 1;
+    }
+    table
+}
+
+/// Number of bytes needed to store `value` (1 to 4).
+fn byte_len(value: u32) -> usize {
+    ((32 - value.leading_zeros()).max(1) as usize).div_ceil(8)
+}
+
+/// Encodes `values` in groups of four, each group being one tag byte (two
+/// bits per value holding its byte length minus one, first value in the
+/// low bits) followed by the values in 1 to 4 little-endian bytes.
+///
+/// The stream starts with the value count (u32, little endian). A last
+/// group with fewer than four values only writes the bytes of the values it
+/// has; the tag bits of the missing values are zero.
+pub fn compress(values: &[u32]) -> Vec<u8> {
+    let mut result = Vec::new();
+    write_count(&mut result, values.len());
+
+    for group in values.chunks(GROUP_SIZE) {
+        let tag_pos = result.len();
+        result.push(0);
+        let mut tag = 0u8;
+        for (i, &value) in group.iter().enumerate() {
+            let len = byte_len(value);
+            tag |= ((len - 1) as u8) << (2 * i);
+            result.extend_from_slice(&value.to_le_bytes()[..len]);
+        }
+        result[tag_pos] = tag;
+    }
+
+    result
+}
+
+/// Decodes a stream produced by [`compress`].
+pub fn decompress(bytes: &[u8]) -> Result<Vec<u32>, DecodeError> {
+    let mut result = Vec::new();
+    decode_append(bytes, &mut result)?;
+    Ok(result)
+}
+
+/// Decodes a stream produced by [`compress`], appending the values to
+/// `output`. Returns the number of values appended.
+pub fn decode_append(bytes: &[u8], output: &mut Vec<u32>) -> Result<usize, DecodeError> {
+    let (count, data) = read_header(bytes)?;
+    let start = output.len();
+    output.resize(start + count, 0);
+    if let Err(err) = decode_groups(data, &mut output[start..]) {
+        output.truncate(start);
+        return Err(err);
+    }
+    Ok(count)
+}
+
+/// Decodes a stream produced by [`compress`] into `output` without
+/// allocating, and returns the number of values written. Fails with
+/// [`DecodeError::OutputTooSmall`] if the stream holds more values than
+/// `output`.
+pub fn decode_into(bytes: &[u8], output: &mut [u32]) -> Result<usize, DecodeError> {
+    let (count, data) = read_header(bytes)?;
+    let output = output.get_mut(..count).ok_or(DecodeError::OutputTooSmall)?;
+    decode_groups(data, output)?;
+    Ok(count)
+}
+
+/// Splits off the value count, rejecting counts the data cannot hold since
+/// every value takes at least one byte.
+fn read_header(bytes: &[u8]) -> Result<(usize, &[u8]), DecodeError> {
+    let (count, data) = read_count(bytes)?;
+    if count > data.len() {
+        return Err(DecodeError::Truncated);
+    }
+    Ok((count, data))
+}
+
+/// Decodes exactly `output.len()` values from `data`.
+fn decode_groups(mut data: &[u8], output: &mut [u32]) -> Result<(), DecodeError> {
+    let mut groups = output.chunks_exact_mut(GROUP_SIZE);
+    for group in &mut groups {
+        let (&tag, rest) = data.split_first().ok_or(DecodeError::Truncated)?;
+        let entry = &TAG_TABLE[tag as usize];
+        if rest.len() >= 4 * GROUP_SIZE {
+            // Fast path: four unaligned loads, each trimmed by its mask
+            for (i, value) in group.iter_mut().enumerate() {
+                let offset = entry.offsets[i] as usize;
+                let word = u32::from_le_bytes(rest[offset..offset + 4].try_into().unwrap());
+                *value = word & entry.masks[i];
+            }
+        } else {
+            let bytes = rest
+                .get(..entry.len as usize)
+                .ok_or(DecodeError::Truncated)?;
+            read_values(bytes, entry, group);
+        }
+        data = &rest[entry.len as usize..];
+    }
+
+    let tail = groups.into_remainder();
+    if !tail.is_empty() {
+        let (&tag, rest) = data.split_first().ok_or(DecodeError::Truncated)?;
+        if tag >> (2 * tail.len()) != 0 {
+            return Err(DecodeError::InvalidHeader);
+        }
+        let entry = &TAG_TABLE[tag as usize];
+        let len = entry.offsets[tail.len()] as usize;
+        let bytes = rest.get(..len).ok_or(DecodeError::Truncated)?;
+        read_values(bytes, entry, tail);
+        data = &rest[len..];
+    }
+
+    if !data.is_empty() {
+        return Err(DecodeError::TrailingBytes);
+    }
+    Ok(())
+}
+
+/// Reads the first `output.len()` values of a group byte by byte, for groups
+/// too close to the end of the input for 4-byte loads.
+fn read_values(bytes: &[u8], entry: &TagEntry, output: &mut [u32]) {
+    for (i, value) in output.iter_mut().enumerate() {
+        let offset = entry.offsets[i] as usize;
+        let len = entry.lengths[i] as usize;
+        let mut word = [0u8; 4];
+        word[..len].copy_from_slice(&bytes[offset..offset + len]);
+        *value = u32::from_le_bytes(word);
+    }
+}
+
+/// Group Varint as an [`IntegerCodec`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct GroupVarint;
+
+impl IntegerCodec for GroupVarint {
+    type Value = u32;
+
+    fn encode(&self, input: &[u32], output: &mut Vec<u8>) {
+        output.extend_from_slice(&compress(input));
+    }
+
+    fn decode(&self, input: &[u8], output: &mut Vec<u32>) -> Result<usize, DecodeError> {
+        decode_append(input, output)
+    }
+
+    fn encoded_len(&self, input: &[u32]) -> usize {
+        // Count, one tag per group, then the value bytes
+        4 + input.len().div_ceil(GROUP_SIZE) + input.iter().map(|&v| byte_len(v)).sum::<usize>()
+    }
+}
+
+/// Streaming Group Varint encoder over a [`std::io::Write`], see [`stream::Encoder`].
+pub type Encoder<W> = stream::Encoder<W, GroupVarint>;
+
+/// Streaming Group Varint decoder over a [`std::io::Read`], see [`stream::Decoder`].
+pub type Decoder<R> = stream::Decoder<R, GroupVarint>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_group_layout() {
+        let encoded = compress(&[1, 300, 70_000, u32::MAX]);
+        assert_eq!(&encoded[..4], &4u32.to_le_bytes());
+        // Lengths 1, 2, 3, 4 are stored as 0, 1, 2, 3 from the low bits up
+        assert_eq!(encoded[4], 0b11_10_01_00);
+        assert_eq!(
+            &encoded[5..],
+            &[1, 0x2C, 0x01, 0x70, 0x11, 0x01, 0xFF, 0xFF, 0xFF, 0xFF]
+        );
+    }
+
+    #[test]
+    fn test_roundtrip_every_length() {
+        for len in 0..40u32 {
+            let values: Vec<u32> = (0..len)
+                .map(|i| i.wrapping_mul(2_654_435_761) >> (i % 32))
+                .collect();
+            let encoded = compress(&values);
+            assert_eq!(GroupVarint.encoded_len(&values), encoded.len());
+            assert_eq!(decompress(&encoded), Ok(values), "length {len}");
+        }
+    }
+
+    #[test]
+    fn test_partial_last_group() {
+        let encoded = compress(&[5, 1000]);
+        assert_eq!(encoded.len(), 4 + 1 + 1 + 2);
+        assert_eq!(decompress(&encoded), Ok(vec![5, 1000]));
+
+        // A missing value cannot claim a non-zero length
+        let mut bad = encoded.clone();
+        bad[4] |= 0b01_0000;
+        assert_eq!(decompress(&bad), Err(DecodeError::InvalidHeader));
+    }
+
+    #[test]
+    fn test_rejects_malformed_streams() {
+        let values: Vec<u32> = (0..30).map(|i| i * 1000).collect();
+        let encoded = compress(&values);
+        assert_eq!(decompress(&encoded[..2]), Err(DecodeError::Truncated));
+        assert_eq!(
+            decompress(&encoded[..encoded.len() - 1]),
+            Err(DecodeError::Truncated)
+        );
+
+        let mut extra = encoded.clone();
+        extra.push(0);
+        assert_eq!(decompress(&extra), Err(DecodeError::TrailingBytes));
+
+        // A corrupt count larger than the input is rejected before allocating
+        let mut huge = encoded.clone();
+        huge[..4].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert_eq!(decompress(&huge), Err(DecodeError::Truncated));
+    }
+
+    #[test]
+    fn test_decode_into_caller_buffer() {
+        let values = vec![7, 70_000, 3, 9, 1 << 30];
+        let encoded = compress(&values);
+
+        let mut output = [0u32; 8];
+        assert_eq!(decode_into(&encoded, &mut output), Ok(5));
+        assert_eq!(&output[..5], &values[..]);
+
+        let mut short = [0u32; 4];
+        assert_eq!(
+            decode_into(&encoded, &mut short),
+            Err(DecodeError::OutputTooSmall)
+        );
+    }
+}
@@ -0,0 +1,250 @@
+use std::fmt::Debug;
+
+use crate::error::EncodeError;
+
+/// Number of words between two entries of the skip table when none is given.
+pub const DEFAULT_SKIP_INTERVAL: usize = 16;
+
+/// Word-aligned codecs (`Simple9`, `Simple16`, `Simple8b`) whose words can be
+/// inspected one at a time through their selector tables.
+pub trait WordCodec {
+    /// Machine word the codec packs values into.
+    type Word: Copy + Debug;
+    /// Integer type accepted by the codec.
+    type Value: Copy + Debug + Ord;
+
+    /// Packs `values` into words.
+    fn encode_words(values: &[Self::Value]) -> Result<Vec<Self::Word>, EncodeError>;
+
+    /// Number of values stored in `word`, including padding in the last word.
+    fn values_in_word(word: Self::Word) -> usize;
+
+    /// The `index`-th value stored in `word`, without decoding the others.
+    fn value_in_word(word: Self::Word, index: usize) -> Self::Value;
+}
+
+/// A Simple-family word stream with a sparse skip table for random access.
+///
+/// The skip table records how many values precede every `skip_interval`-th
+/// word, so [`get`](IndexedList::get) binary searches the table, walks at
+/// most `skip_interval` selectors and extracts a single value. It also keeps
+/// the largest value of each such block of words for
+/// [`next_geq`](IndexedList::next_geq).
+#[derive(Debug, Clone)]
+pub struct IndexedList<C: WordCodec> {
+    words: Vec<C::Word>,
+    skips: Vec<usize>,
+    maxes: Vec<C::Value>,
+    skip_interval: usize,
+    len: usize,
+}
+
+impl<C: WordCodec> IndexedList<C> {
+    /// Encodes `values` with a skip entry every [`DEFAULT_SKIP_INTERVAL`] words.
+    pub fn new(values: &[C::Value]) -> Result<Self, EncodeError> {
+        Self::with_skip_interval(values, DEFAULT_SKIP_INTERVAL)
+    }
+
+    /// Encodes `values` with a skip entry every `skip_interval` words.
+    ///
+    /// # Panics
+    /// Panics if `skip_interval` is zero.
+    pub fn with_skip_interval(
+        values: &[C::Value],
+        skip_interval: usize,
+    ) -> Result<Self, EncodeError> {
+        assert!(skip_interval > 0, "skip interval must be at least one word");
+        let words = C::encode_words(values)?;
+
+        let mut skips = Vec::with_capacity(words.len().div_ceil(skip_interval));
+        let mut values_before = 0;
+        for (i, &word) in words.iter().enumerate() {
+            if i % skip_interval == 0 {
+                skips.push(values_before);
+            }
+            values_before += C::values_in_word(word);
+        }
+
+        // Padding in the last word is not part of the list, so the maxima
+        // are taken over the input rather than the decoded words
+        let maxes = skips
+            .iter()
+            .enumerate()
+            .map(|(sample, &start)| {
+                let end = skips.get(sample + 1).map_or(values.len(), |&end| end);
+                *values[start..end.min(values.len())].iter().max().unwrap()
+            })
+            .collect();
+
+        Ok(IndexedList {
+            words,
+            skips,
+            maxes,
+            skip_interval,
+            len: values.len(),
+        })
+    }
+
+    /// Number of values in the list.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The encoded words.
+    pub fn words(&self) -> &[C::Word] {
+        &self.words
+    }
+
+    /// Returns the `index`-th value, or `None` if it is out of bounds.
+    pub fn get(&self, index: usize) -> Option<C::Value> {
+        if index >= self.len {
+            return None;
+        }
+        let (word_idx, offset) = self.locate(index);
+        Some(C::value_in_word(self.words[word_idx], offset))
+    }
+
+    /// For a sorted list, returns the position and value of the first value
+    /// greater than or equal to `target`, or `None` if every value is smaller.
+    ///
+    /// Blocks whose maximum is below `target` are skipped by binary search;
+    /// only the words of the one candidate block are inspected.
+    pub fn next_geq(&self, target: C::Value) -> Option<(usize, C::Value)> {
+        let sample = self.maxes.partition_point(|&max| max < target);
+        if sample == self.maxes.len() {
+            return None;
+        }
+
+        let mut index = self.skips[sample];
+        for &word in &self.words[sample * self.skip_interval..] {
+            for offset in 0..C::values_in_word(word) {
+                if index >= self.len {
+                    return None;
+                }
+                let value = C::value_in_word(word, offset);
+                if value >= target {
+                    return Some((index, value));
+                }
+                index += 1;
+            }
+        }
+        None
+    }
+
+    /// Finds the word holding `index` and the position inside that word.
+    /// `index` must be in bounds.
+    fn locate(&self, index: usize) -> (usize, usize) {
+        let sample = self.skips.partition_point(|&before| before <= index) - 1;
+        let mut values_before = self.skips[sample];
+        let mut word_idx = sample * self.skip_interval;
+        loop {
+            let items = C::values_in_word(self.words[word_idx]);
+            if index < values_before + items {
+                return (word_idx, index - values_before);
+            }
+            values_before += items;
+            word_idx += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simple8b::Simple8b;
+    use crate::simple9::Simple9;
+    use crate::simple16::Simple16;
+
+    fn sample_values() -> Vec<u32> {
+        (0..5000u32)
+            .map(|i| match i % 11 {
+                0 => i * 1000,
+                1..=4 => i % 2,
+                _ => i % 300,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_simple9_get() {
+        let values = sample_values();
+        let list = IndexedList::<Simple9>::new(&values).unwrap();
+        assert_eq!(list.len(), values.len());
+        for (i, &value) in values.iter().enumerate() {
+            assert_eq!(list.get(i), Some(value));
+        }
+        assert_eq!(list.get(values.len()), None);
+    }
+
+    #[test]
+    fn test_simple16_get() {
+        let values = sample_values();
+        let list = IndexedList::<Simple16>::with_skip_interval(&values, 3).unwrap();
+        for (i, &value) in values.iter().enumerate() {
+            assert_eq!(list.get(i), Some(value));
+        }
+        assert_eq!(list.get(values.len()), None);
+    }
+
+    #[test]
+    fn test_simple8b_get_with_runs_of_ones() {
+        let mut values: Vec<u64> = vec![1; 500];
+        values.extend((0..500).map(|i| i * (1 << 35)));
+        values.extend(vec![1; 130]);
+        let list = IndexedList::<Simple8b>::with_skip_interval(&values, 1).unwrap();
+        for (i, &value) in values.iter().enumerate() {
+            assert_eq!(list.get(i), Some(value));
+        }
+        assert_eq!(list.get(values.len()), None);
+    }
+
+    #[test]
+    fn test_empty_list() {
+        let list = IndexedList::<Simple9>::new(&[]).unwrap();
+        assert!(list.is_empty());
+        assert_eq!(list.get(0), None);
+    }
+
+    #[test]
+    fn test_value_too_large() {
+        let result = IndexedList::<Simple9>::new(&[1, u32::MAX]);
+        assert_eq!(
+            result.unwrap_err(),
+            EncodeError::ValueTooLarge {
+                index: 1,
+                value: u32::MAX as u64
+            }
+        );
+    }
+
+    #[test]
+    fn test_next_geq_on_sorted_list() {
+        let values: Vec<u32> = (0..3000u32).map(|i| i * 7 + (i % 5)).collect();
+        let list = IndexedList::<Simple16>::with_skip_interval(&values, 4).unwrap();
+
+        for target in [0, 1, 6, 7, 8, 500, 10_000, 20_990] {
+            let expected = values
+                .iter()
+                .position(|&v| v >= target)
+                .map(|i| (i, values[i]));
+            assert_eq!(list.next_geq(target), expected, "target {target}");
+        }
+        assert_eq!(list.next_geq(values[2999] + 1), None);
+    }
+
+    #[test]
+    fn test_next_geq_ignores_padding() {
+        // A lone trailing value leaves padding zeros in the last word
+        let values: Vec<u64> = vec![1, 2, 3, 1000];
+        let list = IndexedList::<Simple8b>::with_skip_interval(&values, 1).unwrap();
+        assert_eq!(list.next_geq(1000), Some((3, 1000)));
+        assert_eq!(list.next_geq(1001), None);
+
+        let empty = IndexedList::<Simple9>::new(&[]).unwrap();
+        assert_eq!(empty.next_geq(0), None);
+    }
+}
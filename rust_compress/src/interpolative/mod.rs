@@ -0,0 +1,369 @@
+use crate::bit_io::{BitReader, BitWriter};
+use crate::codec::count_field;
+use crate::error::{DecodeError, EncodeError};
+
+/// Compresses a strictly increasing list with binary interpolative coding
+/// (Moffat and Stuiver).
+///
+/// The middle value of a range is written first, in truncated binary over
+/// the values it can take given the range's bounds and the number of values
+/// on each side; then both halves are coded with the middle value as a new
+/// bound. Clustered lists shrink dramatically: once a range is known to be
+/// filled by consecutive values, they cost no bits at all.
+///
+/// The header holds the value count and the last value (32 bits each, MSB
+/// first, the latter only for a non-empty list); the last value bounds the
+/// recursion over the others.
+///
+/// Fails with [`EncodeError::Unsorted`] at the first value that is not
+/// greater than its predecessor, since repeated values cannot be coded.
+pub fn compress(numbers: &[u32]) -> Result<Vec<u8>, EncodeError> {
+    let mut writer = write_header(numbers)?;
+    if let Some((&last, rest)) = numbers.split_last() {
+        let mut stack = vec![(rest, 0, last as u64)];
+        while let Some((values, lo, hi)) = stack.pop() {
+            if values.is_empty() {
+                continue;
+            }
+            let (left, value, right) = write_middle(&mut writer, values, lo, hi);
+            // Right half first, so the left half is coded first
+            stack.push((right, value + 1, hi));
+            stack.push((left, lo, value));
+        }
+    }
+    Ok(writer.finish())
+}
+
+/// Recursive form of [`compress`], producing the same bytes.
+pub fn compress_recursive(numbers: &[u32]) -> Result<Vec<u8>, EncodeError> {
+    fn encode(writer: &mut BitWriter, values: &[u32], lo: u64, hi: u64) {
+        if values.is_empty() {
+            return;
+        }
+        let (left, value, right) = write_middle(writer, values, lo, hi);
+        encode(writer, left, lo, value);
+        encode(writer, right, value + 1, hi);
+    }
+
+    let mut writer = write_header(numbers)?;
+    if let Some((&last, rest)) = numbers.split_last() {
+        encode(&mut writer, rest, 0, last as u64);
+    }
+    Ok(writer.finish())
+}
+
+/// Decodes a stream produced by [`compress`] or [`compress_recursive`].
+///
+/// A long run of consecutive values takes no bits, so the output can be far
+/// larger than the input; use [`decode_into`] to bound it.
+pub fn decompress(encoded: &[u8]) -> Result<Vec<u32>, DecodeError> {
+    let mut numbers = Vec::new();
+    decode_append(encoded, &mut numbers)?;
+    Ok(numbers)
+}
+
+/// Decodes a stream produced by [`compress`], appending the values to
+/// `output`. Returns the number of values appended.
+///
+/// The stream is checked before `output` grows, so a corrupt count cannot
+/// allocate more than the values the input really holds.
+pub fn decode_append(encoded: &[u8], output: &mut Vec<u32>) -> Result<usize, DecodeError> {
+    let (mut reader, count, last) = check_stream(encoded)?;
+    let start = output.len();
+    output.resize(start + count, 0);
+    decode_values(&mut reader, &mut output[start..], last)
+        .expect("the stream was checked before decoding");
+    Ok(count)
+}
+
+/// Decodes a stream produced by [`compress`] into `output` without
+/// allocating, and returns the number of values written. Fails with
+/// [`DecodeError::OutputTooSmall`] if the header announces more values than
+/// `output` can hold.
+pub fn decode_into(encoded: &[u8], output: &mut [u32]) -> Result<usize, DecodeError> {
+    let mut reader = BitReader::new(encoded);
+    let (count, last) = read_header(&mut reader)?;
+    let output = output.get_mut(..count).ok_or(DecodeError::OutputTooSmall)?;
+    decode_values(&mut reader, output, last)?;
+    check_consumed(&reader, encoded)?;
+    Ok(count)
+}
+
+/// Recursive form of [`decompress`].
+pub fn decompress_recursive(encoded: &[u8]) -> Result<Vec<u32>, DecodeError> {
+    fn decode(
+        reader: &mut BitReader,
+        values: &mut [u32],
+        lo: u64,
+        hi: u64,
+    ) -> Result<(), DecodeError> {
+        if values.is_empty() {
+            return Ok(());
+        }
+        let (left, value, right) = read_middle(reader, values, lo, hi)?;
+        decode(reader, left, lo, value)?;
+        decode(reader, right, value + 1, hi)
+    }
+
+    let (mut reader, count, last) = check_stream(encoded)?;
+    let mut numbers = vec![0; count];
+    if let Some((tail, rest)) = numbers.split_last_mut() {
+        *tail = last;
+        decode(&mut reader, rest, 0, last as u64)?;
+    }
+    Ok(numbers)
+}
+
+/// Walks the whole stream without storing the values, and returns a reader
+/// positioned after the header along with the count and the last value.
+///
+/// A range holding as many values as it has candidates is a run that takes
+/// no bits, so it is skipped; every other range reads at least one bit,
+/// which keeps the walk proportional to the input whatever the count says.
+fn check_stream(encoded: &[u8]) -> Result<(BitReader<'_>, usize, u32), DecodeError> {
+    let mut reader = BitReader::new(encoded);
+    let (count, last) = read_header(&mut reader)?;
+    let values = reader.clone();
+
+    let mut stack = vec![(count.saturating_sub(1), 0, last as u64)];
+    while let Some((len, lo, hi)) = stack.pop() {
+        if len == 0 || hi - lo == len as u64 {
+            continue;
+        }
+        let (mid, low, high) = middle_range(len, lo, hi);
+        let offset = reader
+            .read_truncated(high - low + 1)
+            .ok_or(DecodeError::Truncated)?;
+        let value = low + offset;
+        stack.push((len - mid - 1, value + 1, hi));
+        stack.push((mid, lo, value));
+    }
+    check_consumed(&reader, encoded)?;
+    Ok((values, count, last))
+}
+
+/// Fills `output` with the count values of the stream, the last of which is
+/// known from the header.
+fn decode_values(reader: &mut BitReader, output: &mut [u32], last: u32) -> Result<(), DecodeError> {
+    let Some((tail, rest)) = output.split_last_mut() else {
+        return Ok(());
+    };
+    *tail = last;
+    let mut stack = vec![(rest, 0, last as u64)];
+    while let Some((values, lo, hi)) = stack.pop() {
+        if values.is_empty() {
+            continue;
+        }
+        let (left, value, right) = read_middle(reader, values, lo, hi)?;
+        stack.push((right, value + 1, hi));
+        stack.push((left, lo, value));
+    }
+    Ok(())
+}
+
+/// Bounds of the middle value of `len` strictly increasing values in
+/// `lo..hi`, which leave room for the values on either side.
+fn middle_range(len: usize, lo: u64, hi: u64) -> (usize, u64, u64) {
+    let mid = len / 2;
+    (mid, lo + mid as u64, hi - (len - mid) as u64)
+}
+
+/// Writes the middle value of `values`, all in `lo..hi`, and splits around
+/// it.
+fn write_middle<'a>(
+    writer: &mut BitWriter,
+    values: &'a [u32],
+    lo: u64,
+    hi: u64,
+) -> (&'a [u32], u64, &'a [u32]) {
+    let (mid, low, high) = middle_range(values.len(), lo, hi);
+    let value = values[mid] as u64;
+    writer.write_truncated(value - low, high - low + 1);
+    (&values[..mid], value, &values[mid + 1..])
+}
+
+/// Reads the middle value of `values`, all in `lo..hi`, and splits around it.
+fn read_middle<'a>(
+    reader: &mut BitReader,
+    values: &'a mut [u32],
+    lo: u64,
+    hi: u64,
+) -> Result<(&'a mut [u32], u64, &'a mut [u32]), DecodeError> {
+    let (mid, low, high) = middle_range(values.len(), lo, hi);
+    let offset = reader
+        .read_truncated(high - low + 1)
+        .ok_or(DecodeError::Truncated)?;
+    let value = low + offset;
+    let (left, rest) = values.split_at_mut(mid);
+    let (middle, right) = rest.split_first_mut().unwrap();
+    *middle = value as u32;
+    Ok((left, value, right))
+}
+
+fn write_header(numbers: &[u32]) -> Result<BitWriter, EncodeError> {
+    if let Some(index) = numbers.windows(2).position(|pair| pair[1] <= pair[0]) {
+        return Err(EncodeError::Unsorted { index: index + 1 });
+    }
+    let mut writer = BitWriter::new();
+    writer.write_bits(count_field(numbers.len()) as u64, 32);
+    if let Some(&last) = numbers.last() {
+        writer.write_bits(last as u64, 32);
+    }
+    Ok(writer)
+}
+
+/// Reads the number of values and the last value. `count` strictly
+/// increasing values need a last value of at least `count - 1`, which every
+/// range in the recursion then relies on.
+fn read_header(reader: &mut BitReader) -> Result<(usize, u32), DecodeError> {
+    let count = reader.read_bits(32).ok_or(DecodeError::Truncated)? as usize;
+    if count == 0 {
+        return Ok((0, 0));
+    }
+    let last = reader.read_bits(32).ok_or(DecodeError::Truncated)? as u32;
+    if (last as usize) < count - 1 {
+        return Err(DecodeError::InvalidHeader);
+    }
+    Ok((count, last))
+}
+
+/// Rejects input left over after the last value.
+fn check_consumed(reader: &BitReader, encoded: &[u8]) -> Result<(), DecodeError> {
+    if reader.bytes_consumed() != encoded.len() {
+        return Err(DecodeError::TrailingBytes);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::Rng;
+
+    use super::*;
+    use crate::rice;
+
+    fn random_sorted(len: usize, max_gap: u32) -> Vec<u32> {
+        let mut rng = rand::thread_rng();
+        let mut value = 0u32;
+        (0..len)
+            .map(|_| {
+                value += rng.gen_range(1..=max_gap);
+                value
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_roundtrip_both_forms() {
+        for (len, max_gap) in [(1, 1), (2, 5), (100, 1), (1000, 3), (5000, 10_000)] {
+            let numbers = random_sorted(len, max_gap);
+            let encoded = compress(&numbers).unwrap();
+            assert_eq!(compress_recursive(&numbers).unwrap(), encoded);
+            assert_eq!(decompress(&encoded).unwrap(), numbers);
+            assert_eq!(decompress_recursive(&encoded).unwrap(), numbers);
+        }
+        assert!(decompress(&compress(&[]).unwrap()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_runs_take_no_bits() {
+        // Only the header: every middle value has a single candidate
+        let numbers: Vec<u32> = (0..1000).collect();
+        assert_eq!(compress(&numbers).unwrap().len(), 8);
+
+        // The run's offset is paid once, near the top of the recursion
+        let numbers: Vec<u32> = (1000..2000).collect();
+        assert!(compress(&numbers).unwrap().len() <= 8 + 16);
+
+        let numbers = vec![0, 1, 2, u32::MAX - 1, u32::MAX];
+        assert_eq!(decompress(&compress(&numbers).unwrap()).unwrap(), numbers);
+    }
+
+    #[test]
+    fn test_beats_rice_on_clustered_gaps() {
+        let mut numbers = Vec::new();
+        for cluster in 0..50u32 {
+            let start = cluster * 100_000;
+            numbers.extend((0..40).map(|i| start + i * 2));
+        }
+        let gaps: Vec<u32> = numbers
+            .iter()
+            .scan(0, |prev, &n| {
+                let gap = n - *prev;
+                *prev = n;
+                Some(gap)
+            })
+            .collect();
+        let interpolative = compress(&numbers).unwrap();
+        assert!(interpolative.len() < rice::compress(&gaps, None).len());
+    }
+
+    #[test]
+    fn test_rejects_unsorted_and_repeats() {
+        assert_eq!(
+            compress(&[1, 5, 4]),
+            Err(EncodeError::Unsorted { index: 2 })
+        );
+        assert_eq!(
+            compress_recursive(&[1, 1]),
+            Err(EncodeError::Unsorted { index: 1 })
+        );
+    }
+
+    #[test]
+    fn test_rejects_malformed_streams() {
+        assert_eq!(decompress(&[]), Err(DecodeError::Truncated));
+        assert_eq!(decompress(&[0, 0, 0, 1]), Err(DecodeError::Truncated));
+
+        let numbers = random_sorted(200, 1000);
+        let encoded = compress(&numbers).unwrap();
+        assert_eq!(
+            decompress(&encoded[..encoded.len() - 2]),
+            Err(DecodeError::Truncated)
+        );
+        assert_eq!(
+            decompress_recursive(&encoded[..encoded.len() - 2]),
+            Err(DecodeError::Truncated)
+        );
+
+        let mut extra = encoded.clone();
+        extra.push(0);
+        assert_eq!(decompress(&extra), Err(DecodeError::TrailingBytes));
+
+        // Five strictly increasing values cannot end at 3
+        let header = [0, 0, 0, 5, 0, 0, 0, 3];
+        assert_eq!(decompress(&header), Err(DecodeError::InvalidHeader));
+
+        // Four billion values short of a full run need bits the input lacks,
+        // and are rejected before anything is allocated
+        let header = [0xFF, 0xFF, 0xFF, 0xF0, 0xFF, 0xFF, 0xFF, 0xFF];
+        assert_eq!(decompress(&header), Err(DecodeError::Truncated));
+        assert_eq!(decompress_recursive(&header), Err(DecodeError::Truncated));
+        let mut appended = vec![7];
+        assert_eq!(
+            decode_append(&header, &mut appended),
+            Err(DecodeError::Truncated)
+        );
+        assert_eq!(appended, vec![7]);
+    }
+
+    #[test]
+    fn test_decode_into_caller_buffer() {
+        let numbers = vec![3, 17, 18, 250, 900];
+        let encoded = compress(&numbers).unwrap();
+
+        let mut output = [0u32; 8];
+        assert_eq!(decode_into(&encoded, &mut output), Ok(5));
+        assert_eq!(&output[..5], &numbers[..]);
+
+        let mut short = [0u32; 4];
+        assert_eq!(
+            decode_into(&encoded, &mut short),
+            Err(DecodeError::OutputTooSmall)
+        );
+
+        let mut appended = vec![7];
+        assert_eq!(decode_append(&encoded, &mut appended), Ok(5));
+        assert_eq!(&appended[1..], &numbers[..]);
+    }
+}
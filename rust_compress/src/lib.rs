@@ -1,9 +1,26 @@
+pub mod bit_io;
+pub mod bitpacking;
+pub mod codec;
+pub mod elias_fano;
+pub mod error;
+pub mod golomb;
+pub mod group_varint;
+pub mod indexed;
+pub mod interpolative;
 pub mod p_for_delta;
 pub mod rice;
+pub mod simd_bp128;
 pub mod simple16;
 pub mod simple8b;
 pub mod simple9;
+pub mod stream;
+pub mod stream_vbyte;
+pub mod universal;
 pub mod var_byte;
+pub mod zigzag;
+
+pub use codec::IntegerCodec;
+pub use error::{DecodeError, EncodeError};
 
 #[cfg(test)]
 mod tests {
@@ -16,7 +33,7 @@ mod tests {
         // Shows how to use simple8b module
         let data = vec![1, 2, 3, 4, 5];
         let encoded = simple8b::compress(&data);
-        let decoded = simple8b::decompress_from_bytes(&encoded);
+        let decoded = simple8b::decompress_from_bytes(&encoded).unwrap();
         assert_eq!(&data, &decoded[..data.len()]);
     }
 
@@ -25,7 +42,7 @@ mod tests {
         // Shows how to use simple8b module
         let data = vec![1, 2, 3, 4, 5];
         let encoded = simple9::compress(&data);
-        let decoded = simple9::decompress_from_bytes(&encoded);
+        let decoded = simple9::decompress_from_bytes(&encoded).unwrap();
         assert_eq!(&data, &decoded[..data.len()]);
     }
 
@@ -34,7 +51,7 @@ mod tests {
         // Shows how to use simple8b module
         let data = vec![1, 2, 3, 4, 5];
         let encoded = simple16::compress(&data);
-        let decoded = simple16::decompress_from_bytes(&encoded);
+        let decoded = simple16::decompress_from_bytes(&encoded).unwrap();
         assert_eq!(&data, &decoded[..data.len()]);
     }
 
@@ -47,13 +64,13 @@ mod tests {
                 let val: u32 = rng.r#gen();
                 if i % 16 == 0 {
                     if val == 0 {
-                        1 % (1u32 << 7)
+                        1
                     } else {
                         (1 + val) % (1u32 << 7)
                     } // Ensure it's at least 1
                 } else {
                     if val == 0 {
-                        1 % (1u32 << 2)
+                        1
                     } else {
                         (val) % (1u32 << 2)
                     } // Ensure it's at least 1
@@ -61,10 +78,26 @@ mod tests {
             })
             .collect();
         let encoded = p_for_delta::compress(&data);
-        let decoded = p_for_delta::decompress(&encoded);
+        let decoded = p_for_delta::decompress(&encoded).unwrap();
         assert_eq!(&data, &decoded[..data.len()]);
     }
 
+    #[test]
+    fn example_integer_codec_usage() {
+        // Shows how to pick a codec behind the common trait
+        fn roundtrip(codec: &dyn IntegerCodec<Value = u32>, data: &[u32]) -> Vec<u32> {
+            let mut encoded = Vec::new();
+            codec.encode(data, &mut encoded);
+            let mut decoded = Vec::new();
+            codec.decode(&encoded, &mut decoded).unwrap();
+            decoded
+        }
+
+        let data = vec![1, 2, 3, 4, 5];
+        assert_eq!(roundtrip(&simple9::Simple9, &data), data);
+        assert_eq!(roundtrip(&var_byte::VarByte, &data), data);
+    }
+
     // #[test]
     // fn example_rice_encoding() {
     //     // Shows rice encoding
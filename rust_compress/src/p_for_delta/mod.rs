@@ -1,7 +1,13 @@
 mod utils;
+use crate::codec::{IntegerCodec, read_count, write_count};
+use crate::error::{DecodeError, EncodeError};
+use crate::stream;
+use crate::var_byte;
+use crate::zigzag;
 use utils::*;
 const BATCH_SIZE: usize = 128;
-static DECOMPRESSORS: &[DecompressorFn] = &[
+/// Unpacking kernels for 32 values, indexed by bit width minus one.
+pub(crate) static DECOMPRESSORS: &[DecompressorFn] = &[
     decompress_1_bit,
     decompress_2_bit,
     decompress_3_bit,
@@ -35,7 +41,8 @@ static DECOMPRESSORS: &[DecompressorFn] = &[
     decompress_31_bit,
     decompress_32_bit,
 ];
-static COMPRESSORS: &[DecompressorFn] = &[
+/// Packing kernels for 32 values, indexed by bit width minus one.
+pub(crate) static COMPRESSORS: &[DecompressorFn] = &[
     compress_1_bit,
     compress_2_bit,
     compress_3_bit,
@@ -88,6 +95,16 @@ impl ExceptionSize {
         }
     }
 
+    fn from_bits(bits: u8) -> Option<Self> {
+        match bits {
+            0 => Some(ExceptionSize::BitsNotNeeded),
+            8 => Some(ExceptionSize::Bits8),
+            16 => Some(ExceptionSize::Bits16),
+            32 => Some(ExceptionSize::Bits32),
+            _ => None,
+        }
+    }
+
     fn bits(&self) -> usize {
         match self {
             ExceptionSize::Bits8 => 8,
@@ -107,12 +124,7 @@ pub fn compress(values: &[u32]) -> Vec<u8> {
     // Find optimal b such that at least 90% of values fit in b bits
     let b = find_optimal_b(values);
 
-    let threshold;
-    if b == 32 {
-        threshold = u32::MAX;
-    } else {
-        threshold = 1u32 << b;
-    }
+    let threshold = if b == 32 { u32::MAX } else { 1u32 << b };
     // Identify exceptions
     let mut exceptions = Vec::new();
     for (i, &val) in values.iter().enumerate() {
@@ -124,7 +136,7 @@ pub fn compress(values: &[u32]) -> Vec<u8> {
     // Force additional exceptions if gaps are too large
     let exceptions: Vec<(usize, u32)> = force_intermediate_exceptions(&exceptions, b, values);
     let mut exc_size = ExceptionSize::BitsNotNeeded;
-    if exceptions.len() != 0 {
+    if !exceptions.is_empty() {
         let max_val = exceptions.iter().map(|(_, value)| *value).max().unwrap();
         exc_size = ExceptionSize::from_max_value(max_val);
     }
@@ -141,7 +153,7 @@ pub fn compress(values: &[u32]) -> Vec<u8> {
 
     compressed.push(b as u8);
     compressed.push(exc_size.bits() as u8);
-    compressed.push(first_exc_idx as u8);
+    compressed.push(first_exc_idx);
 
     // Create b-bit slots
     let mut slots = vec![0u32; BATCH_SIZE];
@@ -179,54 +191,422 @@ pub fn compress(values: &[u32]) -> Vec<u8> {
     compressed
 }
 
-pub fn decompress(compressed: &[u8]) -> Vec<u32> {
+pub fn decompress(compressed: &[u8]) -> Result<Vec<u32>, DecodeError> {
+    let mut result = vec![0; BATCH_SIZE];
+    decode_into(compressed, &mut result)?;
+    Ok(result)
+}
+
+/// Decodes a batch produced by [`compress`], appending its 128 values to
+/// `output`. Returns the number of values appended.
+pub fn decode_append(compressed: &[u8], output: &mut Vec<u32>) -> Result<usize, DecodeError> {
+    let start = output.len();
+    output.resize(start + BATCH_SIZE, 0);
+    let result = decode_into(compressed, &mut output[start..]);
+    if result.is_err() {
+        output.truncate(start);
+    }
+    result
+}
+
+/// Decodes a batch produced by [`compress`] into the first 128 slots of
+/// `output` without allocating, and returns the number of values written.
+/// Fails with [`DecodeError::OutputTooSmall`] if `output` holds fewer than
+/// 128 values.
+pub fn decode_into(compressed: &[u8], output: &mut [u32]) -> Result<usize, DecodeError> {
+    decode_batch(compressed, output, None)
+}
+
+/// Decodes one batch into `output`. With a `base`, the values are d-gaps and
+/// are added up from it as each group of 32 is unpacked and patched, so the
+/// batch is traversed only once.
+fn decode_batch(
+    compressed: &[u8],
+    output: &mut [u32],
+    base: Option<u32>,
+) -> Result<usize, DecodeError> {
+    if compressed.len() < 3 {
+        return Err(DecodeError::Truncated);
+    }
     let mut pos = 0;
 
     let b = compressed[pos];
-    pos = pos + 1;
+    pos += 1;
     let exc_size_code = compressed[pos];
     pos += 1;
     let first_exc_idx = compressed[pos];
     pos += 1;
 
-    let exc_size = match exc_size_code {
-        0 => ExceptionSize::BitsNotNeeded,
-        8 => ExceptionSize::Bits8,
-        16 => ExceptionSize::Bits16,
-        32 => ExceptionSize::Bits32,
-        _ => {
-            panic!();
-        }
-    };
-    let pos_end = (pos + 16usize * b as usize) as usize;
-    // Read b-bit slots
-    let mut result = read_packed_bits(
-        &u8_chunks_to_u32_vec(&compressed[pos..pos_end]),
-        BATCH_SIZE,
-        b.into(),
-    );
-    // Read exception values
-    let mut exception_values: Vec<u32> = Vec::new();
-
+    if !(1..=32).contains(&b) {
+        return Err(DecodeError::InvalidHeader);
+    }
+    let exc_size = ExceptionSize::from_bits(exc_size_code).ok_or(DecodeError::InvalidHeader)?;
+    let pos_end = pos + 16usize * b as usize;
+    if compressed.len() < pos_end {
+        return Err(DecodeError::Truncated);
+    }
+    let exception_bytes = compressed.len() - pos_end;
     match exc_size {
-        ExceptionSize::Bits8 => {
-            exception_values = u8_chunks_to_u8_vec(&compressed[pos_end..]);
+        ExceptionSize::BitsNotNeeded if exception_bytes != 0 => {
+            return Err(DecodeError::TrailingBytes);
         }
-        ExceptionSize::Bits16 => exception_values = u8_chunks_to_u16_vec(&compressed[pos_end..]),
-        ExceptionSize::Bits32 => exception_values = u8_chunks_to_u32_vec(&compressed[pos_end..]),
         ExceptionSize::BitsNotNeeded => {}
+        _ if !exception_bytes.is_multiple_of(exc_size.bits() / 8) => {
+            return Err(DecodeError::Truncated);
+        }
+        _ => {}
     }
-
+    let result = output
+        .get_mut(..BATCH_SIZE)
+        .ok_or(DecodeError::OutputTooSmall)?;
+
+    // Without exceptions the area is empty, so the width only has to be
+    // non-zero
+    let mut exceptions = compressed[pos_end..]
+        .chunks_exact((exc_size.bits() / 8).max(1))
+        .map(|chunk| match *chunk {
+            [byte] => byte as u32,
+            [lo, hi] => u16::from_le_bytes([lo, hi]) as u32,
+            _ => u32::from_le_bytes(chunk.try_into().unwrap()),
+        });
     let mut curr_exc_idx = first_exc_idx as usize;
+    let mut running = base;
+    let mut words = [0u32; 32];
+    let packed = compressed[pos..pos_end].chunks_exact(4 * b as usize);
+
+    // One group of 32 values (b words) at a time: unpack the b-bit slots,
+    // patch the exceptions that fall in the group, then add up the gaps
+    for ((group_idx, group), bytes) in result.chunks_exact_mut(32).enumerate().zip(packed) {
+        for (word, chunk) in words.iter_mut().zip(bytes.chunks_exact(4)) {
+            *word = u32::from_le_bytes(chunk.try_into().unwrap());
+        }
+        DECOMPRESSORS[b as usize - 1](&words[..b as usize], group);
+
+        // Follow linked list to find exception positions
+        let group_start = group_idx * 32;
+        while curr_exc_idx < group_start + 32 {
+            let Some(exception_value) = exceptions.next() else {
+                break;
+            };
+            let slot = &mut group[curr_exc_idx - group_start];
+            let offset_to_next_exception = *slot;
+            *slot = exception_value;
+            curr_exc_idx += 1 + offset_to_next_exception as usize;
+        }
 
-    // Follow linked list to find exception positions
-    for i in 0..exception_values.len() {
-        let offset_to_next_exception = result[curr_exc_idx];
-        result[curr_exc_idx] = exception_values[i];
-        curr_exc_idx = curr_exc_idx + (1 + offset_to_next_exception) as usize;
+        if let Some(prev) = running.as_mut() {
+            for value in group.iter_mut() {
+                *prev = prev.checked_add(*value).ok_or(DecodeError::Overflow)?;
+                *value = *prev;
+            }
+        }
+    }
+    // The list pointed past the batch with exceptions left to place
+    if exceptions.next().is_some() {
+        return Err(DecodeError::InvalidHeader);
     }
 
-    result
+    Ok(BATCH_SIZE)
+}
+
+/// Encodes a sorted (non-decreasing) batch of 128 values as d-gaps.
+///
+/// The first gap is taken from `base`, so a long list can be split into
+/// batches by passing the last value of the previous batch as the next
+/// `base` (use 0 for the first batch). The gaps are then packed with
+/// [`compress`].
+pub fn compress_sorted(values: &[u32], base: u32) -> Result<Vec<u8>, EncodeError> {
+    let gaps = to_gaps(values, base)?;
+    Ok(compress(&gaps))
+}
+
+/// Decodes a batch produced by [`compress_sorted`] with the same `base`,
+/// adding the d-gaps back up into the original sorted values while the
+/// batch is unpacked.
+pub fn decompress_sorted(compressed: &[u8], base: u32) -> Result<Vec<u32>, DecodeError> {
+    let mut result = vec![0; BATCH_SIZE];
+    decode_batch(compressed, &mut result, Some(base))?;
+    Ok(result)
+}
+
+/// Encodes a list of any length.
+///
+/// The list is split into 128-value batches packed with [`compress`]; the
+/// remaining `values.len() % 128` values are written with `var_byte`.
+///
+/// Layout (little endian): value count (u32), then for every full batch its
+/// byte length (u32) and bytes, then the `var_byte` tail.
+pub fn compress_list(values: &[u32]) -> Vec<u8> {
+    let mut compressed = Vec::new();
+    write_count(&mut compressed, values.len());
+
+    let mut batches = values.chunks_exact(BATCH_SIZE);
+    for batch in &mut batches {
+        let block = compress(batch);
+        write_count(&mut compressed, block.len());
+        compressed.extend_from_slice(&block);
+    }
+    compressed.extend_from_slice(&var_byte::compress(batches.remainder()));
+
+    compressed
+}
+
+/// Decodes a stream produced by [`compress_list`], returning exactly the
+/// encoded values.
+pub fn decompress_list(compressed: &[u8]) -> Result<Vec<u32>, DecodeError> {
+    decode_list(compressed, None)
+}
+
+/// Decodes a [`compress_list`] stream, adding d-gaps up from `base` batch by
+/// batch when one is given.
+fn decode_list(compressed: &[u8], mut base: Option<u32>) -> Result<Vec<u32>, DecodeError> {
+    let (count, mut rest) = read_count(compressed)?;
+    let mut result = Vec::with_capacity(count.min(compressed.len() * 8));
+
+    for _ in 0..count / BATCH_SIZE {
+        let (block_len, after_len) = read_count(rest)?;
+        let (block, after_block) = after_len
+            .split_at_checked(block_len)
+            .ok_or(DecodeError::Truncated)?;
+        let start = result.len();
+        result.resize(start + BATCH_SIZE, 0);
+        decode_batch(block, &mut result[start..], base)?;
+        base = base.map(|_| result[result.len() - 1]);
+        rest = after_block;
+    }
+
+    let tail_start = result.len();
+    let tail_len = var_byte::decode_append(rest, &mut result)?;
+    match tail_len.cmp(&(count % BATCH_SIZE)) {
+        std::cmp::Ordering::Less => return Err(DecodeError::Truncated),
+        std::cmp::Ordering::Greater => return Err(DecodeError::TrailingBytes),
+        std::cmp::Ordering::Equal => {}
+    }
+    if let Some(base) = base {
+        prefix_sum(&mut result[tail_start..], base)?;
+    }
+
+    Ok(result)
+}
+
+/// Encodes a sorted list of any length as d-gaps from `base`, using the
+/// [`compress_list`] layout. The running value carries over from one batch
+/// to the next, so only the first gap depends on `base`.
+pub fn compress_sorted_list(values: &[u32], base: u32) -> Result<Vec<u8>, EncodeError> {
+    let gaps = to_gaps(values, base)?;
+    Ok(compress_list(&gaps))
+}
+
+/// Decodes a stream produced by [`compress_sorted_list`] with the same `base`.
+pub fn decompress_sorted_list(compressed: &[u8], base: u32) -> Result<Vec<u32>, DecodeError> {
+    decode_list(compressed, Some(base))
+}
+
+/// Encodes signed values of any length ZigZag-mapped, using the
+/// [`compress_list`] layout.
+pub fn compress_i32(values: &[i32]) -> Vec<u8> {
+    let mapped: Vec<u32> = values.iter().map(|&value| zigzag::encode_i32(value)).collect();
+    compress_list(&mapped)
+}
+
+/// Decodes a stream produced by [`compress_i32`].
+pub fn decompress_i32(compressed: &[u8]) -> Result<Vec<i32>, DecodeError> {
+    let mapped = decompress_list(compressed)?;
+    Ok(mapped.into_iter().map(zigzag::decode_i32).collect())
+}
+
+/// A sorted list stored as d-gap batches, with the largest value of every
+/// batch kept aside so searches can skip batches without decoding them.
+///
+/// Full batches of 128 values are packed with [`compress_sorted`], each
+/// starting from the previous batch's maximum; the last partial batch is
+/// stored as `var_byte` d-gaps. Every batch a list holds was either encoded
+/// by [`SortedList::new`] or checked by [`SortedList::from_bytes`], so
+/// queries never fail.
+#[derive(Debug, Clone)]
+pub struct SortedList {
+    data: Vec<u8>,
+    offsets: Vec<usize>,
+    maxes: Vec<u32>,
+    len: usize,
+}
+
+impl SortedList {
+    /// Encodes a sorted (non-decreasing) list.
+    pub fn new(values: &[u32]) -> Result<Self, EncodeError> {
+        let mut data = Vec::new();
+        let mut offsets = Vec::new();
+        let mut maxes = Vec::new();
+        let mut base = 0;
+
+        for (block_idx, batch) in values.chunks(BATCH_SIZE).enumerate() {
+            offsets.push(data.len());
+            let block = if batch.len() == BATCH_SIZE {
+                compress_sorted(batch, base)
+            } else {
+                to_gaps(batch, base).map(|gaps| var_byte::compress(&gaps))
+            };
+            // Report positions relative to the whole list
+            let block = block.map_err(|err| match err {
+                EncodeError::Unsorted { index } => EncodeError::Unsorted {
+                    index: block_idx * BATCH_SIZE + index,
+                },
+                err => err,
+            })?;
+            data.extend_from_slice(&block);
+            base = batch[batch.len() - 1];
+            maxes.push(base);
+        }
+        offsets.push(data.len());
+
+        Ok(SortedList {
+            data,
+            offsets,
+            maxes,
+            len: values.len(),
+        })
+    }
+
+    /// Number of values in the list.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the `index`-th value, decoding only the batch that holds it.
+    pub fn get(&self, index: usize) -> Option<u32> {
+        if index >= self.len {
+            return None;
+        }
+        Some(self.decode_block(index / BATCH_SIZE)[index % BATCH_SIZE])
+    }
+
+    /// Returns the position and value of the first value greater than or
+    /// equal to `target`, or `None` if every value is smaller.
+    ///
+    /// Batches whose maximum is below `target` are skipped by binary search
+    /// over the stored maxima; only the one candidate batch is decoded.
+    pub fn next_geq(&self, target: u32) -> Option<(usize, u32)> {
+        let block = self.maxes.partition_point(|&max| max < target);
+        if block == self.maxes.len() {
+            return None;
+        }
+        let values = self.decode_block(block);
+        let offset = values.partition_point(|&value| value < target);
+        Some((block * BATCH_SIZE + offset, values[offset]))
+    }
+
+    /// Serializes the list: the value count (u32 LE), then every batch as
+    /// its byte length (u32 LE) and bytes. The batch maxima are rebuilt on
+    /// load.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(4 + 4 * self.maxes.len() + self.data.len());
+        write_count(&mut bytes, self.len);
+        for block in self.offsets.windows(2) {
+            write_count(&mut bytes, block[1] - block[0]);
+            bytes.extend_from_slice(&self.data[block[0]..block[1]]);
+        }
+        bytes
+    }
+
+    /// Loads a list written by [`to_bytes`](SortedList::to_bytes).
+    ///
+    /// Every batch is decoded once to check it and to recover its maximum,
+    /// so a list that loads successfully answers every query without
+    /// panicking.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let (len, mut rest) = read_count(bytes)?;
+        let no_of_blocks = len.div_ceil(BATCH_SIZE);
+        // Every batch takes at least its 4-byte length
+        if no_of_blocks > rest.len() / 4 {
+            return Err(DecodeError::Truncated);
+        }
+
+        let mut data = Vec::with_capacity(rest.len());
+        let mut offsets = Vec::with_capacity(no_of_blocks + 1);
+        let mut maxes = Vec::with_capacity(no_of_blocks);
+        let mut base = 0;
+        for block_idx in 0..no_of_blocks {
+            let (block_len, after_len) = read_count(rest)?;
+            let (block, after_block) = after_len
+                .split_at_checked(block_len)
+                .ok_or(DecodeError::Truncated)?;
+            let block_values = (len - block_idx * BATCH_SIZE).min(BATCH_SIZE);
+            let values = decode_list_block(block, base, block_values)?;
+            offsets.push(data.len());
+            data.extend_from_slice(block);
+            base = values[values.len() - 1];
+            maxes.push(base);
+            rest = after_block;
+        }
+        if !rest.is_empty() {
+            return Err(DecodeError::TrailingBytes);
+        }
+        offsets.push(data.len());
+
+        Ok(SortedList {
+            data,
+            offsets,
+            maxes,
+            len,
+        })
+    }
+
+    fn decode_block(&self, block: usize) -> Vec<u32> {
+        let bytes = &self.data[self.offsets[block]..self.offsets[block + 1]];
+        let base = if block == 0 { 0 } else { self.maxes[block - 1] };
+        let block_values = (self.len - block * BATCH_SIZE).min(BATCH_SIZE);
+        decode_list_block(bytes, base, block_values)
+            .expect("SortedList blocks are checked when the list is built")
+    }
+}
+
+/// Decodes one [`SortedList`] batch of `block_values` values: a full batch
+/// packed with [`compress_sorted`], or the `var_byte` d-gaps of the last
+/// partial one.
+fn decode_list_block(
+    bytes: &[u8],
+    base: u32,
+    block_values: usize,
+) -> Result<Vec<u32>, DecodeError> {
+    if block_values == BATCH_SIZE {
+        return decompress_sorted(bytes, base);
+    }
+    let mut gaps = var_byte::decompress(bytes)?;
+    match gaps.len().cmp(&block_values) {
+        std::cmp::Ordering::Less => return Err(DecodeError::Truncated),
+        std::cmp::Ordering::Greater => return Err(DecodeError::TrailingBytes),
+        std::cmp::Ordering::Equal => {}
+    }
+    prefix_sum(&mut gaps, base)?;
+    Ok(gaps)
+}
+
+fn to_gaps(values: &[u32], base: u32) -> Result<Vec<u32>, EncodeError> {
+    let mut prev = base;
+    values
+        .iter()
+        .enumerate()
+        .map(|(index, &value)| {
+            let gap = value
+                .checked_sub(prev)
+                .ok_or(EncodeError::Unsorted { index })?;
+            prev = value;
+            Ok(gap)
+        })
+        .collect()
+}
+
+fn prefix_sum(gaps: &mut [u32], base: u32) -> Result<(), DecodeError> {
+    let mut prev = base;
+    for value in gaps.iter_mut() {
+        prev = prev.checked_add(*value).ok_or(DecodeError::Overflow)?;
+        *value = prev;
+    }
+    Ok(())
 }
 
 fn find_optimal_b(values: &[u32]) -> usize {
@@ -309,41 +689,30 @@ fn write_packed_bits(output: &mut Vec<u8>, values: &[u32], bits_per_value: usize
     }
 }
 
-fn read_packed_bits(input: &[u32], count: usize, bits_per_value: usize) -> Vec<u32> {
-    let mut result: Vec<u32> = vec![0; count];
-    let mut j = 0;
-    let mut i = 0;
-    while i < 128 {
-        DECOMPRESSORS[bits_per_value - 1](&input[j..j + bits_per_value], &mut result[i..i + 32]);
-        i += 32;
-        j += bits_per_value;
+/// Patched frame-of-reference as an [`IntegerCodec`], using the
+/// [`compress_list`] stream so input of any length is accepted.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PForDelta;
+
+impl IntegerCodec for PForDelta {
+    type Value = u32;
+
+    fn encode(&self, input: &[u32], output: &mut Vec<u8>) {
+        output.extend_from_slice(&compress_list(input));
     }
 
-    result
+    fn decode(&self, input: &[u8], output: &mut Vec<u32>) -> Result<usize, DecodeError> {
+        let decoded = decompress_list(input)?;
+        output.extend_from_slice(&decoded);
+        Ok(decoded.len())
+    }
 }
 
-fn u8_chunks_to_u32_vec(bytes: &[u8]) -> Vec<u32> {
-    let u32_vec = bytes
-        .chunks_exact(4)
-        .map(|chunk| u32::from_le_bytes(chunk.try_into().unwrap()))
-        .collect();
-    u32_vec
-}
+/// Streaming PForDelta encoder over a [`std::io::Write`], see [`stream::Encoder`].
+pub type Encoder<W> = stream::Encoder<W, PForDelta>;
 
-fn u8_chunks_to_u16_vec(bytes: &[u8]) -> Vec<u32> {
-    let u32_vec = bytes
-        .chunks_exact(2)
-        .map(|chunk| u16::from_le_bytes(chunk.try_into().unwrap()) as u32)
-        .collect();
-    u32_vec
-}
-fn u8_chunks_to_u8_vec(bytes: &[u8]) -> Vec<u32> {
-    let u32_vec = bytes
-        .chunks_exact(1)
-        .map(|chunk| u8::from_le_bytes(chunk.try_into().unwrap()) as u32)
-        .collect();
-    u32_vec
-}
+/// Streaming PForDelta decoder over a [`std::io::Read`], see [`stream::Decoder`].
+pub type Decoder<R> = stream::Decoder<R, PForDelta>;
 
 #[cfg(test)]
 mod tests {
@@ -359,13 +728,13 @@ mod tests {
                 let val: u32 = rng.r#gen();
                 if i % 16 == 0 {
                     if val == 0 {
-                        1 % (1u32 << 7)
+                        1
                     } else {
                         (1 + val) % (1u32 << 7)
                     } // Ensure it's at least 1
                 } else {
                     if val == 0 {
-                        1 % (1u32 << 1)
+                        1
                     } else {
                         (val) % (1u32 << 1)
                     } // Ensure it's at least 1
@@ -374,7 +743,7 @@ mod tests {
             .collect();
         let encoded = compress(&original);
 
-        let decoded = decompress(&encoded);
+        let decoded = decompress(&encoded).unwrap();
 
         // Decoded may have padding zeros, so check prefix matches
         assert!(decoded.len() == original.len());
@@ -389,13 +758,13 @@ mod tests {
                 let val: u32 = rng.r#gen();
                 if i % 16 == 0 {
                     if val == 0 {
-                        1 % (1u32 << 7)
+                        1
                     } else {
                         (1 + val) % (1u32 << 7)
                     } // Ensure it's at least 1
                 } else {
                     if val == 0 {
-                        1 % (1u32 << 2)
+                        1
                     } else {
                         (val) % (1u32 << 2)
                     } // Ensure it's at least 1
@@ -404,7 +773,7 @@ mod tests {
             .collect();
         let encoded = compress(&original);
 
-        let decoded = decompress(&encoded);
+        let decoded = decompress(&encoded).unwrap();
 
 
         // Decoded may have padding zeros, so check prefix matches
@@ -420,13 +789,13 @@ mod tests {
                 let val: u32 = rng.r#gen();
                 if i % 16 == 0 {
                     if val == 0 {
-                        1 % (1u32 << 7)
+                        1
                     } else {
                         (1 + val) % (1u32 << 7)
                     } // Ensure it's at least 1
                 } else {
                     if val == 0 {
-                        1 % (1u32 << 3)
+                        1
                     } else {
                         (val) % (1u32 << 3)
                     } // Ensure it's at least 1
@@ -435,7 +804,7 @@ mod tests {
             .collect();
         let encoded = compress(&original);
 
-        let decoded = decompress(&encoded);
+        let decoded = decompress(&encoded).unwrap();
 
 
         // Decoded may have padding zeros, so check prefix matches
@@ -451,13 +820,13 @@ mod tests {
                 let val: u32 = rng.r#gen();
                 if i % 16 == 0 {
                     if val == 0 {
-                        1 % (1u32 << 7)
+                        1
                     } else {
                         (1 + val) % (1u32 << 7)
                     } // Ensure it's at least 1
                 } else {
                     if val == 0 {
-                        1 % (1u32 << 4)
+                        1
                     } else {
                         (val) % (1u32 << 4)
                     } // Ensure it's at least 1
@@ -466,7 +835,7 @@ mod tests {
             .collect();
          let encoded = compress(&original);
 
-        let decoded = decompress(&encoded);
+        let decoded = decompress(&encoded).unwrap();
 
 
         // Decoded may have padding zeros, so check prefix matches
@@ -482,13 +851,13 @@ mod tests {
                 let val: u32 = rng.r#gen();
                 if i % 16 == 0 {
                     if val == 0 {
-                        1 % (1u32 << 14)
+                        1
                     } else {
                         (1 + val) % (1u32 << 14)
                     } // Ensure it's at least 1
                 } else {
                     if val == 0 {
-                        1 % (1u32 << 5)
+                        1
                     } else {
                         (val) % (1u32 << 5)
                     } // Ensure it's at least 1
@@ -497,7 +866,7 @@ mod tests {
             .collect();
          let encoded = compress(&original);
 
-        let decoded = decompress(&encoded);
+        let decoded = decompress(&encoded).unwrap();
 
 
         // Decoded may have padding zeros, so check prefix matches
@@ -513,13 +882,13 @@ mod tests {
                 let val: u32 = rng.r#gen();
                 if i % 16 == 0 {
                     if val == 0 {
-                        1 % (1u32 << 21)
+                        1
                     } else {
                         (1 + val) % (1u32 << 21)
                     } // Ensure it's at least 1
                 } else {
                     if val == 0 {
-                        1 % (1u32 << 6)
+                        1
                     } else {
                         (val) % (1u32 << 6)
                     } // Ensure it's at least 1
@@ -528,7 +897,7 @@ mod tests {
             .collect();
         let encoded = compress(&original);
 
-        let decoded = decompress(&encoded);
+        let decoded = decompress(&encoded).unwrap();
 
 
         // Decoded may have padding zeros, so check prefix matches
@@ -544,13 +913,13 @@ mod tests {
                 let val: u32 = rng.r#gen();
                 if i % 16 == 0 {
                     if val == 0 {
-                        1 % (1u32 << 21)
+                        1
                     } else {
                         (1 + val) % (1u32 << 21)
                     } // Ensure it's at least 1
                 } else {
                     if val == 0 {
-                        1 % (1u32 << 7)
+                        1
                     } else {
                         (val) % (1u32 << 7)
                     } // Ensure it's at least 1
@@ -559,7 +928,7 @@ mod tests {
             .collect();
         let encoded = compress(&original);
 
-        let decoded = decompress(&encoded);
+        let decoded = decompress(&encoded).unwrap();
 
 
         // Decoded may have padding zeros, so check prefix matches
@@ -575,13 +944,13 @@ mod tests {
                 let val: u32 = rng.r#gen();
                 if i % 16 == 0 {
                     if val == 0 {
-                        1 % (1u32 << 21)
+                        1
                     } else {
                         (1 + val) % (1u32 << 21)
                     } // Ensure it's at least 1
                 } else {
                     if val == 0 {
-                        1 % (1u32 << 8)
+                        1
                     } else {
                         (val) % (1u32 << 8)
                     } // Ensure it's at least 1
@@ -590,7 +959,7 @@ mod tests {
             .collect();
         let encoded = compress(&original);
 
-        let decoded = decompress(&encoded);
+        let decoded = decompress(&encoded).unwrap();
 
         // Decoded may have padding zeros, so check prefix matches
         assert!(decoded.len() == original.len());
@@ -605,13 +974,13 @@ mod tests {
                 let val: u32 = rng.r#gen();
                 if i % 16 == 0 {
                     if val == 0 {
-                        1 % (1u32 << 21)
+                        1
                     } else {
                         (1 + val) % (1u32 << 21)
                     } // Ensure it's at least 1
                 } else {
                     if val == 0 {
-                        1 % (1u32 << 9)
+                        1
                     } else {
                         (val) % (1u32 << 9)
                     } // Ensure it's at least 1
@@ -620,7 +989,7 @@ mod tests {
             .collect();
         let encoded = compress(&original);
 
-        let decoded = decompress(&encoded);
+        let decoded = decompress(&encoded).unwrap();
 
 
         // Decoded may have padding zeros, so check prefix matches
@@ -636,13 +1005,13 @@ mod tests {
                 let val: u32 = rng.r#gen();
                 if i % 16 == 0 {
                     if val == 0 {
-                        1 % (1u32 << 21)
+                        1
                     } else {
                         (1 + val) % (1u32 << 21)
                     } // Ensure it's at least 1
                 } else {
                     if val == 0 {
-                        1 % (1u32 << 10)
+                        1
                     } else {
                         (val) % (1u32 << 10)
                     } // Ensure it's at least 1
@@ -651,7 +1020,7 @@ mod tests {
             .collect();
         let encoded = compress(&original);
 
-        let decoded = decompress(&encoded);
+        let decoded = decompress(&encoded).unwrap();
 
 
         // Decoded may have padding zeros, so check prefix matches
@@ -667,13 +1036,13 @@ mod tests {
                 let val: u32 = rng.r#gen();
                 if i % 16 == 0 {
                     if val == 0 {
-                        1 % (1u32 << 21)
+                        1
                     } else {
                         (1 + val) % (1u32 << 21)
                     } // Ensure it's at least 1
                 } else {
                     if val == 0 {
-                        1 % (1u32 << 11)
+                        1
                     } else {
                         (val) % (1u32 << 11)
                     } // Ensure it's at least 1
@@ -682,7 +1051,7 @@ mod tests {
             .collect();
        let encoded = compress(&original);
 
-        let decoded = decompress(&encoded);
+        let decoded = decompress(&encoded).unwrap();
 
 
         // Decoded may have padding zeros, so check prefix matches
@@ -698,13 +1067,13 @@ mod tests {
                 let val: u32 = rng.r#gen();
                 if i % 16 == 0 {
                     if val == 0 {
-                        1 % (1u32 << 21)
+                        1
                     } else {
                         (1 + val) % (1u32 << 21)
                     } // Ensure it's at least 1
                 } else {
                     if val == 0 {
-                        1 % (1u32 << 12)
+                        1
                     } else {
                         (val) % (1u32 << 12)
                     } // Ensure it's at least 1
@@ -713,7 +1082,7 @@ mod tests {
             .collect();
          let encoded = compress(&original);
 
-        let decoded = decompress(&encoded);
+        let decoded = decompress(&encoded).unwrap();
 
 
         // Decoded may have padding zeros, so check prefix matches
@@ -729,13 +1098,13 @@ mod tests {
                 let val: u32 = rng.r#gen();
                 if i % 16 == 0 {
                     if val == 0 {
-                        1 % (1u32 << 21)
+                        1
                     } else {
                         (1 + val) % (1u32 << 21)
                     } // Ensure it's at least 1
                 } else {
                     if val == 0 {
-                        1 % (1u32 << 13)
+                        1
                     } else {
                         (val) % (1u32 << 13)
                     } // Ensure it's at least 1
@@ -744,7 +1113,7 @@ mod tests {
             .collect();
         let encoded = compress(&original);
 
-        let decoded = decompress(&encoded);
+        let decoded = decompress(&encoded).unwrap();
 
 
         // Decoded may have padding zeros, so check prefix matches
@@ -760,13 +1129,13 @@ mod tests {
                 let val: u32 = rng.r#gen();
                 if i % 16 == 0 {
                     if val == 0 {
-                        1 % (1u32 << 21)
+                        1
                     } else {
                         (1 + val) % (1u32 << 21)
                     } // Ensure it's at least 1
                 } else {
                     if val == 0 {
-                        1 % (1u32 << 14)
+                        1
                     } else {
                         (val) % (1u32 << 14)
                     } // Ensure it's at least 1
@@ -775,7 +1144,7 @@ mod tests {
             .collect();
         let encoded = compress(&original);
 
-        let decoded = decompress(&encoded);
+        let decoded = decompress(&encoded).unwrap();
 
 
         // Decoded may have padding zeros, so check prefix matches
@@ -791,13 +1160,13 @@ mod tests {
                 let val: u32 = rng.r#gen();
                 if i % 16 == 0 {
                     if val == 0 {
-                        1 % (1u32 << 21)
+                        1
                     } else {
                         (1 + val) % (1u32 << 21)
                     } // Ensure it's at least 1
                 } else {
                     if val == 0 {
-                        1 % (1u32 << 15)
+                        1
                     } else {
                         (val) % (1u32 << 15)
                     } // Ensure it's at least 1
@@ -806,7 +1175,7 @@ mod tests {
             .collect();
         let encoded = compress(&original);
 
-        let decoded = decompress(&encoded);
+        let decoded = decompress(&encoded).unwrap();
 
 
         // Decoded may have padding zeros, so check prefix matches
@@ -822,13 +1191,13 @@ mod tests {
                 let val: u32 = rng.r#gen();
                 if i % 16 == 0 {
                     if val == 0 {
-                        1 % (1u32 << 21)
+                        1
                     } else {
                         (1 + val) % (1u32 << 21)
                     } // Ensure it's at least 1
                 } else {
                     if val == 0 {
-                        1 % (1u32 << 16)
+                        1
                     } else {
                         (val) % (1u32 << 16)
                     } // Ensure it's at least 1
@@ -837,7 +1206,7 @@ mod tests {
             .collect();
         let encoded = compress(&original);
 
-        let decoded = decompress(&encoded);
+        let decoded = decompress(&encoded).unwrap();
 
 
         // Decoded may have padding zeros, so check prefix matches
@@ -853,13 +1222,13 @@ mod tests {
                 let val: u32 = rng.r#gen();
                 if i % 16 == 0 {
                     if val == 0 {
-                        1 % (1u32 << 21)
+                        1
                     } else {
                         (1 + val) % (1u32 << 21)
                     } // Ensure it's at least 1
                 } else {
                     if val == 0 {
-                        1 % (1u32 << 17)
+                        1
                     } else {
                         (val) % (1u32 << 17)
                     } // Ensure it's at least 1
@@ -868,7 +1237,7 @@ mod tests {
             .collect();
        let encoded = compress(&original);
 
-        let decoded = decompress(&encoded);
+        let decoded = decompress(&encoded).unwrap();
 
 
         // Decoded may have padding zeros, so check prefix matches
@@ -884,13 +1253,13 @@ mod tests {
                 let val: u32 = rng.r#gen();
                 if i % 16 == 0 {
                     if val == 0 {
-                        1 % (1u32 << 21)
+                        1
                     } else {
                         (1 + val) % (1u32 << 21)
                     } // Ensure it's at least 1
                 } else {
                     if val == 0 {
-                        1 % (1u32 << 18)
+                        1
                     } else {
                         (val) % (1u32 << 18)
                     } // Ensure it's at least 1
@@ -899,7 +1268,7 @@ mod tests {
             .collect();
         let encoded = compress(&original);
 
-        let decoded = decompress(&encoded);
+        let decoded = decompress(&encoded).unwrap();
 
 
         // Decoded may have padding zeros, so check prefix matches
@@ -915,13 +1284,13 @@ mod tests {
                 let val: u32 = rng.r#gen();
                 if i % 16 == 0 {
                     if val == 0 {
-                        1 % (1u32 << 21)
+                        1
                     } else {
                         (1 + val) % (1u32 << 21)
                     } // Ensure it's at least 1
                 } else {
                     if val == 0 {
-                        1 % (1u32 << 19)
+                        1
                     } else {
                         (val) % (1u32 << 19)
                     } // Ensure it's at least 1
@@ -930,7 +1299,7 @@ mod tests {
             .collect();
         let encoded = compress(&original);
 
-        let decoded = decompress(&encoded);
+        let decoded = decompress(&encoded).unwrap();
 
 
         // Decoded may have padding zeros, so check prefix matches
@@ -946,13 +1315,13 @@ mod tests {
                 let val: u32 = rng.r#gen();
                 if i % 16 == 0 {
                     if val == 0 {
-                        1 % (1u32 << 21)
+                        1
                     } else {
                         (1 + val) % (1u32 << 21)
                     } // Ensure it's at least 1
                 } else {
                     if val == 0 {
-                        1 % (1u32 << 20)
+                        1
                     } else {
                         (val) % (1u32 << 20)
                     } // Ensure it's at least 1
@@ -961,7 +1330,7 @@ mod tests {
             .collect();
          let encoded = compress(&original);
 
-        let decoded = decompress(&encoded);
+        let decoded = decompress(&encoded).unwrap();
 
 
         // Decoded may have padding zeros, so check prefix matches
@@ -977,13 +1346,13 @@ mod tests {
                 let val: u32 = rng.r#gen();
                 if i % 16 == 0 {
                     if val == 0 {
-                        1 % (1u32 << 31)
+                        1
                     } else {
                         (1 + val) % (1u32 << 31)
                     } // Ensure it's at least 1
                 } else {
                     if val == 0 {
-                        1 % (1u32 << 21)
+                        1
                     } else {
                         (val) % (1u32 << 21)
                     } // Ensure it's at least 1
@@ -992,7 +1361,7 @@ mod tests {
             .collect();
         let encoded = compress(&original);
 
-        let decoded = decompress(&encoded);
+        let decoded = decompress(&encoded).unwrap();
 
 
         // Decoded may have padding zeros, so check prefix matches
@@ -1008,13 +1377,13 @@ mod tests {
                 let val: u32 = rng.r#gen();
                 if i % 16 == 0 {
                     if val == 0 {
-                        1 % (1u32 << 31)
+                        1
                     } else {
                         (1 + val) % (1u32 << 31)
                     } // Ensure it's at least 1
                 } else {
                     if val == 0 {
-                        1 % (1u32 << 22)
+                        1
                     } else {
                         (val) % (1u32 << 22)
                     } // Ensure it's at least 1
@@ -1023,7 +1392,7 @@ mod tests {
             .collect();
          let encoded = compress(&original);
 
-        let decoded = decompress(&encoded);
+        let decoded = decompress(&encoded).unwrap();
 
 
         // Decoded may have padding zeros, so check prefix matches
@@ -1039,13 +1408,13 @@ mod tests {
                 let val: u32 = rng.r#gen();
                 if i % 16 == 0 {
                     if val == 0 {
-                        1 % (1u32 << 31)
+                        1
                     } else {
                         (1 + val) % (1u32 << 31)
                     } // Ensure it's at least 1
                 } else {
                     if val == 0 {
-                        1 % (1u32 << 23)
+                        1
                     } else {
                         (val) % (1u32 << 23)
                     } // Ensure it's at least 1
@@ -1054,7 +1423,7 @@ mod tests {
             .collect();
         let encoded = compress(&original);
 
-        let decoded = decompress(&encoded);
+        let decoded = decompress(&encoded).unwrap();
 
 
         // Decoded may have padding zeros, so check prefix matches
@@ -1070,13 +1439,13 @@ mod tests {
                 let val: u32 = rng.r#gen();
                 if i % 16 == 0 {
                     if val == 0 {
-                        1 % (1u32 << 31)
+                        1
                     } else {
                         (1 + val) % (1u32 << 31)
                     } // Ensure it's at least 1
                 } else {
                     if val == 0 {
-                        1 % (1u32 << 24)
+                        1
                     } else {
                         (val) % (1u32 << 24)
                     } // Ensure it's at least 1
@@ -1085,7 +1454,7 @@ mod tests {
             .collect();
          let encoded = compress(&original);
 
-        let decoded = decompress(&encoded);
+        let decoded = decompress(&encoded).unwrap();
 
 
         // Decoded may have padding zeros, so check prefix matches
@@ -1101,13 +1470,13 @@ mod tests {
                 let val: u32 = rng.r#gen();
                 if i % 16 == 0 {
                     if val == 0 {
-                        1 % (1u32 << 31)
+                        1
                     } else {
                         (1 + val) % (1u32 << 31)
                     } // Ensure it's at least 1
                 } else {
                     if val == 0 {
-                        1 % (1u32 << 25)
+                        1
                     } else {
                         (val) % (1u32 << 25)
                     } // Ensure it's at least 1
@@ -1116,7 +1485,7 @@ mod tests {
             .collect();
         let encoded = compress(&original);
 
-        let decoded = decompress(&encoded);
+        let decoded = decompress(&encoded).unwrap();
 
 
         // Decoded may have padding zeros, so check prefix matches
@@ -1132,13 +1501,13 @@ mod tests {
                 let val: u32 = rng.r#gen();
                 if i % 16 == 0 {
                     if val == 0 {
-                        1 % (1u32 << 31)
+                        1
                     } else {
                         (1 + val) % (1u32 << 31)
                     } // Ensure it's at least 1
                 } else {
                     if val == 0 {
-                        1 % (1u32 << 26)
+                        1
                     } else {
                         (val) % (1u32 << 26)
                     } // Ensure it's at least 1
@@ -1147,7 +1516,7 @@ mod tests {
             .collect();
          let encoded = compress(&original);
 
-        let decoded = decompress(&encoded);
+        let decoded = decompress(&encoded).unwrap();
 
         // Decoded may have padding zeros, so check prefix matches
         assert!(decoded.len() == original.len());
@@ -1162,13 +1531,13 @@ mod tests {
                 let val: u32 = rng.r#gen();
                 if i % 16 == 0 {
                     if val == 0 {
-                        1 % (1u32 << 31)
+                        1
                     } else {
                         (1 + val) % (1u32 << 31)
                     } // Ensure it's at least 1
                 } else {
                     if val == 0 {
-                        1 % (1u32 << 27)
+                        1
                     } else {
                         (val) % (1u32 << 27)
                     } // Ensure it's at least 1
@@ -1177,7 +1546,7 @@ mod tests {
             .collect();
         let encoded = compress(&original);
 
-        let decoded = decompress(&encoded);
+        let decoded = decompress(&encoded).unwrap();
 
         // Decoded may have padding zeros, so check prefix matches
         assert!(decoded.len() == original.len());
@@ -1192,13 +1561,13 @@ mod tests {
                 let val: u32 = rng.r#gen();
                 if i % 16 == 0 {
                     if val == 0 {
-                        1 % (1u32 << 31)
+                        1
                     } else {
                         (1 + val) % (1u32 << 31)
                     } // Ensure it's at least 1
                 } else {
                     if val == 0 {
-                        1 % (1u32 << 28)
+                        1
                     } else {
                         (val) % (1u32 << 28)
                     } // Ensure it's at least 1
@@ -1207,7 +1576,7 @@ mod tests {
             .collect();
         let encoded = compress(&original);
 
-        let decoded = decompress(&encoded);
+        let decoded = decompress(&encoded).unwrap();
 
         // Decoded may have padding zeros, so check prefix matches
         assert!(decoded.len() == original.len());
@@ -1222,13 +1591,13 @@ mod tests {
                 let val: u32 = rng.r#gen();
                 if i % 16 == 0 {
                     if val == 0 {
-                        1 % (1u32 << 31)
+                        1
                     } else {
                         (1 + val) % (1u32 << 31)
                     } // Ensure it's at least 1
                 } else {
                     if val == 0 {
-                        1 % (1u32 << 29)
+                        1
                     } else {
                         (val) % (1u32 << 29)
                     } // Ensure it's at least 1
@@ -1237,7 +1606,7 @@ mod tests {
             .collect();
          let encoded = compress(&original);
 
-        let decoded = decompress(&encoded);
+        let decoded = decompress(&encoded).unwrap();
 
 
         // Decoded may have padding zeros, so check prefix matches
@@ -1253,13 +1622,13 @@ mod tests {
                 let val: u32 = rng.r#gen();
                 if i % 16 == 0 {
                     if val == 0 {
-                        1 % u32::MAX
+                        1
                     } else {
                         (1 + val) % u32::MAX
                     } // Ensure it's at least 1
                 } else {
                     if val == 0 {
-                        1 % (1u32 << 30)
+                        1
                     } else {
                         (val) % (1u32 << 30)
                     } // Ensure it's at least 1
@@ -1268,7 +1637,7 @@ mod tests {
             .collect();
         let encoded = compress(&original);
 
-        let decoded = decompress(&encoded);
+        let decoded = decompress(&encoded).unwrap();
 
         // Decoded may have padding zeros, so check prefix matches
         assert!(decoded.len() == original.len());
@@ -1283,13 +1652,13 @@ mod tests {
                 let val: u32 = rng.r#gen();
                 if i % 16 == 0 {
                     if val == 0 {
-                        1 % u32::MAX
+                        1
                     } else {
                         (1 + val) % u32::MAX
                     } // Ensure it's at least 1
                 } else {
                     if val == 0 {
-                        1 % (1u32 << 31)
+                        1
                     } else {
                         (val) % (1u32 << 31)
                     } // Ensure it's at least 1
@@ -1298,7 +1667,7 @@ mod tests {
             .collect();
         let encoded = compress(&original);
 
-        let decoded = decompress(&encoded);
+        let decoded = decompress(&encoded).unwrap();
 
 
         // Decoded may have padding zeros, so check prefix matches
@@ -1314,13 +1683,13 @@ mod tests {
                 let val: u32 = rng.r#gen();
                 if i % 16 == 0 {
                     if val == 0 {
-                        1 % u32::MAX
+                        1
                     } else {
                         (1 + val) % u32::MAX
                     } // Ensure it's at least 1
                 } else {
                     if val == 0 {
-                        1 % u32::MAX
+                        1
                     } else {
                         (val) % (u32::MAX)
                     } // Ensure it's at least 1
@@ -1329,11 +1698,285 @@ mod tests {
             .collect();
     let encoded = compress(&original);
 
-        let decoded = decompress(&encoded);
+        let decoded = decompress(&encoded).unwrap();
 
 
         // Decoded may have padding zeros, so check prefix matches
         assert!(decoded.len() == original.len());
         assert_eq!(&decoded[..original.len()], &original[..]);
     }
+
+    #[test]
+    fn test_decompress_rejects_malformed_input() {
+        let original: Vec<u32> = (0..128).map(|i| if i % 10 == 0 { 1000 } else { i % 4 }).collect();
+        let encoded = compress(&original);
+
+        assert_eq!(decompress(&encoded[..2]), Err(DecodeError::Truncated));
+        assert_eq!(decompress(&encoded[..10]), Err(DecodeError::Truncated));
+
+        let mut bad_width = encoded.clone();
+        bad_width[0] = 33;
+        assert_eq!(decompress(&bad_width), Err(DecodeError::InvalidHeader));
+
+        let mut bad_exception_size = encoded.clone();
+        bad_exception_size[1] = 7;
+        assert_eq!(decompress(&bad_exception_size), Err(DecodeError::InvalidHeader));
+
+        // One byte short of the last 16-bit exception value
+        assert_eq!(
+            decompress(&encoded[..encoded.len() - 1]),
+            Err(DecodeError::Truncated)
+        );
+    }
+
+    #[test]
+    fn test_decompress_rejects_exception_past_block_end() {
+        let original: Vec<u32> = (0..128).map(|i| if i == 127 { 1000 } else { 1 }).collect();
+        let mut encoded = compress(&original);
+        // A second exception value has nowhere to go after the last slot
+        encoded.extend_from_slice(&[0, 0]);
+        assert_eq!(decompress(&encoded), Err(DecodeError::InvalidHeader));
+        assert_eq!(decompress_sorted(&encoded, 0), Err(DecodeError::InvalidHeader));
+    }
+
+    #[test]
+    fn test_decompress_trailing_bytes_without_exceptions() {
+        let original = vec![3u32; 128];
+        let mut encoded = compress(&original);
+        encoded.push(0);
+        assert_eq!(decompress(&encoded), Err(DecodeError::TrailingBytes));
+    }
+
+    #[test]
+    fn test_sorted_roundtrip() {
+        let mut rng = rand::thread_rng();
+        let mut current = 0u32;
+        let original: Vec<u32> = (0..128)
+            .map(|i| {
+                // Mostly small gaps with an occasional long jump
+                current += if i % 25 == 0 {
+                    rng.gen_range(1000..100_000)
+                } else {
+                    rng.gen_range(0..8)
+                };
+                current
+            })
+            .collect();
+        let encoded = compress_sorted(&original, 0).unwrap();
+        // Gaps fit in far fewer bits than the raw docIDs
+        assert!(encoded.len() < compress(&original).len());
+        assert_eq!(decompress_sorted(&encoded, 0).unwrap(), original);
+    }
+
+    #[test]
+    fn test_sorted_base_carried_across_batches() {
+        let original: Vec<u32> = (0..256).map(|i| 1_000_000 + i * 3).collect();
+        let (first, second) = original.split_at(128);
+
+        let first_encoded = compress_sorted(first, 0).unwrap();
+        let second_encoded = compress_sorted(second, first[127]).unwrap();
+
+        let mut decoded = decompress_sorted(&first_encoded, 0).unwrap();
+        let base = *decoded.last().unwrap();
+        decoded.extend(decompress_sorted(&second_encoded, base).unwrap());
+        assert_eq!(decoded, original);
+    }
+
+    #[test]
+    fn test_sorted_rejects_unsorted_input() {
+        let mut original: Vec<u32> = (0..128).collect();
+        original[40] = 3;
+        assert_eq!(
+            compress_sorted(&original, 0),
+            Err(EncodeError::Unsorted { index: 40 })
+        );
+        let original: Vec<u32> = (10..138).collect();
+        assert_eq!(
+            compress_sorted(&original, 11),
+            Err(EncodeError::Unsorted { index: 0 })
+        );
+    }
+
+    #[test]
+    fn test_sorted_decode_overflow() {
+        let original: Vec<u32> = (0..128).map(|i| u32::MAX - 127 + i).collect();
+        let encoded = compress_sorted(&original, 0).unwrap();
+        assert_eq!(decompress_sorted(&encoded, 1), Err(DecodeError::Overflow));
+    }
+
+    #[test]
+    fn test_list_roundtrip_any_length() {
+        let mut rng = rand::thread_rng();
+        for len in [0, 1, 127, 128, 129, 255, 256, 1000] {
+            let original: Vec<u32> = (0..len)
+                .map(|i| {
+                    if i % 17 == 0 {
+                        rng.r#gen()
+                    } else {
+                        rng.gen_range(0..64)
+                    }
+                })
+                .collect();
+            let encoded = compress_list(&original);
+            assert_eq!(decompress_list(&encoded).unwrap(), original);
+        }
+    }
+
+    #[test]
+    fn test_list_rejects_malformed_tail() {
+        let original: Vec<u32> = (0..200).collect();
+        let encoded = compress_list(&original);
+        assert_eq!(
+            decompress_list(&encoded[..encoded.len() - 1]),
+            Err(DecodeError::Truncated)
+        );
+
+        let mut extra = encoded.clone();
+        extra.push(5);
+        assert_eq!(decompress_list(&extra), Err(DecodeError::TrailingBytes));
+
+        // Block length pointing past the end of the input
+        let mut bad_len = encoded.clone();
+        bad_len[4..8].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert_eq!(decompress_list(&bad_len), Err(DecodeError::Truncated));
+    }
+
+    #[test]
+    fn test_sorted_list_roundtrip() {
+        let mut current = 5u32;
+        let original: Vec<u32> = (0..1000)
+            .map(|i| {
+                current += 1 + (i * 7) % 13;
+                current
+            })
+            .collect();
+        let encoded = compress_sorted_list(&original, 5).unwrap();
+        assert!(encoded.len() < compress_list(&original).len());
+        assert_eq!(decompress_sorted_list(&encoded, 5).unwrap(), original);
+    }
+
+    #[test]
+    fn test_sorted_list_next_geq() {
+        let mut rng = rand::thread_rng();
+        let mut current = 0u32;
+        let original: Vec<u32> = (0..1000)
+            .map(|_| {
+                current += rng.gen_range(0..50);
+                current
+            })
+            .collect();
+        let list = SortedList::new(&original).unwrap();
+        assert_eq!(list.len(), original.len());
+
+        for _ in 0..500 {
+            let target = rng.gen_range(0..current + 10);
+            let expected = original
+                .iter()
+                .position(|&v| v >= target)
+                .map(|i| (i, original[i]));
+            assert_eq!(list.next_geq(target), expected, "target {target}");
+        }
+        for (i, &value) in original.iter().enumerate() {
+            assert_eq!(list.get(i), Some(value));
+        }
+        assert_eq!(list.get(original.len()), None);
+    }
+
+    #[test]
+    fn test_sorted_list_edge_cases() {
+        let empty = SortedList::new(&[]).unwrap();
+        assert!(empty.is_empty());
+        assert_eq!(empty.next_geq(0), None);
+
+        let exact: Vec<u32> = (0..128).map(|i| i * 2).collect();
+        let list = SortedList::new(&exact).unwrap();
+        assert_eq!(list.next_geq(253), Some((127, 254)));
+        assert_eq!(list.next_geq(255), None);
+
+        let mut unsorted: Vec<u32> = (0..300).collect();
+        unsorted[200] = 0;
+        assert_eq!(
+            SortedList::new(&unsorted).unwrap_err(),
+            EncodeError::Unsorted { index: 200 }
+        );
+    }
+
+    #[test]
+    fn test_sorted_list_bytes_roundtrip() {
+        for len in [0, 1, 128, 300] {
+            let original: Vec<u32> = (0..len).map(|i| i * 7 + i % 3).collect();
+            let list = SortedList::new(&original).unwrap();
+            let loaded = SortedList::from_bytes(&list.to_bytes()).unwrap();
+            assert_eq!(loaded.len(), original.len());
+            for (i, &value) in original.iter().enumerate() {
+                assert_eq!(loaded.get(i), Some(value));
+            }
+            assert_eq!(loaded.next_geq(700), list.next_geq(700));
+        }
+    }
+
+    #[test]
+    fn test_sorted_list_rejects_corrupt_bytes() {
+        let original: Vec<u32> = (0..300).map(|i| i * 5).collect();
+        let bytes = SortedList::new(&original).unwrap().to_bytes();
+
+        assert_eq!(
+            SortedList::from_bytes(&bytes[..bytes.len() - 1]).unwrap_err(),
+            DecodeError::Truncated
+        );
+        let mut extra = bytes.clone();
+        extra.push(0);
+        assert_eq!(
+            SortedList::from_bytes(&extra).unwrap_err(),
+            DecodeError::TrailingBytes
+        );
+        // Claim a count the batches cannot back
+        let mut huge = bytes.clone();
+        huge[..4].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert_eq!(
+            SortedList::from_bytes(&huge).unwrap_err(),
+            DecodeError::Truncated
+        );
+        // An invalid bit width in the first batch header
+        let mut bad_width = bytes.clone();
+        bad_width[8] = 40;
+        assert_eq!(
+            SortedList::from_bytes(&bad_width).unwrap_err(),
+            DecodeError::InvalidHeader
+        );
+    }
+
+    #[test]
+    fn test_decode_into_reuses_buffer() {
+        let values: Vec<u32> = (0..128)
+            .map(|i| if i % 9 == 0 { 70_000 } else { i % 13 })
+            .collect();
+        let compressed = compress(&values);
+
+        let mut output = [0u32; 200];
+        assert_eq!(decode_into(&compressed, &mut output), Ok(BATCH_SIZE));
+        assert_eq!(&output[..BATCH_SIZE], &values[..]);
+
+        let mut short = [0u32; 127];
+        assert_eq!(decode_into(&compressed, &mut short), Err(DecodeError::OutputTooSmall));
+
+        let mut appended = vec![5];
+        assert_eq!(decode_append(&compressed, &mut appended), Ok(BATCH_SIZE));
+        assert_eq!(&appended[1..], &values[..]);
+        assert_eq!(decode_append(&compressed[..10], &mut appended), Err(DecodeError::Truncated));
+        assert_eq!(appended.len(), BATCH_SIZE + 1);
+    }
+
+    #[test]
+    fn test_signed_roundtrip() {
+        let values: Vec<i32> = (0..300)
+            .map(|i| match i % 50 {
+                0 => i32::MIN,
+                1 => i32::MAX,
+                _ => (i % 7) - 3,
+            })
+            .collect();
+        let compressed = compress_i32(&values);
+        assert_eq!(decompress_i32(&compressed).unwrap(), values);
+    }
 }
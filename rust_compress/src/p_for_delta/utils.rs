@@ -1,3 +1,5 @@
+#![allow(clippy::identity_op)]
+
 pub type DecompressorFn = fn(&[u32], &mut [u32]);
 
 // 32 values * 1 bit = 32 bits = 1 word
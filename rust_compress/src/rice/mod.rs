@@ -1,178 +1,323 @@
-/// A simple bit writer to pack bits into bytes
-struct BitWriter {
-    bytes: Vec<u8>,
-    current_byte: u8,
-    bit_pos: u8,
-}
-
-impl BitWriter {
-    fn new() -> Self {
-        BitWriter {
-            bytes: Vec::new(),
-            current_byte: 0,
-            bit_pos: 0,
-        }
-    }
-
-    fn write_bit(&mut self, bit: bool) {
-        if bit {
-            self.current_byte |= 1 << (7 - self.bit_pos);
-        }
-        self.bit_pos += 1;
-        if self.bit_pos == 8 {
-            self.bytes.push(self.current_byte);
-            self.current_byte = 0;
-            self.bit_pos = 0;
-        }
-    }
-
-    fn write_bits(&mut self, value: u32, num_bits: u8) {
-        for i in (0..num_bits).rev() {
-            self.write_bit((value >> i) & 1 == 1);
-        }
-    }
-
-    fn finish(mut self) -> Vec<u8> {
-        if self.bit_pos > 0 {
-            self.bytes.push(self.current_byte);
-        }
-        self.bytes
-    }
-}
-
-/// A simple bit reader to unpack bits from bytes
-struct BitReader {
-    bytes: Vec<u8>,
-    byte_pos: usize,
-    bit_pos: u8,
-}
+//! Rice coding: each value is its quotient by `2^k` in unary, then its low
+//! k bits.
+//!
+//! Quotients of [`ESCAPE_QUOTIENT`] or more are escaped to the value in 32
+//! bits. Streams written before the escape was added are only readable if
+//! every quotient is below [`ESCAPE_QUOTIENT`]: an old unary quotient of 32
+//! now reads as the escape, and a longer one fails with
+//! [`DecodeError::Overflow`].
 
-impl BitReader {
-    fn new(bytes: Vec<u8>) -> Self {
-        BitReader {
-            bytes,
-            byte_pos: 0,
-            bit_pos: 0,
-        }
-    }
-
-    fn read_bit(&mut self) -> Option<bool> {
-        if self.byte_pos >= self.bytes.len() {
-            return None;
-        }
-        let bit = (self.bytes[self.byte_pos] >> (7 - self.bit_pos)) & 1 == 1;
-        self.bit_pos += 1;
-        if self.bit_pos == 8 {
-            self.byte_pos += 1;
-            self.bit_pos = 0;
-        }
-        Some(bit)
-    }
-
-    fn read_bits(&mut self, num_bits: u8) -> Option<u32> {
-        let mut value = 0u32;
-        for _ in 0..num_bits {
-            let bit = self.read_bit()?;
-            value = (value << 1) | (bit as u32);
-        }
-        Some(value)
-    }
-}
+use crate::bit_io::{BitReader, BitWriter};
+use crate::codec::{IntegerCodec, count_field};
+use crate::error::DecodeError;
+use crate::stream;
 
 /// Compresses u32 numbers using Rice coding
 /// 
 /// # Arguments
 /// * `numbers` - Input array of u32 values to compress
-/// * `k` - Rice parameter (b = 2^k). If None, automatically calculated as 0.69 * average
+/// * `k` - Rice parameter (b = 2^k), at most 31. If None, automatically calculated as
+///   0.69 * average
 /// 
 /// # Returns
 /// Encoded byte array
+///
+/// Quotients of [`ESCAPE_QUOTIENT`] or more are not written in unary: the
+/// escape code (a unary [`ESCAPE_QUOTIENT`]) is followed by the value in
+/// 32 bits. No value takes more than 65 bits whatever `k` is, so the output
+/// never exceeds [`max_encoded_len`].
+///
+/// # Panics
+/// Panics if `k` is above 31.
 pub fn compress(numbers: &[u32], k: Option<u8>) -> Vec<u8> {
-    if numbers.is_empty() {
-        return vec![0]; // Store k=0 for empty input
+    if let Some(k) = k {
+        assert!(k <= 31, "Rice parameter must be at most 31");
     }
-
     // Calculate k if not provided (b = 2^k, where b ≈ 0.69 * average)
     let k = k.unwrap_or_else(|| {
+        if numbers.is_empty() {
+            return 0;
+        }
         let sum: u64 = numbers.iter().map(|&n| n as u64).sum();
         let avg = sum as f64 / numbers.len() as f64;
         let b = (0.69 * avg).max(1.0);
-        let k = (b.log2().ceil() as u8).max(0);
+        let k = b.log2().ceil() as u8;
         k.min(31) // Cap at 31 to prevent overflow
     });
 
     let mut writer = BitWriter::new();
     
     // Write the parameter k (5 bits, supports k from 0 to 31)
-    writer.write_bits(k as u32, 5);
+    writer.write_bits(k as u64, 5);
     
     // Write the number of values (32 bits)
-    writer.write_bits(numbers.len() as u32, 32);
+    writer.write_bits(count_field(numbers.len()) as u64, 32);
 
     // Encode each number
     for &n in numbers {
-        // q = n / 2^k (quotient)
-        let q = n >> k;
-        // r = n % 2^k (remainder)
-        let r = n & ((1 << k) - 1);
-
-        // Write quotient in unary: q zeros followed by a one
-        for _ in 0..q {
-            writer.write_bit(false);
-        }
-        writer.write_bit(true);
+        write_value(&mut writer, n, k);
+    }
+
+    writer.finish()
+}
+
+/// Unary quotient that escapes to a 32-bit binary value.
+pub const ESCAPE_QUOTIENT: u32 = 32;
+
+/// Largest value, in bits, of a single code: the escape prefix and its
+/// one, then 32 value bits.
+const MAX_VALUE_BITS: usize = ESCAPE_QUOTIENT as usize + 1 + 32;
+
+/// Upper bound on the size of [`compress`] output for `count` values, for
+/// any values and any `k`.
+pub fn max_encoded_len(count: usize) -> usize {
+    (5 + 32 + count * MAX_VALUE_BITS).div_ceil(8)
+}
+
+/// Upper bound on the size of [`compress_adaptive`] output for `count`
+/// values in blocks of `block_size`.
+pub fn max_encoded_len_adaptive(count: usize, block_size: usize) -> usize {
+    (32 + 32 + count.div_ceil(block_size) * 5 + count * MAX_VALUE_BITS).div_ceil(8)
+}
+
+/// Default number of values per block for [`compress_adaptive`].
+pub const DEFAULT_BLOCK_SIZE: usize = 128;
 
-        // Write remainder in binary (k bits)
-        writer.write_bits(r, k);
+/// Rice parameter giving the shortest encoding of `numbers`.
+///
+/// A value costs `(n >> k) + 1 + k` bits, or the fixed escape cost, so the
+/// exact total for every k is cheap to compute and no distribution is
+/// assumed.
+pub fn optimal_k(numbers: &[u32]) -> u8 {
+    (0..32u8)
+        .min_by_key(|&k| numbers.iter().map(|&n| value_bits(n, k)).sum::<u64>())
+        .unwrap()
+}
+
+/// Compresses u32 numbers using Rice coding with a separate parameter for
+/// each block of `block_size` values, chosen with [`optimal_k`].
+///
+/// This tracks gap distributions that drift along a long list, where the
+/// single parameter of [`compress`] fits neither end. The header holds the
+/// block size and the value count (32 bits each, MSB first); every block
+/// then starts with its own 5-bit k.
+///
+/// # Panics
+/// Panics if `block_size` is 0 or does not fit in 32 bits.
+pub fn compress_adaptive(numbers: &[u32], block_size: usize) -> Vec<u8> {
+    assert!(block_size > 0, "block size must be at least 1");
+    let block_size = u32::try_from(block_size).expect("block size must fit in 32 bits");
+
+    let mut writer = BitWriter::new();
+    writer.write_bits(block_size as u64, 32);
+    writer.write_bits(count_field(numbers.len()) as u64, 32);
+
+    for block in numbers.chunks(block_size as usize) {
+        let k = optimal_k(block);
+        writer.write_bits(k as u64, 5);
+        for &n in block {
+            write_value(&mut writer, n, k);
+        }
     }
 
     writer.finish()
 }
 
+/// Decompresses data produced by [`compress_adaptive`].
+pub fn decompress_adaptive(encoded: &[u8]) -> Result<Vec<u32>, DecodeError> {
+    let mut numbers = Vec::new();
+    decode_append_adaptive(encoded, &mut numbers)?;
+    Ok(numbers)
+}
+
+/// Decodes a stream produced by [`compress_adaptive`], appending the values
+/// to `output`. Returns the number of values appended.
+pub fn decode_append_adaptive(
+    encoded: &[u8],
+    output: &mut Vec<u32>,
+) -> Result<usize, DecodeError> {
+    let mut reader = BitReader::new(encoded);
+    let block_size = reader.read_bits(32).ok_or(DecodeError::Truncated)? as usize;
+    let count = reader.read_bits(32).ok_or(DecodeError::Truncated)? as usize;
+    if block_size == 0 {
+        return Err(DecodeError::InvalidHeader);
+    }
+
+    output.reserve(count.min(encoded.len() * 8));
+    let mut remaining = count;
+    while remaining > 0 {
+        let k = reader.read_bits(5).ok_or(DecodeError::Truncated)? as u8;
+        for _ in 0..remaining.min(block_size) {
+            output.push(read_value(&mut reader, k)?);
+        }
+        remaining -= remaining.min(block_size);
+    }
+
+    check_consumed(&reader, encoded)?;
+    Ok(count)
+}
+
 /// Decompresses Rice-coded data back to u32 numbers
 /// 
 /// # Arguments
 /// * `encoded` - Encoded byte array from compress()
 /// 
 /// # Returns
-/// Original array of u32 values, or None if decoding fails
-pub fn decompress(encoded: &[u8]) -> Option<Vec<u32>> {
-    if encoded.is_empty() {
-        return None;
+/// Original array of u32 values, or the reason the input is malformed
+pub fn decompress(encoded: &[u8]) -> Result<Vec<u32>, DecodeError> {
+    let mut numbers = Vec::new();
+    decode_append(encoded, &mut numbers)?;
+    Ok(numbers)
+}
+
+/// Decodes a stream produced by [`compress`], appending the values to
+/// `output`. Returns the number of values appended.
+pub fn decode_append(encoded: &[u8], output: &mut Vec<u32>) -> Result<usize, DecodeError> {
+    let mut reader = BitReader::new(encoded);
+    let (k, count) = read_header(&mut reader)?;
+
+    // Every value takes at least k + 1 bits, so a corrupt count cannot make us
+    // reserve more than the input could possibly hold
+    output.reserve(count.min(encoded.len() * 8 / (k as usize + 1)));
+    for _ in 0..count {
+        output.push(read_value(&mut reader, k)?);
     }
 
-    let mut reader = BitReader::new(encoded.to_vec());
+    check_consumed(&reader, encoded)?;
+    Ok(count)
+}
 
-    // Read the parameter k
-    let k = reader.read_bits(5)? as u8;
-    
-    // Read the number of values
-    let count = reader.read_bits(32)?;
+/// Decodes a stream produced by [`compress`] into `output` without
+/// allocating, and returns the number of values written. Fails with
+/// [`DecodeError::OutputTooSmall`] if the header announces more values than
+/// `output` can hold.
+pub fn decode_into(encoded: &[u8], output: &mut [u32]) -> Result<usize, DecodeError> {
+    let mut reader = BitReader::new(encoded);
+    let (k, count) = read_header(&mut reader)?;
 
-    let mut numbers = Vec::with_capacity(count as usize);
+    let output = output.get_mut(..count).ok_or(DecodeError::OutputTooSmall)?;
+    for n in output {
+        *n = read_value(&mut reader, k)?;
+    }
 
-    for _ in 0..count {
-        // Read quotient (unary coded: count zeros until we hit a one)
-        let mut q = 0u32;
-        loop {
-            let bit = reader.read_bit()?;
-            if bit {
-                break;
-            }
-            q += 1;
+    check_consumed(&reader, encoded)?;
+    Ok(count)
+}
+
+/// Reads the parameter k and the number of values.
+fn read_header(reader: &mut BitReader) -> Result<(u8, usize), DecodeError> {
+    let k = reader.read_bits(5).ok_or(DecodeError::Truncated)? as u8;
+    let count = reader.read_bits(32).ok_or(DecodeError::Truncated)? as usize;
+    Ok((k, count))
+}
+
+/// Number of bits [`write_value`] spends on `n`.
+fn value_bits(n: u32, k: u8) -> u64 {
+    let q = n >> k;
+    if q < ESCAPE_QUOTIENT {
+        q as u64 + 1 + k as u64
+    } else {
+        MAX_VALUE_BITS as u64
+    }
+}
+
+/// Writes one value with parameter `k`: `n >> k` in unary, then the low k
+/// bits in binary, or the escape code and all 32 bits of `n`.
+fn write_value(writer: &mut BitWriter, n: u32, k: u8) {
+    let q = n >> k;
+    if q < ESCAPE_QUOTIENT {
+        writer.write_unary(q as u64);
+        writer.write_bits(n as u64, k as u32);
+    } else {
+        writer.write_unary(ESCAPE_QUOTIENT as u64);
+        writer.write_bits(n as u64, 32);
+    }
+}
+
+/// Reads one Rice-coded value with parameter `k`.
+fn read_value(reader: &mut BitReader, k: u8) -> Result<u32, DecodeError> {
+    // Read quotient (unary coded: count zeros until we hit a one)
+    let q = reader.read_unary().ok_or(DecodeError::Truncated)?;
+    if q == ESCAPE_QUOTIENT as u64 {
+        let n = reader.read_bits(32).ok_or(DecodeError::Truncated)? as u32;
+        if n >> k < ESCAPE_QUOTIENT {
+            return Err(DecodeError::NonCanonical);
         }
+        return Ok(n);
+    }
+    // Longer prefixes are never written; like quotients past the u32 range
+    // they cannot stand for a value
+    if q > ESCAPE_QUOTIENT as u64 || q > (u32::MAX >> k) as u64 {
+        return Err(DecodeError::Overflow);
+    }
+
+    // Read remainder (k bits in binary)
+    let r = reader.read_bits(k as u32).ok_or(DecodeError::Truncated)?;
 
-        // Read remainder (k bits in binary)
-        let r = reader.read_bits(k)?;
+    // Reconstruct the number: n = q * 2^k + r
+    Ok(((q << k) | r) as u32)
+}
 
-        // Reconstruct the number: n = q * 2^k + r
-        let n = (q << k) | r;
-        numbers.push(n);
+/// Rejects input left over after the last value.
+fn check_consumed(reader: &BitReader, encoded: &[u8]) -> Result<(), DecodeError> {
+    if reader.bytes_consumed() != encoded.len() {
+        return Err(DecodeError::TrailingBytes);
     }
+    Ok(())
+}
 
-    Some(numbers)
+/// Rice coding as an [`IntegerCodec`]. `k` is the Rice parameter passed to
+/// [`compress`]; `None` derives it from the input.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Rice {
+    pub k: Option<u8>,
 }
 
+impl IntegerCodec for Rice {
+    type Value = u32;
+
+    fn encode(&self, input: &[u32], output: &mut Vec<u8>) {
+        output.extend_from_slice(&compress(input, self.k));
+    }
+
+    fn decode(&self, input: &[u8], output: &mut Vec<u32>) -> Result<usize, DecodeError> {
+        decode_append(input, output)
+    }
+}
+
+/// Block-adaptive Rice coding as an [`IntegerCodec`], see
+/// [`compress_adaptive`].
+#[derive(Debug, Clone, Copy)]
+pub struct AdaptiveRice {
+    pub block_size: usize,
+}
+
+impl Default for AdaptiveRice {
+    fn default() -> Self {
+        AdaptiveRice {
+            block_size: DEFAULT_BLOCK_SIZE,
+        }
+    }
+}
+
+impl IntegerCodec for AdaptiveRice {
+    type Value = u32;
+
+    fn encode(&self, input: &[u32], output: &mut Vec<u8>) {
+        output.extend_from_slice(&compress_adaptive(input, self.block_size));
+    }
+
+    fn decode(&self, input: &[u8], output: &mut Vec<u32>) -> Result<usize, DecodeError> {
+        decode_append_adaptive(input, output)
+    }
+}
+
+/// Streaming Rice encoder over a [`std::io::Write`], see [`stream::Encoder`].
+pub type Encoder<W> = stream::Encoder<W, Rice>;
+
+/// Streaming Rice decoder over a [`std::io::Read`], see [`stream::Decoder`].
+pub type Decoder<R> = stream::Decoder<R, Rice>;
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -216,4 +361,165 @@ mod tests {
         let decoded = decompress(&encoded).unwrap();
         assert_eq!(numbers, decoded);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_empty_input() {
+        let encoded = compress(&[], None);
+        let decoded = decompress(&encoded).unwrap();
+        assert!(decoded.is_empty());
+    }
+
+    #[test]
+    fn test_decompress_empty_input_is_truncated() {
+        assert_eq!(decompress(&[]), Err(DecodeError::Truncated));
+    }
+
+    #[test]
+    fn test_decompress_truncated_values() {
+        let numbers = vec![100, 200, 300, 400];
+        let encoded = compress(&numbers, Some(2));
+        let result = decompress(&encoded[..encoded.len() - 3]);
+        assert_eq!(result, Err(DecodeError::Truncated));
+    }
+
+    #[test]
+    fn test_decompress_trailing_bytes() {
+        let numbers = vec![1, 2, 3];
+        let mut encoded = compress(&numbers, Some(1));
+        encoded.push(0xFF);
+        assert_eq!(decompress(&encoded), Err(DecodeError::TrailingBytes));
+    }
+
+    #[test]
+    fn test_decompress_huge_count_does_not_allocate() {
+        // k = 0 followed by a count of u32::MAX and no payload
+        let encoded = [0x07, 0xFF, 0xFF, 0xFF, 0xF8];
+        assert_eq!(decompress(&encoded), Err(DecodeError::Truncated));
+    }
+
+    #[test]
+    fn test_decode_into_caller_buffer() {
+        let numbers = vec![3, 17, 0, 250, 9];
+        let encoded = compress(&numbers, Some(3));
+
+        let mut output = [0u32; 8];
+        assert_eq!(decode_into(&encoded, &mut output), Ok(5));
+        assert_eq!(&output[..5], &numbers[..]);
+
+        let mut short = [0u32; 4];
+        assert_eq!(decode_into(&encoded, &mut short), Err(DecodeError::OutputTooSmall));
+
+        let mut appended = vec![1];
+        assert_eq!(decode_append(&encoded, &mut appended), Ok(5));
+        assert_eq!(&appended[1..], &numbers[..]);
+    }
+
+    #[test]
+    fn test_optimal_k_is_exact() {
+        assert_eq!(optimal_k(&[]), 0);
+        assert_eq!(optimal_k(&[0, 0, 1]), 0);
+        assert_eq!(optimal_k(&[u32::MAX; 4]), 31);
+
+        let numbers: Vec<u32> = (0..300).map(|i| (i * 37) % 500).collect();
+        let best = optimal_k(&numbers);
+        let best_len = compress(&numbers, Some(best)).len();
+        for k in 0..32 {
+            assert!(best_len <= compress(&numbers, Some(k)).len(), "k = {k}");
+        }
+    }
+
+    #[test]
+    fn test_adaptive_roundtrip() {
+        let numbers: Vec<u32> = (0..1000u32)
+            .map(|i| i.wrapping_mul(2_654_435_761) >> (i / 40))
+            .collect();
+        for block_size in [1, 7, 128, 1000, 5000] {
+            let encoded = compress_adaptive(&numbers, block_size);
+            assert_eq!(decompress_adaptive(&encoded).unwrap(), numbers);
+        }
+        assert!(decompress_adaptive(&compress_adaptive(&[], 16)).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_adaptive_tracks_drifting_gaps() {
+        // Small gaps followed by large ones: one k cannot fit both halves
+        let mut numbers: Vec<u32> = (0..2000).map(|i| i % 4).collect();
+        numbers.extend((0..2000).map(|i| 100_000 + i * 13));
+        let single = compress(&numbers, None);
+        let adaptive = compress_adaptive(&numbers, DEFAULT_BLOCK_SIZE);
+        assert!(adaptive.len() * 3 < single.len() * 2);
+    }
+
+    #[test]
+    fn test_adaptive_rejects_malformed_streams() {
+        let numbers = vec![5, 90, 3, 1000, 7];
+        let encoded = compress_adaptive(&numbers, 2);
+        assert_eq!(decompress_adaptive(&encoded[..5]), Err(DecodeError::Truncated));
+        assert_eq!(
+            decompress_adaptive(&encoded[..encoded.len() - 1]),
+            Err(DecodeError::Truncated)
+        );
+
+        let mut extra = encoded.clone();
+        extra.push(0);
+        assert_eq!(decompress_adaptive(&extra), Err(DecodeError::TrailingBytes));
+
+        let mut zero_block = encoded.clone();
+        zero_block[..4].copy_from_slice(&[0; 4]);
+        assert_eq!(decompress_adaptive(&zero_block), Err(DecodeError::InvalidHeader));
+    }
+
+    #[test]
+    fn test_escape_bounds_large_quotients() {
+        // Without the escape, u32::MAX with k = 0 would take 512 MB
+        let numbers = vec![u32::MAX, 0, 31, 32, 1 << 20, u32::MAX - 1];
+        let encoded = compress(&numbers, Some(0));
+        assert!(encoded.len() <= max_encoded_len(numbers.len()));
+        assert_eq!(decompress(&encoded).unwrap(), numbers);
+
+        for k in 0..32 {
+            let encoded = compress(&numbers, Some(k));
+            assert!(encoded.len() <= max_encoded_len(numbers.len()), "k = {k}");
+            assert_eq!(decompress(&encoded).unwrap(), numbers, "k = {k}");
+        }
+
+        let encoded = compress_adaptive(&numbers, 4);
+        assert!(encoded.len() <= max_encoded_len_adaptive(numbers.len(), 4));
+        assert_eq!(decompress_adaptive(&encoded).unwrap(), numbers);
+    }
+
+    #[test]
+    fn test_escape_code_layout() {
+        // Quotient 31 is still unary; 32 takes the escape and 32 value bits
+        let encoded = compress(&[31, 32], Some(0));
+        let mut reader = BitReader::new(&encoded);
+        reader.read_bits(37).unwrap();
+        assert_eq!(reader.read_unary(), Some(31));
+        assert_eq!(reader.read_unary(), Some(ESCAPE_QUOTIENT as u64));
+        assert_eq!(reader.read_bits(32), Some(32));
+        // 37 header bits, 32 for the unary value and 65 for the escaped one
+        assert_eq!(encoded.len(), 17);
+    }
+
+    #[test]
+    fn test_rejects_malformed_escapes() {
+        let header = |writer: &mut BitWriter| {
+            writer.write_bits(2, 5);
+            writer.write_bits(1, 32);
+        };
+
+        // An escaped value that fits in the unary range
+        let mut writer = BitWriter::new();
+        header(&mut writer);
+        writer.write_unary(ESCAPE_QUOTIENT as u64);
+        writer.write_bits(100, 32);
+        assert_eq!(decompress(&writer.finish()), Err(DecodeError::NonCanonical));
+
+        // A prefix longer than the escape
+        let mut writer = BitWriter::new();
+        header(&mut writer);
+        writer.write_unary(ESCAPE_QUOTIENT as u64 + 1);
+        writer.write_bits(0, 2);
+        assert_eq!(decompress(&writer.finish()), Err(DecodeError::Overflow));
+    }
+}
@@ -0,0 +1,366 @@
+#[cfg(target_arch = "x86_64")]
+mod simd;
+
+use crate::bitpacking;
+use crate::codec::{IntegerCodec, read_count, write_count};
+use crate::error::DecodeError;
+use crate::p_for_delta::{COMPRESSORS, DECOMPRESSORS};
+use crate::stream;
+
+/// Number of values in a block.
+pub const BLOCK_SIZE: usize = 128;
+
+/// 32-bit lanes of a 128-bit register.
+const LANES: usize = 4;
+
+/// Packs one block of [`BLOCK_SIZE`] values with `bits` bits each in the
+/// SIMD-BP128 vertical layout, appending `16 * bits` bytes to `output`.
+/// Bits above `bits` are dropped.
+///
+/// Value `i` belongs to lane `i % 4`, and each lane packs its 32 values LSB
+/// first into `bits` u32 words. Word `w` of the four lanes forms the 16
+/// bytes at `16 * w`, lane 0 first, every u32 little endian. This is Lemire's
+/// layout, so one register load fetches the same word of every lane.
+///
+/// # Panics
+/// Panics if `values` is not exactly one block or `bits` is above 32.
+pub fn pack_block(values: &[u32], bits: u32, output: &mut Vec<u8>) {
+    assert_eq!(values.len(), BLOCK_SIZE, "a block holds exactly 128 values");
+    assert!(bits <= 32, "cannot pack more than 32 bits per value");
+    if bits == 0 {
+        return;
+    }
+    let start = output.len();
+    output.resize(start + 16 * bits as usize, 0);
+    let packed = &mut output[start..];
+
+    #[cfg(target_arch = "x86_64")]
+    {
+        // SAFETY: SSE2 is part of the x86_64 baseline, and the block and
+        // output sizes were checked above
+        unsafe { simd::pack_sse2(values, bits, packed) };
+    }
+    #[cfg(not(target_arch = "x86_64"))]
+    pack_scalar(values, bits, packed);
+}
+
+/// Unpacks one block written by [`pack_block`] into `output` and returns
+/// the number of bytes read (`16 * bits`).
+///
+/// Uses AVX2 when the CPU supports it, SSE2 otherwise on x86_64, and the
+/// scalar kernels elsewhere; all of them read the same layout.
+///
+/// # Panics
+/// Panics if `output` is not exactly one block or `bits` is above 32.
+pub fn unpack_block(input: &[u8], bits: u32, output: &mut [u32]) -> Result<usize, DecodeError> {
+    assert_eq!(output.len(), BLOCK_SIZE, "a block holds exactly 128 values");
+    assert!(bits <= 32, "cannot unpack more than 32 bits per value");
+    let len = 16 * bits as usize;
+    let input = input.get(..len).ok_or(DecodeError::Truncated)?;
+    if bits == 0 {
+        output.fill(0);
+        return Ok(0);
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    {
+        if is_x86_feature_detected!("avx2") {
+            // SAFETY: AVX2 support was just checked, and the block and
+            // input sizes were checked above
+            unsafe { simd::unpack_avx2(input, bits, output) };
+        } else {
+            // SAFETY: SSE2 is part of the x86_64 baseline
+            unsafe { simd::unpack_sse2(input, bits, output) };
+        }
+    }
+    #[cfg(not(target_arch = "x86_64"))]
+    unpack_scalar(input, bits, output);
+    Ok(len)
+}
+
+/// Scalar [`pack_block`] body: each lane goes through the 32-value kernel
+/// of its width, then the lane words are interleaved.
+#[cfg_attr(target_arch = "x86_64", allow(dead_code))]
+fn pack_scalar(values: &[u32], bits: u32, output: &mut [u8]) {
+    let bits = bits as usize;
+    let mut lane_values = [0u32; 32];
+    let mut lane_words = [0u32; 32];
+    for lane in 0..LANES {
+        for (row, value) in lane_values.iter_mut().enumerate() {
+            *value = values[LANES * row + lane];
+        }
+        COMPRESSORS[bits - 1](&lane_values, &mut lane_words[..bits]);
+        for (word, &lane_word) in lane_words[..bits].iter().enumerate() {
+            let at = 4 * (LANES * word + lane);
+            output[at..at + 4].copy_from_slice(&lane_word.to_le_bytes());
+        }
+    }
+}
+
+/// Scalar [`unpack_block`] body, the inverse of [`pack_scalar`].
+#[cfg_attr(target_arch = "x86_64", allow(dead_code))]
+fn unpack_scalar(input: &[u8], bits: u32, output: &mut [u32]) {
+    let bits = bits as usize;
+    let mut lane_words = [0u32; 32];
+    let mut lane_values = [0u32; 32];
+    for lane in 0..LANES {
+        for (word, lane_word) in lane_words[..bits].iter_mut().enumerate() {
+            let at = 4 * (LANES * word + lane);
+            *lane_word = u32::from_le_bytes(input[at..at + 4].try_into().unwrap());
+        }
+        DECOMPRESSORS[bits - 1](&lane_words[..bits], &mut lane_values);
+        for (row, &value) in lane_values.iter().enumerate() {
+            output[LANES * row + lane] = value;
+        }
+    }
+}
+
+/// Compresses u32 numbers with SIMD-BP128.
+///
+/// The stream holds the value count (4 bytes LE), then every full block as
+/// its bit width (1 byte) and [`pack_block`] output. Fewer than 128 values
+/// left at the end are stored as a bit width and [`bitpacking::pack`]
+/// output. Widths are those of the largest value in the block; there is no
+/// frame of reference, so delta-code sorted lists first.
+pub fn compress(numbers: &[u32]) -> Vec<u8> {
+    let mut output = Vec::new();
+    write_count(&mut output, numbers.len());
+
+    let mut blocks = numbers.chunks_exact(BLOCK_SIZE);
+    for block in &mut blocks {
+        let bits = bitpacking::max_bits(block);
+        output.push(bits as u8);
+        pack_block(block, bits, &mut output);
+    }
+
+    let tail = blocks.remainder();
+    if !tail.is_empty() {
+        let bits = bitpacking::max_bits(tail);
+        output.push(bits as u8);
+        bitpacking::pack(tail, bits, &mut output);
+    }
+    output
+}
+
+/// Decompresses data produced by [`compress`].
+pub fn decompress(encoded: &[u8]) -> Result<Vec<u32>, DecodeError> {
+    let mut numbers = Vec::new();
+    decode_append(encoded, &mut numbers)?;
+    Ok(numbers)
+}
+
+/// Decodes a stream produced by [`compress`], appending the values to
+/// `output`. Returns the number of values appended.
+pub fn decode_append(encoded: &[u8], output: &mut Vec<u32>) -> Result<usize, DecodeError> {
+    let (count, blocks) = read_header(encoded)?;
+    let start = output.len();
+    output.resize(start + count, 0);
+    if let Err(err) = decode_blocks(blocks, &mut output[start..]) {
+        output.truncate(start);
+        return Err(err);
+    }
+    Ok(count)
+}
+
+/// Decodes a stream produced by [`compress`] into `output` without
+/// allocating, and returns the number of values written. Fails with
+/// [`DecodeError::OutputTooSmall`] if the header announces more values than
+/// `output` can hold.
+pub fn decode_into(encoded: &[u8], output: &mut [u32]) -> Result<usize, DecodeError> {
+    let (count, blocks) = read_header(encoded)?;
+    let output = output.get_mut(..count).ok_or(DecodeError::OutputTooSmall)?;
+    decode_blocks(blocks, output)?;
+    Ok(count)
+}
+
+/// Splits off the value count, and checks that the input holds at least the
+/// width byte of every block before anything is allocated.
+fn read_header(encoded: &[u8]) -> Result<(usize, &[u8]), DecodeError> {
+    let (count, blocks) = read_count(encoded)?;
+    if count.div_ceil(BLOCK_SIZE) > blocks.len() {
+        return Err(DecodeError::Truncated);
+    }
+    Ok((count, blocks))
+}
+
+fn decode_blocks(mut blocks: &[u8], output: &mut [u32]) -> Result<(), DecodeError> {
+    for block in output.chunks_mut(BLOCK_SIZE) {
+        let (&bits, rest) = blocks.split_first().ok_or(DecodeError::Truncated)?;
+        let bits = bits as u32;
+        if bits > 32 {
+            return Err(DecodeError::InvalidHeader);
+        }
+        let read = if block.len() == BLOCK_SIZE {
+            unpack_block(rest, bits, block)?
+        } else {
+            bitpacking::unpack(rest, bits, block)?
+        };
+        blocks = &rest[read..];
+    }
+    if !blocks.is_empty() {
+        return Err(DecodeError::TrailingBytes);
+    }
+    Ok(())
+}
+
+/// SIMD-BP128 as an [`IntegerCodec`], using the [`compress`] stream.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SimdBp128;
+
+impl IntegerCodec for SimdBp128 {
+    type Value = u32;
+
+    fn encode(&self, input: &[u32], output: &mut Vec<u8>) {
+        output.extend_from_slice(&compress(input));
+    }
+
+    fn decode(&self, input: &[u8], output: &mut Vec<u32>) -> Result<usize, DecodeError> {
+        decode_append(input, output)
+    }
+}
+
+/// Streaming SIMD-BP128 encoder over a [`std::io::Write`], see [`stream::Encoder`].
+pub type Encoder<W> = stream::Encoder<W, SimdBp128>;
+
+/// Streaming SIMD-BP128 decoder over a [`std::io::Read`], see [`stream::Decoder`].
+pub type Decoder<R> = stream::Decoder<R, SimdBp128>;
+
+#[cfg(test)]
+mod tests {
+    use rand::Rng;
+
+    use super::*;
+
+    fn random_block(bits: u32) -> Vec<u32> {
+        let mut rng = rand::thread_rng();
+        (0..BLOCK_SIZE)
+            .map(|_| match bits {
+                0 => 0,
+                32 => rng.r#gen(),
+                _ => rng.gen_range(0..1u32 << bits),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_vertical_layout() {
+        // Value i sits in lane i % 4 at bit (i / 4) * bits of that lane
+        let bits = 5;
+        let block = random_block(bits);
+        let mut packed = Vec::new();
+        pack_block(&block, bits, &mut packed);
+        assert_eq!(packed.len(), 16 * bits as usize);
+
+        let lane_word = |word: usize, lane: usize| {
+            let at = 16 * word + 4 * lane;
+            u32::from_le_bytes(packed[at..at + 4].try_into().unwrap()) as u64
+        };
+        for (i, &value) in block.iter().enumerate() {
+            let (lane, bit) = (i % 4, (i / 4) * bits as usize);
+            let (word, offset) = (bit / 32, bit % 32);
+            let mut pair = lane_word(word, lane);
+            if word + 1 < bits as usize {
+                pair |= lane_word(word + 1, lane) << 32;
+            }
+            assert_eq!((pair >> offset) as u32 & 0x1F, value, "value {i}");
+        }
+    }
+
+    #[test]
+    fn test_simd_and_scalar_agree() {
+        for bits in 1..=32 {
+            let block = random_block(bits);
+            let mut packed = Vec::new();
+            pack_block(&block, bits, &mut packed);
+            let mut scalar = vec![0; packed.len()];
+            pack_scalar(&block, bits, &mut scalar);
+            assert_eq!(packed, scalar, "bits {bits}");
+
+            let mut unpacked = vec![0; BLOCK_SIZE];
+            assert_eq!(unpack_block(&packed, bits, &mut unpacked), Ok(packed.len()));
+            assert_eq!(unpacked, block, "bits {bits}");
+            let mut unpacked = vec![0; BLOCK_SIZE];
+            unpack_scalar(&packed, bits, &mut unpacked);
+            assert_eq!(unpacked, block, "bits {bits}");
+        }
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    #[test]
+    fn test_every_kernel_decodes_every_width() {
+        for bits in 1..=32 {
+            let block = random_block(bits);
+            let mut packed = Vec::new();
+            pack_block(&block, bits, &mut packed);
+
+            let mut unpacked = vec![0; BLOCK_SIZE];
+            // SAFETY: SSE2 is part of the x86_64 baseline
+            unsafe { simd::unpack_sse2(&packed, bits, &mut unpacked) };
+            assert_eq!(unpacked, block, "sse2, bits {bits}");
+
+            if is_x86_feature_detected!("avx2") {
+                let mut unpacked = vec![0; BLOCK_SIZE];
+                // SAFETY: AVX2 support was just checked
+                unsafe { simd::unpack_avx2(&packed, bits, &mut unpacked) };
+                assert_eq!(unpacked, block, "avx2, bits {bits}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let mut rng = rand::thread_rng();
+        for len in [0, 1, 127, 128, 129, 300, 1024, 5000] {
+            let numbers: Vec<u32> = (0..len)
+                .map(|i| rng.r#gen::<u32>() >> (i / 128 % 33).min(31))
+                .collect();
+            let encoded = compress(&numbers);
+            assert_eq!(decompress(&encoded).unwrap(), numbers, "len {len}");
+        }
+
+        let zeros = compress(&[0; 256]);
+        assert_eq!(zeros.len(), 4 + 2);
+        assert_eq!(decompress(&zeros).unwrap(), vec![0; 256]);
+    }
+
+    #[test]
+    fn test_rejects_malformed_streams() {
+        assert_eq!(decompress(&[]), Err(DecodeError::Truncated));
+
+        let numbers: Vec<u32> = (0..300).map(|i| i * 7).collect();
+        let encoded = compress(&numbers);
+        assert_eq!(
+            decompress(&encoded[..encoded.len() - 1]),
+            Err(DecodeError::Truncated)
+        );
+        assert_eq!(decompress(&encoded[..100]), Err(DecodeError::Truncated));
+
+        let mut extra = encoded.clone();
+        extra.push(0);
+        assert_eq!(decompress(&extra), Err(DecodeError::TrailingBytes));
+
+        let mut wide = encoded.clone();
+        wide[4] = 33;
+        assert_eq!(decompress(&wide), Err(DecodeError::InvalidHeader));
+
+        let mut huge = encoded[..8].to_vec();
+        huge[..4].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert_eq!(decompress(&huge), Err(DecodeError::Truncated));
+    }
+
+    #[test]
+    fn test_decode_into_caller_buffer() {
+        let numbers: Vec<u32> = (0..200).collect();
+        let encoded = compress(&numbers);
+
+        let mut output = [0u32; 256];
+        assert_eq!(decode_into(&encoded, &mut output), Ok(200));
+        assert_eq!(&output[..200], &numbers[..]);
+
+        let mut short = [0u32; 199];
+        assert_eq!(
+            decode_into(&encoded, &mut short),
+            Err(DecodeError::OutputTooSmall)
+        );
+    }
+}
@@ -0,0 +1,116 @@
+use std::arch::x86_64::*;
+
+use super::{BLOCK_SIZE, LANES};
+
+/// Rows of four values in a block; row `r` holds values `4r..4r + 4`.
+const ROWS: usize = BLOCK_SIZE / LANES;
+
+fn mask(bits: u32) -> i32 {
+    (u32::MAX >> (32 - bits)) as i32
+}
+
+/// Packs one block into `16 * bits` bytes of `output`, a row at a time:
+/// each row is shifted into the four lane words being filled, and the bits
+/// that overflow them start the next words.
+///
+/// # Safety
+/// The CPU must support SSE2, `bits` must be in `1..=32`, `values` must hold
+/// [`BLOCK_SIZE`] values and `output` at least `16 * bits` bytes.
+#[target_feature(enable = "sse2")]
+pub(super) unsafe fn pack_sse2(values: &[u32], bits: u32, output: &mut [u8]) {
+    let mask = _mm_set1_epi32(mask(bits));
+    let mut acc = _mm_setzero_si128();
+    let mut offset = 0;
+    let mut word = 0;
+    for row in 0..ROWS {
+        // SAFETY: row `row` is inside the block, and `word` stays below
+        // `bits`, so the store writes inside the `16 * bits` output bytes
+        unsafe {
+            let v = _mm_and_si128(
+                _mm_loadu_si128(values.as_ptr().add(LANES * row).cast()),
+                mask,
+            );
+            acc = _mm_or_si128(acc, _mm_sll_epi32(v, _mm_cvtsi32_si128(offset as i32)));
+            offset += bits;
+            if offset >= 32 {
+                _mm_storeu_si128(output.as_mut_ptr().add(16 * word).cast(), acc);
+                word += 1;
+                offset -= 32;
+                // Shifting by the full width leaves nothing to carry
+                acc = _mm_srl_epi32(v, _mm_cvtsi32_si128((bits - offset) as i32));
+            }
+        }
+    }
+}
+
+/// Unpacks one block of `16 * bits` bytes, a row at a time.
+///
+/// # Safety
+/// The CPU must support SSE2, `bits` must be in `1..=32`, `input` must hold
+/// at least `16 * bits` bytes and `output` [`BLOCK_SIZE`] values.
+#[target_feature(enable = "sse2")]
+pub(super) unsafe fn unpack_sse2(input: &[u8], bits: u32, output: &mut [u32]) {
+    let mask = _mm_set1_epi32(mask(bits));
+    let mut offset = 0;
+    let mut word = 0;
+    // SAFETY: the first word is inside `input`
+    let mut current = unsafe { _mm_loadu_si128(input.as_ptr().cast()) };
+    for row in 0..ROWS {
+        let mut v = _mm_srl_epi32(current, _mm_cvtsi32_si128(offset as i32));
+        offset += bits;
+        if offset >= 32 {
+            word += 1;
+            offset -= 32;
+            if word < bits as usize {
+                // SAFETY: `word` is below `bits`, so the load reads inside
+                // the `16 * bits` input bytes
+                current = unsafe { _mm_loadu_si128(input.as_ptr().add(16 * word).cast()) };
+                if offset > 0 {
+                    let back = _mm_cvtsi32_si128((bits - offset) as i32);
+                    v = _mm_or_si128(v, _mm_sll_epi32(current, back));
+                }
+            }
+        }
+        // SAFETY: row `row` is inside the block
+        unsafe {
+            let out = output.as_mut_ptr().add(LANES * row);
+            _mm_storeu_si128(out.cast(), _mm_and_si128(v, mask));
+        }
+    }
+}
+
+/// Unpacks one block of `16 * bits` bytes two rows at a time, each half of
+/// a 256-bit register decoding one row with its own shift. Variable shifts
+/// by 32 clear the lanes, so a row that does not straddle two words needs no
+/// branch.
+///
+/// # Safety
+/// The CPU must support AVX2, `bits` must be in `1..=32`, `input` must hold
+/// at least `16 * bits` bytes and `output` [`BLOCK_SIZE`] values.
+#[target_feature(enable = "avx2")]
+pub(super) unsafe fn unpack_avx2(input: &[u8], bits: u32, output: &mut [u32]) {
+    // One spare word so the next word of the last row can always be loaded
+    let mut words = [0u8; 16 * 33];
+    let len = 16 * bits as usize;
+    words[..len].copy_from_slice(&input[..len]);
+
+    let mask = _mm256_set1_epi32(mask(bits));
+    let word_at = |row: usize| (row * bits as usize / 32, (row * bits as usize % 32) as i32);
+    for pair in 0..ROWS / 2 {
+        let (w0, o0) = word_at(2 * pair);
+        let (w1, o1) = word_at(2 * pair + 1);
+        // SAFETY: `w0 + 1` and `w1 + 1` are at most `bits`, inside the
+        // padded copy, and the store writes rows `2 * pair` and
+        // `2 * pair + 1` of the block
+        unsafe {
+            let load = |word: usize| _mm_loadu_si128(words.as_ptr().add(16 * word).cast());
+            let low = _mm256_set_m128i(load(w1), load(w0));
+            let high = _mm256_set_m128i(load(w1 + 1), load(w0 + 1));
+            let shift = _mm256_setr_epi32(o0, o0, o0, o0, o1, o1, o1, o1);
+            let back = _mm256_sub_epi32(_mm256_set1_epi32(32), shift);
+            let v = _mm256_or_si256(_mm256_srlv_epi32(low, shift), _mm256_sllv_epi32(high, back));
+            let out = output.as_mut_ptr().add(2 * LANES * pair);
+            _mm256_storeu_si256(out.cast(), _mm256_and_si256(v, mask));
+        }
+    }
+}
@@ -1,3 +1,9 @@
+use crate::codec::{IntegerCodec, read_count, write_count};
+use crate::error::{DecodeError, EncodeError};
+use crate::indexed::WordCodec;
+use crate::stream;
+use crate::zigzag;
+
 #[derive(Debug, Clone, PartialEq)]
 pub(crate) struct Simple8bSelector {
     item_width: u32,
@@ -8,6 +14,7 @@ pub(crate) struct Simple8bSelector {
 const SELECTOR_MASK: u64 = 0x0000000F;
 const MAX_NUMBER_POSSIBLE: u64 = (1 << 60) - 1;
 const SELECTOR_BITS: u64 = 4;
+const MAX_ITEMS_PER_WORD: usize = 240;
 
 const SELECTORS: [Simple8bSelector; 16] = [
     Simple8bSelector {
@@ -92,7 +99,18 @@ const SELECTORS: [Simple8bSelector; 16] = [
     },
 ];
 
-pub fn compress(list: &Vec<u64>) -> Vec<u8> {
+/// Encodes `list` as Simple-8b words.
+///
+/// # Panics
+/// Panics if a value exceeds 2^60 - 1; use [`try_compress`] or
+/// [`compress_escaped`] for arbitrary input.
+pub fn compress(list: &[u64]) -> Vec<u8> {
+    try_compress(list).unwrap_or_else(|err| panic!("{err}"))
+}
+
+/// Encodes `list` as Simple-8b words, or reports the first value above
+/// 2^60 - 1.
+pub fn try_compress(list: &[u64]) -> Result<Vec<u8>, EncodeError> {
     let mut encoded_result: Vec<u8> = vec![];
 
     let n = list.len();
@@ -123,11 +141,14 @@ pub fn compress(list: &Vec<u64>) -> Vec<u8> {
 
                     while idx < n {
                         if list[idx] > MAX_NUMBER_POSSIBLE {
-                            panic!();
+                            return Err(EncodeError::ValueTooLarge {
+                                index: idx,
+                                value: list[idx],
+                            });
                         }
 
                         if no_of_items == selector.group_size
-                            || list[idx] > ((1u64 << selector.item_width) - 1) as u64
+                            || list[idx] > ((1u64 << selector.item_width) - 1)
                         {
                             break;
                         }
@@ -147,43 +168,102 @@ pub fn compress(list: &Vec<u64>) -> Vec<u8> {
         }
     }
 
-    encoded_result
+    Ok(encoded_result)
 }
 
-fn decompress_u64(data: u64, result: &mut Vec<u64>) {
-    let mut mut_data = data.clone();
+/// Decodes one word into `output` and returns how many values it holds.
+fn decompress_word(data: u64, output: &mut [u64; MAX_ITEMS_PER_WORD]) -> usize {
+    let mut mut_data = data;
     let selector_idx = data & SELECTOR_MASK;
-    mut_data = mut_data >> SELECTOR_BITS;
-    if selector_idx == 0 {
-        for _ in 0..240 {
-            result.push(1);
-        }
-        return;
-    }
-    if selector_idx == 1 {
-        for _ in 0..120 {
-            result.push(1);
-        }
-        return;
-    }
+    mut_data >>= SELECTOR_BITS;
     let selector = &SELECTORS[selector_idx as usize];
-    let mask = ((1u64 << selector.item_width) - 1) as u64;
-    for _ in 0..selector.group_size {
-        result.push(mut_data & mask);
-        mut_data = mut_data >> selector.item_width;
-    }
+    let values = &mut output[..selector.group_size as usize];
+    if selector_idx <= 1 {
+        // Runs of 240 or 120 ones
+        values.fill(1);
+        return values.len();
+    }
+    let mask = (1u64 << selector.item_width) - 1;
+    for value in values.iter_mut() {
+        *value = mut_data & mask;
+        mut_data >>= selector.item_width;
+    }
+    values.len()
+}
+
+fn decompress_u64(data: u64, result: &mut Vec<u64>) {
+    let mut word = [0u64; MAX_ITEMS_PER_WORD];
+    let no_of_items = decompress_word(data, &mut word);
+    result.extend_from_slice(&word[..no_of_items]);
 }
 
-pub fn decompress_from_bytes(bytes: &[u8]) -> Vec<u64> {
+pub fn decompress_from_bytes(bytes: &[u8]) -> Result<Vec<u64>, DecodeError> {
     let mut decoded_result: Vec<u64> = vec![];
+    decode_append(bytes, &mut decoded_result)?;
+    Ok(decoded_result)
+}
 
-    for chunk in bytes.chunks(8) {
-        let mut arr = [0u8; 8];
-        arr.copy_from_slice(chunk);
-        decompress_u64(u64::from_le_bytes(arr), &mut decoded_result);
+/// Decodes a word stream produced by [`compress`], appending every value
+/// (including the padding of the last word) to `output`. Returns the number
+/// of values appended.
+pub fn decode_append(bytes: &[u8], output: &mut Vec<u64>) -> Result<usize, DecodeError> {
+    if !bytes.len().is_multiple_of(8) {
+        return Err(DecodeError::Truncated);
     }
-    decoded_result
+    let start = output.len();
+
+    for chunk in bytes.chunks_exact(8) {
+        decompress_u64(u64::from_le_bytes(chunk.try_into().unwrap()), output);
+    }
+    Ok(output.len() - start)
 }
+
+/// Decodes a word stream produced by [`compress`] into `output` without
+/// allocating, and returns the number of values written.
+///
+/// Like [`decode_append`], every value of every word is written, including
+/// the padding of the last word: the raw stream cannot tell padding from
+/// trailing zeros, so none are dropped. Fails with
+/// [`DecodeError::OutputTooSmall`] if a decoded value does not fit.
+pub fn decode_into(bytes: &[u8], output: &mut [u64]) -> Result<usize, DecodeError> {
+    if !bytes.len().is_multiple_of(8) {
+        return Err(DecodeError::Truncated);
+    }
+    let mut word = [0u64; MAX_ITEMS_PER_WORD];
+    let mut written = 0;
+
+    for chunk in bytes.chunks_exact(8) {
+        let no_of_items = decompress_word(u64::from_le_bytes(chunk.try_into().unwrap()), &mut word);
+        let slots = output
+            .get_mut(written..written + no_of_items)
+            .ok_or(DecodeError::OutputTooSmall)?;
+        slots.copy_from_slice(&word[..no_of_items]);
+        written += no_of_items;
+    }
+    Ok(written)
+}
+
+/// Decodes just enough words to produce `count` values. Words left over
+/// after that are rejected, since the encoder never writes them.
+fn decompress_counted(bytes: &[u8], count: usize) -> Result<Vec<u64>, DecodeError> {
+    if !bytes.len().is_multiple_of(8) {
+        return Err(DecodeError::Truncated);
+    }
+    let mut decoded_result: Vec<u64> = Vec::with_capacity(count.min(bytes.len() / 8 * 240));
+    let mut chunks = bytes.chunks_exact(8);
+
+    while decoded_result.len() < count {
+        let chunk = chunks.next().ok_or(DecodeError::Truncated)?;
+        decompress_u64(u64::from_le_bytes(chunk.try_into().unwrap()), &mut decoded_result);
+    }
+    if chunks.next().is_some() {
+        return Err(DecodeError::TrailingBytes);
+    }
+
+    decoded_result.truncate(count);
+    Ok(decoded_result)
+}
+
 pub fn decompress(list: Vec<u64>) -> Vec<u64> {
     let mut decoded_result: Vec<u64> = vec![];
     for data in list {
@@ -193,6 +273,124 @@ pub fn decompress(list: Vec<u64>) -> Vec<u64> {
     decoded_result
 }
 
+/// Encodes any `list`, storing values above 2^60 - 1 in an exception
+/// area ahead of the Simple-8b words. Their slots in the word stream hold zero.
+///
+/// Layout (little endian): value count (u32), exception count (u32), one
+/// `(index: u32, value: u64)` pair per exception, then the words.
+pub fn compress_escaped(list: &[u64]) -> Vec<u8> {
+    let mut patched = list.to_vec();
+    let mut exceptions = vec![];
+    for (index, value) in patched.iter_mut().enumerate() {
+        if *value > MAX_NUMBER_POSSIBLE {
+            exceptions.push((index as u32, *value));
+            *value = 0;
+        }
+    }
+
+    let mut encoded_result = vec![];
+    write_count(&mut encoded_result, list.len());
+    write_count(&mut encoded_result, exceptions.len());
+    for (index, value) in exceptions {
+        encoded_result.extend_from_slice(&index.to_le_bytes());
+        encoded_result.extend_from_slice(&value.to_le_bytes());
+    }
+    encoded_result.extend_from_slice(&compress(&patched));
+    encoded_result
+}
+
+/// Decodes a stream produced by [`compress_escaped`], returning exactly the
+/// encoded values.
+pub fn decompress_escaped(bytes: &[u8]) -> Result<Vec<u64>, DecodeError> {
+    let (count, rest) = read_count(bytes)?;
+    let (no_of_exceptions, rest) = read_count(rest)?;
+    let exception_bytes = no_of_exceptions
+        .checked_mul(12)
+        .ok_or(DecodeError::InvalidHeader)?;
+    let (exceptions, words) = rest
+        .split_at_checked(exception_bytes)
+        .ok_or(DecodeError::Truncated)?;
+
+    let mut decoded_result = decompress_counted(words, count)?;
+    for exception in exceptions.chunks_exact(12) {
+        let index = u32::from_le_bytes(exception[..4].try_into().unwrap()) as usize;
+        let value = u64::from_le_bytes(exception[4..].try_into().unwrap());
+        let slot = decoded_result
+            .get_mut(index)
+            .ok_or(DecodeError::InvalidHeader)?;
+        *slot = value;
+    }
+
+    Ok(decoded_result)
+}
+
+/// Encodes signed values ZigZag-mapped with [`compress_escaped`], so any
+/// `i64` is accepted and values near zero pack densely.
+pub fn compress_i64(list: &[i64]) -> Vec<u8> {
+    let mapped: Vec<u64> = list.iter().map(|&value| zigzag::encode_i64(value)).collect();
+    compress_escaped(&mapped)
+}
+
+/// Decodes a stream produced by [`compress_i64`].
+pub fn decompress_i64(bytes: &[u8]) -> Result<Vec<i64>, DecodeError> {
+    let mapped = decompress_escaped(bytes)?;
+    Ok(mapped.into_iter().map(zigzag::decode_i64).collect())
+}
+
+/// Simple-8b as an [`IntegerCodec`]. The word stream is prefixed with the
+/// value count so decoding drops the padding of the last word.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Simple8b;
+
+impl IntegerCodec for Simple8b {
+    type Value = u64;
+
+    fn encode(&self, input: &[u64], output: &mut Vec<u8>) {
+        write_count(output, input.len());
+        output.extend_from_slice(&compress(input));
+    }
+
+    fn decode(&self, input: &[u8], output: &mut Vec<u64>) -> Result<usize, DecodeError> {
+        let (count, words) = read_count(input)?;
+        let decoded = decompress_counted(words, count)?;
+        output.extend_from_slice(&decoded);
+        Ok(count)
+    }
+}
+
+impl WordCodec for Simple8b {
+    type Word = u64;
+    type Value = u64;
+
+    fn encode_words(values: &[u64]) -> Result<Vec<u64>, EncodeError> {
+        let bytes = try_compress(values)?;
+        Ok(bytes
+            .chunks_exact(8)
+            .map(|chunk| u64::from_le_bytes(chunk.try_into().unwrap()))
+            .collect())
+    }
+
+    fn values_in_word(word: u64) -> usize {
+        SELECTORS[(word & SELECTOR_MASK) as usize].group_size as usize
+    }
+
+    fn value_in_word(word: u64, index: usize) -> u64 {
+        let selector = &SELECTORS[(word & SELECTOR_MASK) as usize];
+        if selector.item_width == 0 {
+            // Selectors 0 and 1 are runs of ones
+            return 1;
+        }
+        let shift = SELECTOR_BITS + index as u64 * selector.item_width as u64;
+        (word >> shift) & ((1u64 << selector.item_width) - 1)
+    }
+}
+
+/// Streaming Simple-8b encoder over a [`std::io::Write`], see [`stream::Encoder`].
+pub type Encoder<W> = stream::Encoder<W, Simple8b>;
+
+/// Streaming Simple-8b decoder over a [`std::io::Read`], see [`stream::Decoder`].
+pub type Decoder<R> = stream::Decoder<R, Simple8b>;
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -256,7 +454,7 @@ mod tests {
         // Values 0-3 fit in 2 bits (selector 3: 30 items)
         let original = vec![0, 1, 2, 3, 2, 1, 0, 3];
         let encoded = compress(&original);
-        let decoded = decompress_from_bytes(&encoded);
+        let decoded = decompress_from_bytes(&encoded).unwrap();
 
         assert!(decoded.len() >= original.len());
         assert_eq!(&decoded[..original.len()], &original[..]);
@@ -267,7 +465,7 @@ mod tests {
         // Values 0-7 fit in 3 bits (selector 4: 20 items)
         let original = vec![0, 1, 2, 3, 4, 5, 6, 7];
         let encoded = compress(&original);
-        let decoded = decompress_from_bytes(&encoded);
+        let decoded = decompress_from_bytes(&encoded).unwrap();
 
         assert!(decoded.len() >= original.len());
         assert_eq!(&decoded[..original.len()], &original[..]);
@@ -290,7 +488,7 @@ mod tests {
         // Values 0-31 fit in 5 bits (selector 6: 12 items)
         let original = vec![0, 10, 20, 31, 15, 25];
         let encoded = compress(&original);
-        let decoded = decompress_from_bytes(&encoded);
+        let decoded = decompress_from_bytes(&encoded).unwrap();
 
         assert!(decoded.len() >= original.len());
         assert_eq!(&decoded[..original.len()], &original[..]);
@@ -325,7 +523,7 @@ mod tests {
         // Values 0-255 fit in 8 bits (selector 9: 7 items)
         let original = vec![2, 100, 200, 255, 128, 64];
         let encoded = compress(&original);
-        let decoded = decompress_from_bytes(&encoded);
+        let decoded = decompress_from_bytes(&encoded).unwrap();
 
         assert!(decoded.len() >= original.len());
         assert_eq!(&decoded[..original.len()], &original[..]);
@@ -533,4 +731,108 @@ mod tests {
 
         assert_eq!(&decoded[..extended.len()], &extended[..]);
     }
+
+    #[test]
+    fn test_decode_truncated_word() {
+        let encoded = compress(&[1, 2, 3, 4000, 5]);
+        assert_eq!(
+            decompress_from_bytes(&encoded[..encoded.len() - 3]),
+            Err(DecodeError::Truncated)
+        );
+    }
+
+    #[test]
+    fn test_try_compress_reports_value_too_large() {
+        let list = vec![1, 2, MAX_NUMBER_POSSIBLE, MAX_NUMBER_POSSIBLE + 1, 5];
+        assert_eq!(
+            try_compress(&list),
+            Err(EncodeError::ValueTooLarge {
+                index: 3,
+                value: MAX_NUMBER_POSSIBLE + 1,
+            })
+        );
+        assert_eq!(try_compress(&list[..3]).unwrap(), compress(&list[..3]));
+    }
+
+    #[test]
+    fn test_escaped_roundtrip() {
+        let original = vec![u64::MAX, 1, 2, 3, MAX_NUMBER_POSSIBLE + 1, 7, MAX_NUMBER_POSSIBLE, 0, u64::MAX];
+        let encoded = compress_escaped(&original);
+        assert_eq!(decompress_escaped(&encoded).unwrap(), original);
+    }
+
+    #[test]
+    fn test_escaped_without_exceptions() {
+        let original: Vec<u64> = (0..100).collect();
+        let encoded = compress_escaped(&original);
+        assert_eq!(&encoded[8..], &compress(&original)[..]);
+        assert_eq!(decompress_escaped(&encoded).unwrap(), original);
+    }
+
+    #[test]
+    fn test_escaped_rejects_exception_out_of_range() {
+        let mut encoded = compress_escaped(&[u64::MAX]);
+        // Point the exception past the single encoded value
+        encoded[8] = 1;
+        assert_eq!(decompress_escaped(&encoded), Err(DecodeError::InvalidHeader));
+        assert_eq!(decompress_escaped(&encoded[..10]), Err(DecodeError::Truncated));
+    }
+
+    #[test]
+    fn test_escaped_rejects_extra_words() {
+        let original = vec![3, u64::MAX, 7, 1 << 61, 0, 42];
+        let mut encoded = compress_escaped(&original);
+        encoded.extend_from_slice(&compress(&[5]));
+        assert_eq!(decompress_escaped(&encoded), Err(DecodeError::TrailingBytes));
+
+        let mut encoded = Vec::new();
+        Simple8b.encode(&[1, 2, 3], &mut encoded);
+        encoded.extend_from_slice(&compress(&[5]));
+        assert_eq!(
+            Simple8b.decode(&encoded, &mut Vec::new()),
+            Err(DecodeError::TrailingBytes)
+        );
+    }
+
+    #[test]
+    fn test_decode_append_and_decode_into() {
+        let mut original: Vec<u64> = vec![1; 240];
+        original.extend((0..50).map(|i| i << 40));
+
+        let encoded = compress(&original);
+        let mut buffer = vec![];
+        let count = decode_append(&encoded, &mut buffer).unwrap();
+        assert_eq!(&buffer[..original.len()], &original[..]);
+
+        let mut output = vec![0u64; count];
+        assert_eq!(decode_into(&encoded, &mut output), Ok(count));
+        assert_eq!(output, buffer);
+
+        let mut short = vec![0u64; 200];
+        assert_eq!(
+            decode_into(&encoded, &mut short),
+            Err(DecodeError::OutputTooSmall)
+        );
+
+        // Trailing zeros may be real values, so the padding is written too
+        let encoded = compress(&[5, 0, 0]);
+        assert!(decompress_from_bytes(&encoded).unwrap().len() > 3);
+        let mut exact = [0u64; 3];
+        assert_eq!(
+            decode_into(&encoded, &mut exact),
+            Err(DecodeError::OutputTooSmall)
+        );
+    }
+
+    #[test]
+    fn test_signed_roundtrip() {
+        let mut original: Vec<i64> = (-300..300).collect();
+        original.extend([i64::MIN, i64::MAX, -(1 << 50)]);
+        let encoded = compress_i64(&original);
+        assert_eq!(decompress_i64(&encoded).unwrap(), original);
+
+        // Small magnitudes share words instead of taking one each
+        let small: Vec<i64> = (0..60).map(|i| if i % 2 == 0 { -1 } else { 1 }).collect();
+        assert_eq!(compress_i64(&small).len(), 8 + 8 * 2);
+    }
 }
@@ -1,4 +1,8 @@
 mod utils;
+use crate::codec::{IntegerCodec, read_count, write_count};
+use crate::error::{DecodeError, EncodeError};
+use crate::indexed::WordCodec;
+use crate::stream;
 use utils::*;
 #[derive(Debug, Clone, PartialEq)]
 pub(crate) struct Simple9Selector {
@@ -10,6 +14,7 @@ pub(crate) struct Simple9Selector {
 const SELECTOR_MASK: u32 = 0x000F;
 const MAX_NUMBER_POSSIBLE: u32 = (1 << 28) - 1;
 const SELECTOR_BITS: u32 = 4;
+const MAX_ITEMS_PER_WORD: usize = 28;
 const SELECTORS: [Simple9Selector; 9] = [
     Simple9Selector {
         no_of_items: 28,
@@ -58,7 +63,18 @@ const SELECTORS: [Simple9Selector; 9] = [
     },
 ];
 
-pub fn compress(list: &Vec<u32>) -> Vec<u8> {
+/// Encodes `list` as Simple-9 words.
+///
+/// # Panics
+/// Panics if a value exceeds 2^28 - 1; use [`try_compress`] or
+/// [`compress_escaped`] for arbitrary input.
+pub fn compress(list: &[u32]) -> Vec<u8> {
+    try_compress(list).unwrap_or_else(|err| panic!("{err}"))
+}
+
+/// Encodes `list` as Simple-9 words, or reports the first value above
+/// 2^28 - 1.
+pub fn try_compress(list: &[u32]) -> Result<Vec<u8>, EncodeError> {
     let mut encoded_result: Vec<u8> = vec![];
 
     let n = list.len();
@@ -72,12 +88,15 @@ pub fn compress(list: &Vec<u32>) -> Vec<u8> {
 
             while idx < n {
                 if list[idx] > MAX_NUMBER_POSSIBLE {
-                    panic!();
+                    return Err(EncodeError::ValueTooLarge {
+                        index: idx,
+                        value: list[idx] as u64,
+                    });
                 }
                 if no_of_items == selector.no_of_items {
                     break;
                 }
-                if (list[idx] as u32) > (((1u32 << selector.no_of_used_bits) - 1) as u32) {
+                if list[idx] > ((1u32 << selector.no_of_used_bits) - 1) {
                     break;
                 }
                 data |= list[idx] << (SELECTOR_BITS + shift);
@@ -94,72 +113,286 @@ pub fn compress(list: &Vec<u32>) -> Vec<u8> {
         }
     }
 
-    encoded_result
+    Ok(encoded_result)
 }
 
-fn decompress_u32(data: u32, decoded_result: &mut Vec<u32>) {
-    let mut mut_data = data.clone();
+/// Decodes one word into `output` and returns how many values it holds.
+fn decompress_word(
+    data: u32,
+    output: &mut [u32; MAX_ITEMS_PER_WORD],
+) -> Result<usize, DecodeError> {
+    let mut mut_data = data;
     let selector_idx = data & SELECTOR_MASK;
-    mut_data = mut_data >> SELECTOR_BITS;
+    mut_data >>= SELECTOR_BITS;
     match selector_idx {
         0 => {
-            decode_simple9_1bit(mut_data, decoded_result);
+            decode_simple9_1bit(mut_data, output);
         }
         1 => {
-            decode_simple9_2bit(mut_data, decoded_result);
+            decode_simple9_2bit(mut_data, output);
         }
         2 => {
-            decode_simple9_3bit(mut_data, decoded_result);
+            decode_simple9_3bit(mut_data, output);
         }
         3 => {
-            decode_simple9_4bit(mut_data, decoded_result);
+            decode_simple9_4bit(mut_data, output);
         }
         4 => {
-            decode_simple9_5bit(mut_data, decoded_result);
+            decode_simple9_5bit(mut_data, output);
         }
         5 => {
-            decode_simple9_7bit(mut_data, decoded_result);
+            decode_simple9_7bit(mut_data, output);
         }
         6 => {
-            decode_simple9_9bit(mut_data, decoded_result);
+            decode_simple9_9bit(mut_data, output);
         }
         7 => {
-            decode_simple9_14bit(mut_data, decoded_result);
+            decode_simple9_14bit(mut_data, output);
         }
         8 => {
-            decode_simple9_28bit(mut_data, decoded_result);
+            decode_simple9_28bit(mut_data, output);
         }
         _ => {
-            panic!();
+            return Err(DecodeError::InvalidSelector(selector_idx as u8));
         }
     }
-    // let mask = ((1u32 << selector.no_of_used_bits) - 1) as u32;
-    // for _ in 0..selector.no_of_items {
-    //     decoded_result.push(mut_data & mask);
-    //     decoded_result.ex
-    //     mut_data = mut_data >> selector.no_of_used_bits;
-    // }
+    Ok(SELECTORS[selector_idx as usize].no_of_items as usize)
 }
 
-pub fn decompress_from_bytes(bytes: &[u8]) -> Vec<u32> {
+fn decompress_u32(data: u32, decoded_result: &mut Vec<u32>) -> Result<(), DecodeError> {
+    let mut word = [0u32; MAX_ITEMS_PER_WORD];
+    let no_of_items = decompress_word(data, &mut word)?;
+    decoded_result.extend_from_slice(&word[..no_of_items]);
+    Ok(())
+}
+
+pub fn decompress_from_bytes(bytes: &[u8]) -> Result<Vec<u32>, DecodeError> {
     let mut decoded_result: Vec<u32> = vec![];
+    decode_append(bytes, &mut decoded_result)?;
+    Ok(decoded_result)
+}
+
+/// Decodes a word stream produced by [`compress`], appending every value
+/// (including the padding of the last word) to `output`. Returns the number
+/// of values appended; on error `output` may hold a decoded prefix.
+pub fn decode_append(bytes: &[u8], output: &mut Vec<u32>) -> Result<usize, DecodeError> {
+    if !bytes.len().is_multiple_of(4) {
+        return Err(DecodeError::Truncated);
+    }
+    let start = output.len();
 
-    for chunk in bytes.chunks(4) {
-        let mut bytes = [0u8; 4];
-        bytes.copy_from_slice(chunk);
-        decompress_u32(u32::from_le_bytes(bytes), &mut decoded_result);
+    for chunk in bytes.chunks_exact(4) {
+        decompress_u32(u32::from_le_bytes(chunk.try_into().unwrap()), output)?;
     }
 
-    decoded_result
+    Ok(output.len() - start)
 }
-pub fn decompress(list: Vec<u32>) -> Vec<u32> {
+
+/// Decodes a word stream produced by [`compress`] into `output` without
+/// allocating, and returns the number of values written.
+///
+/// Like [`decode_append`], every value of every word is written, including
+/// the padding of the last word: the raw stream cannot tell padding from
+/// trailing zeros, so none are dropped. Fails with
+/// [`DecodeError::OutputTooSmall`] if a decoded value does not fit; use
+/// [`decode_exact_into`] to get exactly the encoded values.
+pub fn decode_into(bytes: &[u8], output: &mut [u32]) -> Result<usize, DecodeError> {
+    if !bytes.len().is_multiple_of(4) {
+        return Err(DecodeError::Truncated);
+    }
+    let mut word = [0u32; MAX_ITEMS_PER_WORD];
+    let mut written = 0;
+
+    for chunk in bytes.chunks_exact(4) {
+        let data = u32::from_le_bytes(chunk.try_into().unwrap());
+        let no_of_items = decompress_word(data, &mut word)?;
+        let slots = output
+            .get_mut(written..written + no_of_items)
+            .ok_or(DecodeError::OutputTooSmall)?;
+        slots.copy_from_slice(&word[..no_of_items]);
+        written += no_of_items;
+    }
+
+    Ok(written)
+}
+
+pub fn decompress(list: Vec<u32>) -> Result<Vec<u32>, DecodeError> {
     let mut decoded_result: Vec<u32> = vec![];
     for data in list {
-        decompress_u32(data, &mut decoded_result);
+        decompress_u32(data, &mut decoded_result)?;
     }
-    decoded_result
+    Ok(decoded_result)
 }
 
+/// Encodes `list` as a self-describing stream: the value count (u32, little
+/// endian) followed by the Simple-9 words, so [`decompress_exact`] can drop the
+/// padding of the last word.
+///
+/// # Panics
+/// Panics if a value exceeds 2^28 - 1, like [`compress`].
+pub fn compress_exact(list: &[u32]) -> Vec<u8> {
+    let mut encoded_result = vec![];
+    write_count(&mut encoded_result, list.len());
+    encoded_result.extend_from_slice(&compress(list));
+    encoded_result
+}
+
+/// Decodes a stream produced by [`compress_exact`]. The result always holds
+/// exactly the encoded values.
+pub fn decompress_exact(bytes: &[u8]) -> Result<Vec<u32>, DecodeError> {
+    let (count, words) = read_count(bytes)?;
+    decompress_counted(words, count)
+}
+
+/// Decodes a stream produced by [`compress_exact`] into `output` without
+/// allocating, and returns the number of values written. Fails with
+/// [`DecodeError::OutputTooSmall`] if `output` cannot hold the encoded
+/// values.
+pub fn decode_exact_into(bytes: &[u8], output: &mut [u32]) -> Result<usize, DecodeError> {
+    let (count, words) = read_count(bytes)?;
+    let output = output.get_mut(..count).ok_or(DecodeError::OutputTooSmall)?;
+    if !words.len().is_multiple_of(4) {
+        return Err(DecodeError::Truncated);
+    }
+    let mut word = [0u32; MAX_ITEMS_PER_WORD];
+    let mut chunks = words.chunks_exact(4);
+    let mut written = 0;
+
+    while written < count {
+        let chunk = chunks.next().ok_or(DecodeError::Truncated)?;
+        let data = u32::from_le_bytes(chunk.try_into().unwrap());
+        let no_of_items = decompress_word(data, &mut word)?;
+        // Only the last word can run past `count`, into its padding
+        let fits = no_of_items.min(count - written);
+        output[written..written + fits].copy_from_slice(&word[..fits]);
+        written += fits;
+    }
+    if chunks.next().is_some() {
+        return Err(DecodeError::TrailingBytes);
+    }
+
+    Ok(count)
+}
+
+/// Decodes just enough words to produce `count` values. Words left over
+/// after that are rejected, since the encoder never writes them.
+fn decompress_counted(bytes: &[u8], count: usize) -> Result<Vec<u32>, DecodeError> {
+    if !bytes.len().is_multiple_of(4) {
+        return Err(DecodeError::Truncated);
+    }
+    let mut decoded_result: Vec<u32> = Vec::with_capacity(count.min(bytes.len() / 4 * 28));
+    let mut chunks = bytes.chunks_exact(4);
+
+    while decoded_result.len() < count {
+        let chunk = chunks.next().ok_or(DecodeError::Truncated)?;
+        decompress_u32(u32::from_le_bytes(chunk.try_into().unwrap()), &mut decoded_result)?;
+    }
+    if chunks.next().is_some() {
+        return Err(DecodeError::TrailingBytes);
+    }
+
+    decoded_result.truncate(count);
+    Ok(decoded_result)
+}
+
+/// Encodes any `list`, storing values above 2^28 - 1 in an exception
+/// area ahead of the Simple-9 words. Their slots in the word stream hold zero.
+///
+/// Layout (little endian): value count (u32), exception count (u32), one
+/// `(index: u32, value: u32)` pair per exception, then the words.
+pub fn compress_escaped(list: &[u32]) -> Vec<u8> {
+    let mut patched = list.to_vec();
+    let mut exceptions = vec![];
+    for (index, value) in patched.iter_mut().enumerate() {
+        if *value > MAX_NUMBER_POSSIBLE {
+            exceptions.push((index as u32, *value));
+            *value = 0;
+        }
+    }
+
+    let mut encoded_result = vec![];
+    write_count(&mut encoded_result, list.len());
+    write_count(&mut encoded_result, exceptions.len());
+    for (index, value) in exceptions {
+        encoded_result.extend_from_slice(&index.to_le_bytes());
+        encoded_result.extend_from_slice(&value.to_le_bytes());
+    }
+    encoded_result.extend_from_slice(&compress(&patched));
+    encoded_result
+}
+
+/// Decodes a stream produced by [`compress_escaped`], returning exactly the
+/// encoded values.
+pub fn decompress_escaped(bytes: &[u8]) -> Result<Vec<u32>, DecodeError> {
+    let (count, rest) = read_count(bytes)?;
+    let (no_of_exceptions, rest) = read_count(rest)?;
+    let exception_bytes = no_of_exceptions
+        .checked_mul(8)
+        .ok_or(DecodeError::InvalidHeader)?;
+    let (exceptions, words) = rest
+        .split_at_checked(exception_bytes)
+        .ok_or(DecodeError::Truncated)?;
+
+    let mut decoded_result = decompress_counted(words, count)?;
+    for exception in exceptions.chunks_exact(8) {
+        let index = u32::from_le_bytes(exception[..4].try_into().unwrap()) as usize;
+        let value = u32::from_le_bytes(exception[4..].try_into().unwrap());
+        let slot = decoded_result
+            .get_mut(index)
+            .ok_or(DecodeError::InvalidHeader)?;
+        *slot = value;
+    }
+
+    Ok(decoded_result)
+}
+
+/// Simple-9 as an [`IntegerCodec`], using the [`compress_exact`] stream.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Simple9;
+
+impl IntegerCodec for Simple9 {
+    type Value = u32;
+
+    fn encode(&self, input: &[u32], output: &mut Vec<u8>) {
+        output.extend_from_slice(&compress_exact(input));
+    }
+
+    fn decode(&self, input: &[u8], output: &mut Vec<u32>) -> Result<usize, DecodeError> {
+        let decoded = decompress_exact(input)?;
+        output.extend_from_slice(&decoded);
+        Ok(decoded.len())
+    }
+}
+
+impl WordCodec for Simple9 {
+    type Word = u32;
+    type Value = u32;
+
+    fn encode_words(values: &[u32]) -> Result<Vec<u32>, EncodeError> {
+        let bytes = try_compress(values)?;
+        Ok(bytes
+            .chunks_exact(4)
+            .map(|chunk| u32::from_le_bytes(chunk.try_into().unwrap()))
+            .collect())
+    }
+
+    fn values_in_word(word: u32) -> usize {
+        SELECTORS[(word & SELECTOR_MASK) as usize].no_of_items as usize
+    }
+
+    fn value_in_word(word: u32, index: usize) -> u32 {
+        let selector = &SELECTORS[(word & SELECTOR_MASK) as usize];
+        let shift = SELECTOR_BITS + index as u32 * selector.no_of_used_bits;
+        (word >> shift) & ((1u32 << selector.no_of_used_bits) - 1)
+    }
+}
+
+/// Streaming Simple-9 encoder over a [`std::io::Write`], see [`stream::Encoder`].
+pub type Encoder<W> = stream::Encoder<W, Simple9>;
+
+/// Streaming Simple-9 decoder over a [`std::io::Read`], see [`stream::Decoder`].
+pub type Decoder<R> = stream::Decoder<R, Simple9>;
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -223,7 +456,7 @@ mod tests {
             encoded_u32.push(u32::from_le_bytes(bytes));
         }
 
-        let decoded = decompress(encoded_u32);
+        let decoded = decompress(encoded_u32).unwrap();
 
         // Decoded may have padding zeros, so check prefix matches
         assert!(decoded.len() >= original.len());
@@ -243,7 +476,7 @@ mod tests {
             encoded_u32.push(u32::from_le_bytes(bytes));
         }
 
-        let decoded = decompress(encoded_u32);
+        let decoded = decompress(encoded_u32).unwrap();
 
         assert!(decoded.len() >= original.len());
         assert_eq!(&decoded[..original.len()], &original[..]);
@@ -262,7 +495,7 @@ mod tests {
             encoded_u32.push(u32::from_le_bytes(bytes));
         }
 
-        let decoded = decompress(encoded_u32);
+        let decoded = decompress(encoded_u32).unwrap();
 
         assert!(decoded.len() >= original.len());
         assert_eq!(&decoded[..original.len()], &original[..]);
@@ -281,7 +514,7 @@ mod tests {
             encoded_u32.push(u32::from_le_bytes(bytes));
         }
 
-        let decoded = decompress(encoded_u32);
+        let decoded = decompress(encoded_u32).unwrap();
 
         assert!(decoded.len() >= original.len());
         assert_eq!(&decoded[..original.len()], &original[..]);
@@ -300,7 +533,7 @@ mod tests {
             encoded_u32.push(u32::from_le_bytes(bytes));
         }
 
-        let decoded = decompress(encoded_u32);
+        let decoded = decompress(encoded_u32).unwrap();
 
         assert!(decoded.len() >= original.len());
         assert_eq!(&decoded[..original.len()], &original[..]);
@@ -319,7 +552,7 @@ mod tests {
             encoded_u32.push(u32::from_le_bytes(bytes));
         }
 
-        let decoded = decompress(encoded_u32);
+        let decoded = decompress(encoded_u32).unwrap();
 
         assert!(decoded.len() >= original.len());
         assert_eq!(&decoded[..original.len()], &original[..]);
@@ -338,7 +571,7 @@ mod tests {
             encoded_u32.push(u32::from_le_bytes(bytes));
         }
 
-        let decoded = decompress(encoded_u32);
+        let decoded = decompress(encoded_u32).unwrap();
 
         assert!(decoded.len() >= original.len());
         assert_eq!(&decoded[..original.len()], &original[..]);
@@ -357,7 +590,7 @@ mod tests {
             encoded_u32.push(u32::from_le_bytes(bytes));
         }
 
-        let decoded = decompress(encoded_u32);
+        let decoded = decompress(encoded_u32).unwrap();
         assert_eq!(&decoded[..28], &original[..]);
     }
 
@@ -374,8 +607,176 @@ mod tests {
             encoded_u32.push(u32::from_le_bytes(bytes));
         }
 
-        let decoded = decompress(encoded_u32);
+        let decoded = decompress(encoded_u32).unwrap();
         assert!(decoded.len() >= original.len());
         assert_eq!(&decoded[..original.len()], &original[..]);
     }
+
+    #[test]
+    fn test_decode_invalid_selector() {
+        // Selectors 9..=15 are unused by Simple-9
+        let encoded = 0x0000_0019u32.to_le_bytes();
+        assert_eq!(
+            decompress_from_bytes(&encoded),
+            Err(DecodeError::InvalidSelector(9))
+        );
+        assert_eq!(decompress(vec![0xF]), Err(DecodeError::InvalidSelector(15)));
+    }
+
+    #[test]
+    fn test_decode_truncated_word() {
+        let encoded = compress(&[1, 2, 3]);
+        assert_eq!(
+            decompress_from_bytes(&encoded[..3]),
+            Err(DecodeError::Truncated)
+        );
+    }
+
+    #[test]
+    fn test_try_compress_reports_value_too_large() {
+        let list = vec![1, 2, MAX_NUMBER_POSSIBLE, MAX_NUMBER_POSSIBLE + 1, 5];
+        assert_eq!(
+            try_compress(&list),
+            Err(EncodeError::ValueTooLarge {
+                index: 3,
+                value: (MAX_NUMBER_POSSIBLE + 1) as u64,
+            })
+        );
+        assert_eq!(try_compress(&list[..3]).unwrap(), compress(&list[..3]));
+    }
+
+    #[test]
+    fn test_escaped_roundtrip() {
+        let original = vec![u32::MAX, 1, 2, 3, MAX_NUMBER_POSSIBLE + 1, 7, MAX_NUMBER_POSSIBLE, 0, u32::MAX];
+        let encoded = compress_escaped(&original);
+        assert_eq!(decompress_escaped(&encoded).unwrap(), original);
+    }
+
+    #[test]
+    fn test_escaped_without_exceptions() {
+        let original: Vec<u32> = (0..100).collect();
+        let encoded = compress_escaped(&original);
+        assert_eq!(&encoded[8..], &compress(&original)[..]);
+        assert_eq!(decompress_escaped(&encoded).unwrap(), original);
+    }
+
+    #[test]
+    fn test_escaped_rejects_exception_out_of_range() {
+        let mut encoded = compress_escaped(&[u32::MAX]);
+        // Point the exception past the single encoded value
+        encoded[8] = 1;
+        assert_eq!(decompress_escaped(&encoded), Err(DecodeError::InvalidHeader));
+        assert_eq!(decompress_escaped(&encoded[..10]), Err(DecodeError::Truncated));
+    }
+
+    #[test]
+    fn test_exact_roundtrip_has_no_padding() {
+        for len in [0, 1, 5, 27, 28, 29, 100] {
+            let original: Vec<u32> = (0..len).map(|i| i % 3).collect();
+            let encoded = compress_exact(&original);
+            assert_eq!(decompress_exact(&encoded).unwrap(), original);
+        }
+    }
+
+    #[test]
+    fn test_exact_drops_padding_kept_by_raw_stream() {
+        let data = vec![1, 2, 3, 4, 5];
+        let raw = decompress_from_bytes(&compress(&data)).unwrap();
+        assert!(raw.len() > data.len());
+        assert_eq!(&raw[..data.len()], &data[..]);
+        assert_eq!(decompress_exact(&compress_exact(&data)).unwrap(), data);
+    }
+
+    #[test]
+    fn test_exact_rejects_missing_and_extra_words() {
+        let original = vec![1000, 2000, 3000, 4000];
+        let encoded = compress_exact(&original);
+        assert_eq!(
+            decompress_exact(&encoded[..encoded.len() - 4]),
+            Err(DecodeError::Truncated)
+        );
+
+        let mut extra = encoded.clone();
+        extra.extend_from_slice(&compress(&[1]));
+        assert_eq!(decompress_exact(&extra), Err(DecodeError::TrailingBytes));
+    }
+
+    #[test]
+    fn test_decode_append_reuses_buffer() {
+        let original: Vec<u32> = (0..100).collect();
+        let encoded = compress(&original);
+
+        let mut buffer = vec![7];
+        let count = decode_append(&encoded, &mut buffer).unwrap();
+        assert_eq!(count, buffer.len() - 1);
+        assert_eq!(buffer[0], 7);
+        assert_eq!(&buffer[1..101], &original[..]);
+    }
+
+    #[test]
+    fn test_decode_into_keeps_padding() {
+        let original = vec![1, 2, 3, 4, 5, 300, 7];
+        let encoded = compress(&original);
+        let raw = decompress_from_bytes(&encoded).unwrap();
+        assert!(raw.len() > original.len());
+
+        let mut large = [u32::MAX; 40];
+        assert_eq!(decode_into(&encoded, &mut large), Ok(raw.len()));
+        assert_eq!(&large[..raw.len()], &raw[..]);
+
+        // Trailing zeros may be real values, so they are never dropped
+        let mut output = [0u32; 7];
+        assert_eq!(
+            decode_into(&encoded, &mut output),
+            Err(DecodeError::OutputTooSmall)
+        );
+        assert_eq!(decode_exact_into(&compress_exact(&original), &mut output), Ok(7));
+        assert_eq!(output.to_vec(), original);
+    }
+
+    #[test]
+    fn test_decode_exact_into() {
+        let original: Vec<u32> = (0..100).map(|i| i % 5).collect();
+        let encoded = compress_exact(&original);
+
+        let mut output = [u32::MAX; 128];
+        assert_eq!(decode_exact_into(&encoded, &mut output), Ok(100));
+        assert_eq!(&output[..100], &original[..]);
+        assert!(output[100..].iter().all(|&value| value == u32::MAX));
+
+        let mut short = [0u32; 99];
+        assert_eq!(
+            decode_exact_into(&encoded, &mut short),
+            Err(DecodeError::OutputTooSmall)
+        );
+        let mut extra = encoded.clone();
+        extra.extend_from_slice(&compress(&[1]));
+        assert_eq!(
+            decode_exact_into(&extra, &mut output),
+            Err(DecodeError::TrailingBytes)
+        );
+        assert_eq!(
+            decode_exact_into(&encoded[..encoded.len() - 4], &mut output),
+            Err(DecodeError::Truncated)
+        );
+    }
+
+    #[test]
+    fn test_decode_into_output_too_small() {
+        let original: Vec<u32> = (0..60).collect();
+        let encoded = compress(&original);
+        let mut output = [0u32; 20];
+        assert_eq!(
+            decode_into(&encoded, &mut output),
+            Err(DecodeError::OutputTooSmall)
+        );
+
+        // The last word holds real values, not padding
+        let encoded = compress(&[9, 9, 9]);
+        let mut output = [0u32; 2];
+        assert_eq!(
+            decode_into(&encoded, &mut output),
+            Err(DecodeError::OutputTooSmall)
+        );
+    }
 }
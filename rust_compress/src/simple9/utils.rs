@@ -1,113 +1,115 @@
-pub fn decode_simple9_1bit(packed: u32, output: &mut Vec<u32>) {
+#![allow(clippy::identity_op)]
+
+pub fn decode_simple9_1bit(packed: u32, output: &mut [u32]) {
     // Each number is 1 bit, 28 numbers total
     // Extract bits 0-27 (last 28 bits)
-    output.push((packed >> 0) & 0b1);
-    output.push((packed >> 1) & 0b1);
-    output.push((packed >> 2) & 0b1);
-    output.push((packed >> 3) & 0b1);
-    output.push((packed >> 4) & 0b1);
-    output.push((packed >> 5) & 0b1);
-    output.push((packed >> 6) & 0b1);
-    output.push((packed >> 7) & 0b1);
-    output.push((packed >> 8) & 0b1);
-    output.push((packed >> 9) & 0b1);
-    output.push((packed >> 10) & 0b1);
-    output.push((packed >> 11) & 0b1);
-    output.push((packed >> 12) & 0b1);
-    output.push((packed >> 13) & 0b1);
-    output.push((packed >> 14) & 0b1);
-    output.push((packed >> 15) & 0b1);
-    output.push((packed >> 16) & 0b1);
-    output.push((packed >> 17) & 0b1);
-    output.push((packed >> 18) & 0b1);
-    output.push((packed >> 19) & 0b1);
-    output.push((packed >> 20) & 0b1);
-    output.push((packed >> 21) & 0b1);
-    output.push((packed >> 22) & 0b1);
-    output.push((packed >> 23) & 0b1);
-    output.push((packed >> 24) & 0b1);
-    output.push((packed >> 25) & 0b1);
-    output.push((packed >> 26) & 0b1);
-    output.push((packed >> 27) & 0b1);
+    output[0] = (packed >> 0) & 0b1;
+    output[1] = (packed >> 1) & 0b1;
+    output[2] = (packed >> 2) & 0b1;
+    output[3] = (packed >> 3) & 0b1;
+    output[4] = (packed >> 4) & 0b1;
+    output[5] = (packed >> 5) & 0b1;
+    output[6] = (packed >> 6) & 0b1;
+    output[7] = (packed >> 7) & 0b1;
+    output[8] = (packed >> 8) & 0b1;
+    output[9] = (packed >> 9) & 0b1;
+    output[10] = (packed >> 10) & 0b1;
+    output[11] = (packed >> 11) & 0b1;
+    output[12] = (packed >> 12) & 0b1;
+    output[13] = (packed >> 13) & 0b1;
+    output[14] = (packed >> 14) & 0b1;
+    output[15] = (packed >> 15) & 0b1;
+    output[16] = (packed >> 16) & 0b1;
+    output[17] = (packed >> 17) & 0b1;
+    output[18] = (packed >> 18) & 0b1;
+    output[19] = (packed >> 19) & 0b1;
+    output[20] = (packed >> 20) & 0b1;
+    output[21] = (packed >> 21) & 0b1;
+    output[22] = (packed >> 22) & 0b1;
+    output[23] = (packed >> 23) & 0b1;
+    output[24] = (packed >> 24) & 0b1;
+    output[25] = (packed >> 25) & 0b1;
+    output[26] = (packed >> 26) & 0b1;
+    output[27] = (packed >> 27) & 0b1;
 }
 
-pub fn decode_simple9_2bit(packed: u32, output: &mut Vec<u32>) {
+pub fn decode_simple9_2bit(packed: u32, output: &mut [u32]) {
     // Each number is 2 bits, 14 numbers total
     // Extract bits 0-27 (last 28 bits)
-    output.push((packed >> 0) & 0b11);
-    output.push((packed >> 2) & 0b11);
-    output.push((packed >> 4) & 0b11);
-    output.push((packed >> 6) & 0b11);
-    output.push((packed >> 8) & 0b11);
-    output.push((packed >> 10) & 0b11);
-    output.push((packed >> 12) & 0b11);
-    output.push((packed >> 14) & 0b11);
-    output.push((packed >> 16) & 0b11);
-    output.push((packed >> 18) & 0b11);
-    output.push((packed >> 20) & 0b11);
-    output.push((packed >> 22) & 0b11);
-    output.push((packed >> 24) & 0b11);
-    output.push((packed >> 26) & 0b11);
+    output[0] = (packed >> 0) & 0b11;
+    output[1] = (packed >> 2) & 0b11;
+    output[2] = (packed >> 4) & 0b11;
+    output[3] = (packed >> 6) & 0b11;
+    output[4] = (packed >> 8) & 0b11;
+    output[5] = (packed >> 10) & 0b11;
+    output[6] = (packed >> 12) & 0b11;
+    output[7] = (packed >> 14) & 0b11;
+    output[8] = (packed >> 16) & 0b11;
+    output[9] = (packed >> 18) & 0b11;
+    output[10] = (packed >> 20) & 0b11;
+    output[11] = (packed >> 22) & 0b11;
+    output[12] = (packed >> 24) & 0b11;
+    output[13] = (packed >> 26) & 0b11;
 }
 
-pub fn decode_simple9_3bit(packed: u32, output: &mut Vec<u32>) {
+pub fn decode_simple9_3bit(packed: u32, output: &mut [u32]) {
     // Each number is 3 bits, 9 numbers total
     // 9 * 3 = 27 bits used, 1 bit wasted
-    output.push((packed >> 0) & 0b111);
-    output.push((packed >> 3) & 0b111);
-    output.push((packed >> 6) & 0b111);
-    output.push((packed >> 9) & 0b111);
-    output.push((packed >> 12) & 0b111);
-    output.push((packed >> 15) & 0b111);
-    output.push((packed >> 18) & 0b111);
-    output.push((packed >> 21) & 0b111);
-    output.push((packed >> 24) & 0b111);
+    output[0] = (packed >> 0) & 0b111;
+    output[1] = (packed >> 3) & 0b111;
+    output[2] = (packed >> 6) & 0b111;
+    output[3] = (packed >> 9) & 0b111;
+    output[4] = (packed >> 12) & 0b111;
+    output[5] = (packed >> 15) & 0b111;
+    output[6] = (packed >> 18) & 0b111;
+    output[7] = (packed >> 21) & 0b111;
+    output[8] = (packed >> 24) & 0b111;
 }
 
-pub fn decode_simple9_4bit(packed: u32, output: &mut Vec<u32>) {
+pub fn decode_simple9_4bit(packed: u32, output: &mut [u32]) {
     // Each number is 4 bits, 7 numbers total
     // 7 * 4 = 28 bits used, 0 bits wasted
-    output.push((packed >> 0) & 0b1111);
-    output.push((packed >> 4) & 0b1111);
-    output.push((packed >> 8) & 0b1111);
-    output.push((packed >> 12) & 0b1111);
-    output.push((packed >> 16) & 0b1111);
-    output.push((packed >> 20) & 0b1111);
-    output.push((packed >> 24) & 0b1111);
+    output[0] = (packed >> 0) & 0b1111;
+    output[1] = (packed >> 4) & 0b1111;
+    output[2] = (packed >> 8) & 0b1111;
+    output[3] = (packed >> 12) & 0b1111;
+    output[4] = (packed >> 16) & 0b1111;
+    output[5] = (packed >> 20) & 0b1111;
+    output[6] = (packed >> 24) & 0b1111;
 }
 
-pub fn decode_simple9_5bit(packed: u32, output: &mut Vec<u32>) {
+pub fn decode_simple9_5bit(packed: u32, output: &mut [u32]) {
     // Each number is 5 bits, 5 numbers total
     // 5 * 5 = 25 bits used, 3 bits wasted
-    output.push((packed >> 0) & 0b11111);
-    output.push((packed >> 5) & 0b11111);
-    output.push((packed >> 10) & 0b11111);
-    output.push((packed >> 15) & 0b11111);
-    output.push((packed >> 20) & 0b11111);
+    output[0] = (packed >> 0) & 0b11111;
+    output[1] = (packed >> 5) & 0b11111;
+    output[2] = (packed >> 10) & 0b11111;
+    output[3] = (packed >> 15) & 0b11111;
+    output[4] = (packed >> 20) & 0b11111;
 }
 
-pub fn decode_simple9_7bit(packed: u32, output: &mut Vec<u32>) {
+pub fn decode_simple9_7bit(packed: u32, output: &mut [u32]) {
     // Each number is 7 bits, 4 numbers total
-    output.push((packed >> 0) & 0b1111111);
-    output.push((packed >> 7) & 0b1111111);
-    output.push((packed >> 14) & 0b1111111);
-    output.push((packed >> 21) & 0b1111111);
+    output[0] = (packed >> 0) & 0b1111111;
+    output[1] = (packed >> 7) & 0b1111111;
+    output[2] = (packed >> 14) & 0b1111111;
+    output[3] = (packed >> 21) & 0b1111111;
 }
 
-pub fn decode_simple9_9bit(packed: u32, output: &mut Vec<u32>) {
+pub fn decode_simple9_9bit(packed: u32, output: &mut [u32]) {
     // Each number is 9 bits, 3 numbers total
-    output.push((packed >> 0) & 0b111111111);
-    output.push((packed >> 9) & 0b111111111);
-    output.push((packed >> 18) & 0b111111111);
+    output[0] = (packed >> 0) & 0b111111111;
+    output[1] = (packed >> 9) & 0b111111111;
+    output[2] = (packed >> 18) & 0b111111111;
 }
 
-pub fn decode_simple9_14bit(packed: u32, output: &mut Vec<u32>) {
+pub fn decode_simple9_14bit(packed: u32, output: &mut [u32]) {
     // Each number is 14 bits, 2 numbers total
-    output.push((packed >> 0) & 0b11111111111111);
-    output.push((packed >> 14) & 0b11111111111111);
+    output[0] = (packed >> 0) & 0b11111111111111;
+    output[1] = (packed >> 14) & 0b11111111111111;
 }
 
-pub fn decode_simple9_28bit(packed: u32, output: &mut Vec<u32>) {
-    // Each number is 14 bits, 2 numbers total
-    output.push(packed);
+pub fn decode_simple9_28bit(packed: u32, output: &mut [u32]) {
+    // Each number is 28 bits, 1 number total
+    output[0] = packed;
 }
\ No newline at end of file
@@ -0,0 +1,301 @@
+use std::io::{self, Read, Write};
+use std::iter::FusedIterator;
+
+use crate::codec::IntegerCodec;
+use crate::error::DecodeError;
+
+/// Number of values buffered per frame when none is given.
+pub const DEFAULT_FRAME_LEN: usize = 1024;
+
+/// Incremental encoder writing any [`IntegerCodec`] to a [`Write`].
+///
+/// Values are buffered until a frame of `frame_len` values is complete, then
+/// encoded and written as `[byte length: u32 LE][codec bytes]`. `finish`
+/// writes the last partial frame and a zero-length end marker, so memory use
+/// is bounded by one frame regardless of the list length.
+///
+/// Each module has an alias for its codec, e.g. `simple9::Encoder<W>`.
+pub struct Encoder<W: Write, C: IntegerCodec> {
+    writer: W,
+    codec: C,
+    frame_len: usize,
+    pending: Vec<C::Value>,
+    scratch: Vec<u8>,
+}
+
+impl<W: Write, C: IntegerCodec + Default> Encoder<W, C> {
+    /// Creates an encoder with [`DEFAULT_FRAME_LEN`] values per frame.
+    pub fn new(writer: W) -> Self {
+        Self::with_codec(writer, C::default(), DEFAULT_FRAME_LEN)
+    }
+}
+
+impl<W: Write, C: IntegerCodec> Encoder<W, C> {
+    /// Creates an encoder using a configured `codec` (e.g. a Rice parameter)
+    /// and `frame_len` values per frame.
+    ///
+    /// # Panics
+    /// Panics if `frame_len` is zero.
+    pub fn with_codec(writer: W, codec: C, frame_len: usize) -> Self {
+        assert!(frame_len > 0, "frame must hold at least one value");
+        Encoder {
+            writer,
+            codec,
+            frame_len,
+            pending: Vec::with_capacity(frame_len),
+            scratch: Vec::new(),
+        }
+    }
+
+    /// Queues one value, writing a frame once `frame_len` values are pending.
+    pub fn write(&mut self, value: C::Value) -> io::Result<()> {
+        self.pending.push(value);
+        if self.pending.len() == self.frame_len {
+            self.flush_frame()?;
+        }
+        Ok(())
+    }
+
+    /// Queues every value of `values`.
+    pub fn write_all(&mut self, values: &[C::Value]) -> io::Result<()> {
+        for &value in values {
+            self.write(value)?;
+        }
+        Ok(())
+    }
+
+    /// Writes the pending values and the end marker, returning the writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        if !self.pending.is_empty() {
+            self.flush_frame()?;
+        }
+        self.writer.write_all(&0u32.to_le_bytes())?;
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+
+    fn flush_frame(&mut self) -> io::Result<()> {
+        self.scratch.clear();
+        self.codec.encode(&self.pending, &mut self.scratch);
+        self.pending.clear();
+
+        let frame_len = u32::try_from(self.scratch.len())
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "frame too large"))?;
+        self.writer.write_all(&frame_len.to_le_bytes())?;
+        self.writer.write_all(&self.scratch)
+    }
+}
+
+/// Incremental decoder reading a stream written by [`Encoder`] from a
+/// [`Read`], one frame at a time.
+///
+/// Malformed frames surface as [`io::ErrorKind::InvalidData`] errors wrapping
+/// a [`DecodeError`]; a stream that ends without the end marker is reported
+/// as [`DecodeError::Truncated`]. The first error ends the stream, since the
+/// reader may be left in the middle of a frame: after it, `read_frame`
+/// returns 0 and the iterator yields `None`.
+pub struct Decoder<R: Read, C: IntegerCodec> {
+    reader: R,
+    codec: C,
+    frame: Vec<u8>,
+    values: Vec<C::Value>,
+    position: usize,
+    finished: bool,
+}
+
+impl<R: Read, C: IntegerCodec + Default> Decoder<R, C> {
+    pub fn new(reader: R) -> Self {
+        Self::with_codec(reader, C::default())
+    }
+}
+
+impl<R: Read, C: IntegerCodec> Decoder<R, C> {
+    /// Creates a decoder using the same `codec` configuration as the encoder.
+    pub fn with_codec(reader: R, codec: C) -> Self {
+        Decoder {
+            reader,
+            codec,
+            frame: Vec::new(),
+            values: Vec::new(),
+            position: 0,
+            finished: false,
+        }
+    }
+
+    /// Decodes the next frame and appends its values to `output`. Returns the
+    /// number of values appended, or 0 once the end marker has been read.
+    pub fn read_frame(&mut self, output: &mut Vec<C::Value>) -> io::Result<usize> {
+        // Hand out values left over from a frame partially consumed by `next`
+        if self.position < self.values.len() {
+            output.extend_from_slice(&self.values[self.position..]);
+            let count = self.values.len() - self.position;
+            self.values.clear();
+            self.position = 0;
+            return Ok(count);
+        }
+        self.read_next_frame(output)
+    }
+
+    fn read_next_frame(&mut self, output: &mut Vec<C::Value>) -> io::Result<usize> {
+        if self.finished {
+            return Ok(0);
+        }
+        let start = output.len();
+        let result = self.read_frame_body(output);
+        if result.is_err() {
+            self.finished = true;
+            output.truncate(start);
+        }
+        result
+    }
+
+    fn read_frame_body(&mut self, output: &mut Vec<C::Value>) -> io::Result<usize> {
+        let mut len_bytes = [0u8; 4];
+        self.reader
+            .read_exact(&mut len_bytes)
+            .map_err(|err| match err.kind() {
+                io::ErrorKind::UnexpectedEof => invalid_data(DecodeError::Truncated),
+                _ => err,
+            })?;
+        let frame_len = u32::from_le_bytes(len_bytes) as usize;
+        if frame_len == 0 {
+            self.finished = true;
+            return Ok(0);
+        }
+
+        // `take` keeps a corrupt length from reserving memory up front
+        self.frame.clear();
+        (&mut self.reader)
+            .take(frame_len as u64)
+            .read_to_end(&mut self.frame)?;
+        if self.frame.len() < frame_len {
+            return Err(invalid_data(DecodeError::Truncated));
+        }
+
+        self.codec.decode(&self.frame, output).map_err(invalid_data)
+    }
+}
+
+impl<R: Read, C: IntegerCodec> Iterator for Decoder<R, C> {
+    type Item = io::Result<C::Value>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.position == self.values.len() {
+            if self.finished {
+                return None;
+            }
+            let mut values = std::mem::take(&mut self.values);
+            values.clear();
+            self.position = 0;
+            let result = self.read_next_frame(&mut values);
+            self.values = values;
+            if let Err(err) = result {
+                return Some(Err(err));
+            }
+        }
+        let value = self.values[self.position];
+        self.position += 1;
+        Some(Ok(value))
+    }
+}
+
+impl<R: Read, C: IntegerCodec> FusedIterator for Decoder<R, C> {}
+
+fn invalid_data(err: DecodeError) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, err)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::p_for_delta::PForDelta;
+    use crate::rice::Rice;
+    use crate::simple8b::Simple8b;
+    use crate::simple9::Simple9;
+    use crate::simple16::Simple16;
+    use crate::var_byte::VarByte;
+
+    fn roundtrip<C>(codec: C, values: &[C::Value], frame_len: usize) -> Vec<C::Value>
+    where
+        C: IntegerCodec + Clone,
+    {
+        let mut encoder = Encoder::with_codec(Vec::new(), codec.clone(), frame_len);
+        encoder.write_all(values).unwrap();
+        let bytes = encoder.finish().unwrap();
+
+        let decoder = Decoder::with_codec(&bytes[..], codec);
+        decoder.collect::<io::Result<Vec<_>>>().unwrap()
+    }
+
+    #[test]
+    fn test_every_codec_roundtrips() {
+        let values: Vec<u32> = (0..5000u32).map(|i| (i * 37) % 1000).collect();
+        for frame_len in [1, 7, 128, DEFAULT_FRAME_LEN, 10_000] {
+            assert_eq!(roundtrip(VarByte, &values, frame_len), values);
+            assert_eq!(roundtrip(Simple9, &values, frame_len), values);
+            assert_eq!(roundtrip(Simple16, &values, frame_len), values);
+            assert_eq!(roundtrip(Rice { k: Some(6) }, &values, frame_len), values);
+            assert_eq!(roundtrip(PForDelta, &values, frame_len), values);
+        }
+        let wide: Vec<u64> = values.iter().map(|&v| (v as u64) << 30).collect();
+        assert_eq!(roundtrip(Simple8b, &wide, 300), wide);
+    }
+
+    #[test]
+    fn test_empty_stream() {
+        let bytes = Encoder::<_, Simple9>::new(Vec::new()).finish().unwrap();
+        assert_eq!(bytes, 0u32.to_le_bytes());
+        let mut decoder = Decoder::<_, Simple9>::new(&bytes[..]);
+        assert!(decoder.next().is_none());
+    }
+
+    #[test]
+    fn test_read_frame() {
+        let mut encoder = Encoder::with_codec(Vec::new(), VarByte, 3);
+        encoder.write_all(&[1, 2, 3, 4, 5]).unwrap();
+        let bytes = encoder.finish().unwrap();
+
+        let mut decoder = Decoder::<_, VarByte>::new(&bytes[..]);
+        let mut output = Vec::new();
+        assert_eq!(decoder.read_frame(&mut output).unwrap(), 3);
+        assert_eq!(decoder.read_frame(&mut output).unwrap(), 2);
+        assert_eq!(decoder.read_frame(&mut output).unwrap(), 0);
+        assert_eq!(output, vec![1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn test_missing_end_marker_is_truncated() {
+        let mut encoder = Encoder::with_codec(Vec::new(), Simple16, 4);
+        encoder.write_all(&[1, 2, 3, 4, 5, 6]).unwrap();
+        let bytes = encoder.finish().unwrap();
+
+        for cut in [bytes.len() - 4, bytes.len() - 6] {
+            let err = Decoder::<_, Simple16>::new(&bytes[..cut])
+                .collect::<io::Result<Vec<_>>>()
+                .unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+            let inner = err.into_inner().unwrap();
+            assert_eq!(
+                inner.downcast_ref::<DecodeError>(),
+                Some(&DecodeError::Truncated)
+            );
+        }
+    }
+
+    #[test]
+    fn test_error_ends_the_stream() {
+        let mut encoder = Encoder::with_codec(Vec::new(), Simple9, 4);
+        encoder.write_all(&[1, 2, 3, 4, 5, 6, 7, 8]).unwrap();
+        let mut bytes = encoder.finish().unwrap();
+        // Break the selector of the first frame's only word
+        bytes[8] = 0x0F;
+
+        let mut decoder = Decoder::<_, Simple9>::new(&bytes[..]);
+        let err = decoder.next().unwrap().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        // The second frame is intact, but the reader state cannot be trusted
+        assert!(decoder.next().is_none());
+        assert!(decoder.next().is_none());
+        assert_eq!(decoder.read_frame(&mut Vec::new()).unwrap(), 0);
+    }
+}
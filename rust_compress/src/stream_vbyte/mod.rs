@@ -0,0 +1,409 @@
+#[cfg(target_arch = "x86_64")]
+mod simd;
+
+use crate::codec::{IntegerCodec, read_count, write_count};
+use crate::error::DecodeError;
+use crate::stream;
+
+const GROUP_SIZE: usize = 4;
+
+/// Byte length of the data of each control byte, counting every one of its
+/// four values.
+static LENGTHS: [u8; 256] = build_lengths();
+
+const fn build_lengths() -> [u8; 256] {
+    let mut table = [0; 256];
+    let mut control = 0;
+    while control < 256 {
+        let mut i = 0;
+        while i < GROUP_SIZE {
+            table[control] += ((control >> (2 * i)) & 3) as u8 + 1;
+            i += 1;
+        }
+        control += 1;
+    }
+    table
+}
+
+/// Number of bytes needed to store `value` (1 to 4).
+fn byte_len(value: u32) -> usize {
+    ((32 - value.leading_zeros()).max(1) as usize).div_ceil(8)
+}
+
+/// Encodes `values` with the Stream VByte layout: the value count (u32,
+/// little endian), then one control byte per four values (two bits per
+/// value holding its byte length minus one, first value in the low bits),
+/// then all values as 1 to 4 little-endian data bytes.
+///
+/// Keeping the control bytes apart from the data lets the decoder find the
+/// layout of four values from one byte and move them in place with a single
+/// shuffle. The control bits of the missing values of a last partial group
+/// are zero.
+pub fn compress(values: &[u32]) -> Vec<u8> {
+    let mut result = Vec::new();
+    write_count(&mut result, values.len());
+
+    let control_start = result.len();
+    result.resize(control_start + values.len().div_ceil(GROUP_SIZE), 0);
+    for (i, &value) in values.iter().enumerate() {
+        let len = byte_len(value);
+        result[control_start + i / GROUP_SIZE] |= ((len - 1) as u8) << (2 * (i % GROUP_SIZE));
+        result.extend_from_slice(&value.to_le_bytes()[..len]);
+    }
+
+    result
+}
+
+/// Decodes a stream produced by [`compress`].
+///
+/// Uses SSSE3 shuffles when the CPU supports them and a scalar loop
+/// otherwise; both give the same result.
+pub fn decompress(bytes: &[u8]) -> Result<Vec<u32>, DecodeError> {
+    let mut result = Vec::new();
+    decode_append(bytes, &mut result)?;
+    Ok(result)
+}
+
+/// Decodes a stream produced by [`compress`], appending the values to
+/// `output`. Returns the number of values appended.
+pub fn decode_append(bytes: &[u8], output: &mut Vec<u32>) -> Result<usize, DecodeError> {
+    decode_append_with(bytes, output, None)
+}
+
+/// Decodes a stream produced by [`compress`] into `output` without
+/// allocating, and returns the number of values written. Fails with
+/// [`DecodeError::OutputTooSmall`] if the stream holds more values than
+/// `output`.
+pub fn decode_into(bytes: &[u8], output: &mut [u32]) -> Result<usize, DecodeError> {
+    let (count, control, data) = split_streams(bytes)?;
+    let output = output.get_mut(..count).ok_or(DecodeError::OutputTooSmall)?;
+    decode_values(control, data, output, None)?;
+    Ok(count)
+}
+
+/// Encodes the differences between consecutive values (the first one taken
+/// from `base`) with [`compress`].
+///
+/// Differences wrap around, so any list is accepted, but only sorted lists
+/// (or lists of nearby values) get small differences.
+pub fn compress_delta(values: &[u32], base: u32) -> Vec<u8> {
+    let mut prev = base;
+    let deltas: Vec<u32> = values
+        .iter()
+        .map(|&value| {
+            let delta = value.wrapping_sub(prev);
+            prev = value;
+            delta
+        })
+        .collect();
+    compress(&deltas)
+}
+
+/// Decodes a stream produced by [`compress_delta`] with the same `base`.
+/// The prefix sum runs in the SIMD registers on the SSSE3 path.
+pub fn decompress_delta(bytes: &[u8], base: u32) -> Result<Vec<u32>, DecodeError> {
+    let mut result = Vec::new();
+    decode_append_with(bytes, &mut result, Some(base))?;
+    Ok(result)
+}
+
+/// Decodes a stream produced by [`compress_delta`] into `output` without
+/// allocating, see [`decode_into`].
+pub fn decode_delta_into(
+    bytes: &[u8],
+    output: &mut [u32],
+    base: u32,
+) -> Result<usize, DecodeError> {
+    let (count, control, data) = split_streams(bytes)?;
+    let output = output.get_mut(..count).ok_or(DecodeError::OutputTooSmall)?;
+    decode_values(control, data, output, Some(base))?;
+    Ok(count)
+}
+
+fn decode_append_with(
+    bytes: &[u8],
+    output: &mut Vec<u32>,
+    base: Option<u32>,
+) -> Result<usize, DecodeError> {
+    let (count, control, data) = split_streams(bytes)?;
+    let start = output.len();
+    output.resize(start + count, 0);
+    if let Err(err) = decode_values(control, data, &mut output[start..], base) {
+        output.truncate(start);
+        return Err(err);
+    }
+    Ok(count)
+}
+
+/// Splits a stream into its value count, control bytes and data bytes.
+fn split_streams(bytes: &[u8]) -> Result<(usize, &[u8], &[u8]), DecodeError> {
+    let (count, rest) = read_count(bytes)?;
+    // Every value takes at least one data byte, so a corrupt count cannot
+    // make us allocate more than the input could hold
+    if count > rest.len() {
+        return Err(DecodeError::Truncated);
+    }
+    let (control, data) = rest.split_at(count.div_ceil(GROUP_SIZE));
+    Ok((count, control, data))
+}
+
+/// Decodes exactly `output.len()` values, adding them up from `base` when
+/// the stream holds differences.
+///
+/// The data length is checked against the control bytes up front, so the
+/// kernels below never read out of bounds.
+fn decode_values(
+    control: &[u8],
+    data: &[u8],
+    output: &mut [u32],
+    base: Option<u32>,
+) -> Result<(), DecodeError> {
+    let tail = output.len() % GROUP_SIZE;
+    let mut data_len: usize = control.iter().map(|&c| LENGTHS[c as usize] as usize).sum();
+    if tail > 0 {
+        let last = control[control.len() - 1];
+        if last >> (2 * tail) != 0 {
+            return Err(DecodeError::InvalidHeader);
+        }
+        // The missing values of the last group count one byte each above
+        data_len -= GROUP_SIZE - tail;
+    }
+    match data_len.cmp(&data.len()) {
+        std::cmp::Ordering::Less => return Err(DecodeError::TrailingBytes),
+        std::cmp::Ordering::Greater => return Err(DecodeError::Truncated),
+        std::cmp::Ordering::Equal => {}
+    }
+
+    let mut groups_done = 0;
+    let mut data_pos = 0;
+    #[cfg(target_arch = "x86_64")]
+    if is_x86_feature_detected!("avx2") {
+        // SAFETY: AVX2 support was just checked, and the data length
+        // matches the control bytes
+        (groups_done, data_pos) = unsafe { simd::decode_groups_avx2(control, data, output, base) };
+    } else if is_x86_feature_detected!("ssse3") {
+        // SAFETY: SSSE3 support was just checked, and the data length
+        // matches the control bytes
+        (groups_done, data_pos) = unsafe { simd::decode_groups(control, data, output, base) };
+    }
+
+    let done = groups_done * GROUP_SIZE;
+    let prev = match (base, done) {
+        (Some(base), 0) => Some(base),
+        (Some(_), _) => Some(output[done - 1]),
+        (None, _) => None,
+    };
+    decode_scalar(
+        &control[groups_done..],
+        &data[data_pos..],
+        &mut output[done..],
+    );
+    if let Some(prev) = prev {
+        prefix_sum(&mut output[done..], prev);
+    }
+    Ok(())
+}
+
+/// Decodes `output.len()` values one control byte at a time. The data length
+/// must match the control bytes.
+fn decode_scalar(control: &[u8], data: &[u8], output: &mut [u32]) {
+    let mut pos = 0;
+    for (&control, group) in control.iter().zip(output.chunks_mut(GROUP_SIZE)) {
+        for (i, value) in group.iter_mut().enumerate() {
+            let len = ((control >> (2 * i)) & 3) as usize + 1;
+            let mut word = [0u8; 4];
+            word[..len].copy_from_slice(&data[pos..pos + len]);
+            *value = u32::from_le_bytes(word);
+            pos += len;
+        }
+    }
+}
+
+fn prefix_sum(values: &mut [u32], base: u32) {
+    let mut prev = base;
+    for value in values {
+        prev = prev.wrapping_add(*value);
+        *value = prev;
+    }
+}
+
+/// Stream VByte as an [`IntegerCodec`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StreamVByte;
+
+impl IntegerCodec for StreamVByte {
+    type Value = u32;
+
+    fn encode(&self, input: &[u32], output: &mut Vec<u8>) {
+        output.extend_from_slice(&compress(input));
+    }
+
+    fn decode(&self, input: &[u8], output: &mut Vec<u32>) -> Result<usize, DecodeError> {
+        decode_append(input, output)
+    }
+
+    fn encoded_len(&self, input: &[u32]) -> usize {
+        // Count, one control byte per group, then the data bytes
+        4 + input.len().div_ceil(GROUP_SIZE) + input.iter().map(|&v| byte_len(v)).sum::<usize>()
+    }
+}
+
+/// Streaming Stream VByte encoder over a [`std::io::Write`], see [`stream::Encoder`].
+pub type Encoder<W> = stream::Encoder<W, StreamVByte>;
+
+/// Streaming Stream VByte decoder over a [`std::io::Read`], see [`stream::Decoder`].
+pub type Decoder<R> = stream::Decoder<R, StreamVByte>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_values(len: usize) -> Vec<u32> {
+        (0..len as u32)
+            .map(|i| i.wrapping_mul(2_654_435_761) >> (i % 32))
+            .collect()
+    }
+
+    #[test]
+    fn test_layout() {
+        let encoded = compress(&[1, 300, 70_000, u32::MAX, 2]);
+        assert_eq!(&encoded[..4], &5u32.to_le_bytes());
+        // Two control bytes come before any data
+        assert_eq!(&encoded[4..6], &[0b11_10_01_00, 0]);
+        assert_eq!(
+            &encoded[6..],
+            &[1, 0x2C, 0x01, 0x70, 0x11, 0x01, 0xFF, 0xFF, 0xFF, 0xFF, 2]
+        );
+    }
+
+    #[test]
+    fn test_roundtrip_every_length() {
+        for len in 0..100 {
+            let values = sample_values(len);
+            let encoded = compress(&values);
+            assert_eq!(StreamVByte.encoded_len(&values), encoded.len());
+            assert_eq!(decompress(&encoded), Ok(values), "length {len}");
+        }
+    }
+
+    #[test]
+    fn test_scalar_matches_dispatch() {
+        let values = sample_values(1001);
+        let encoded = compress(&values);
+        let (count, control, data) = split_streams(&encoded).unwrap();
+
+        let mut scalar = vec![0; count];
+        decode_scalar(control, data, &mut scalar);
+        assert_eq!(scalar, values);
+        assert_eq!(decompress(&encoded).unwrap(), scalar);
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    #[test]
+    fn test_every_kernel_matches_scalar() {
+        let has_avx2 = is_x86_feature_detected!("avx2");
+        let has_ssse3 = is_x86_feature_detected!("ssse3");
+        for len in [0, 3, 4, 8, 9, 33, 64, 1001] {
+            let values = sample_values(len);
+            let encoded = compress(&values);
+            let (count, control, data) = split_streams(&encoded).unwrap();
+            let mut expected = vec![0; count];
+            decode_scalar(control, data, &mut expected);
+            let mut summed = expected.clone();
+            prefix_sum(&mut summed, 5);
+
+            for base in [None, Some(5)] {
+                let want = if base.is_some() { &summed } else { &expected };
+                let finish = |output: &mut [u32], (groups, data_pos): (usize, usize)| {
+                    let done = groups * GROUP_SIZE;
+                    decode_scalar(&control[groups..], &data[data_pos..], &mut output[done..]);
+                    if let Some(base) = base {
+                        let prev = if done == 0 { base } else { output[done - 1] };
+                        prefix_sum(&mut output[done..], prev);
+                    }
+                };
+                if has_ssse3 {
+                    let mut output = vec![0; count];
+                    // SAFETY: SSSE3 support was just checked
+                    let done = unsafe { simd::decode_groups(control, data, &mut output, base) };
+                    finish(&mut output, done);
+                    assert_eq!(&output, want, "ssse3, length {len}, base {base:?}");
+                }
+                if has_avx2 {
+                    let mut output = vec![0; count];
+                    // SAFETY: AVX2 support was just checked
+                    let done =
+                        unsafe { simd::decode_groups_avx2(control, data, &mut output, base) };
+                    finish(&mut output, done);
+                    assert_eq!(&output, want, "avx2, length {len}, base {base:?}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_delta_roundtrip() {
+        let mut prev = 10u32;
+        let sorted: Vec<u32> = sample_values(777)
+            .into_iter()
+            .map(|v| {
+                prev += v % 5000;
+                prev
+            })
+            .collect();
+        let encoded = compress_delta(&sorted, 10);
+        assert!(encoded.len() < compress(&sorted).len());
+        assert_eq!(decompress_delta(&encoded, 10), Ok(sorted.clone()));
+
+        let mut output = vec![0; 800];
+        assert_eq!(decode_delta_into(&encoded, &mut output, 10), Ok(777));
+        assert_eq!(&output[..777], &sorted[..]);
+
+        // Unsorted input wraps around but still roundtrips
+        let unsorted = vec![5, 3, u32::MAX, 0, 9];
+        assert_eq!(
+            decompress_delta(&compress_delta(&unsorted, 7), 7),
+            Ok(unsorted)
+        );
+    }
+
+    #[test]
+    fn test_rejects_malformed_streams() {
+        let values = sample_values(50);
+        let encoded = compress(&values);
+        assert_eq!(decompress(&encoded[..3]), Err(DecodeError::Truncated));
+        assert_eq!(
+            decompress(&encoded[..encoded.len() - 1]),
+            Err(DecodeError::Truncated)
+        );
+
+        let mut extra = encoded.clone();
+        extra.push(0);
+        assert_eq!(decompress(&extra), Err(DecodeError::TrailingBytes));
+
+        // 50 values leave two unused fields in the last control byte
+        let mut bad = encoded.clone();
+        bad[4 + 12] |= 0b0100_0000;
+        assert_eq!(decompress(&bad), Err(DecodeError::InvalidHeader));
+
+        let mut huge = encoded.clone();
+        huge[..4].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert_eq!(decompress(&huge), Err(DecodeError::Truncated));
+    }
+
+    #[test]
+    fn test_decode_into_caller_buffer() {
+        let values = sample_values(20);
+        let encoded = compress(&values);
+
+        let mut output = [0u32; 32];
+        assert_eq!(decode_into(&encoded, &mut output), Ok(20));
+        assert_eq!(&output[..20], &values[..]);
+
+        let mut short = [0u32; 19];
+        assert_eq!(
+            decode_into(&encoded, &mut short),
+            Err(DecodeError::OutputTooSmall)
+        );
+    }
+}
@@ -0,0 +1,146 @@
+use std::arch::x86_64::*;
+
+use super::{GROUP_SIZE, LENGTHS};
+
+/// `_mm_shuffle_epi8` masks moving the data bytes of one control byte into
+/// four u32 lanes. Lanes marked 0x80 are zeroed.
+static SHUFFLES: [[u8; 16]; 256] = build_shuffles();
+
+const fn build_shuffles() -> [[u8; 16]; 256] {
+    let mut table = [[0x80; 16]; 256];
+    let mut control = 0;
+    while control < 256 {
+        let mut offset = 0;
+        let mut i = 0;
+        while i < GROUP_SIZE {
+            let len = ((control >> (2 * i)) & 3) as u8 + 1;
+            let mut byte = 0;
+            while byte < len {
+                table[control][4 * i + byte as usize] = offset + byte;
+                byte += 1;
+            }
+            offset += len;
+            i += 1;
+        }
+        control += 1;
+    }
+    table
+}
+
+/// Decodes full groups of `output` with one shuffle each, for as long as 16
+/// data bytes can be loaded. With a `base`, the values are differences and
+/// are added up in the registers. Returns the number of groups decoded and
+/// the number of data bytes they took.
+///
+/// # Safety
+/// The CPU must support SSSE3.
+#[target_feature(enable = "ssse3")]
+pub(super) unsafe fn decode_groups(
+    control: &[u8],
+    data: &[u8],
+    output: &mut [u32],
+    base: Option<u32>,
+) -> (usize, usize) {
+    let full_groups = (output.len() / GROUP_SIZE).min(control.len());
+    let mut prev = _mm_set1_epi32(base.unwrap_or(0) as i32);
+    let mut data_pos = 0;
+    let mut group = 0;
+
+    while group < full_groups && data_pos + 16 <= data.len() {
+        let control = control[group] as usize;
+        // SAFETY: both loads read 16 bytes inside their slices, and the store
+        // writes the 4 values of a full group of `output`
+        unsafe {
+            let shuffle = _mm_loadu_si128(SHUFFLES[control].as_ptr().cast());
+            let bytes = _mm_loadu_si128(data.as_ptr().add(data_pos).cast());
+            let mut values = _mm_shuffle_epi8(bytes, shuffle);
+            if base.is_some() {
+                // Prefix sum across the four lanes, then carry in the last
+                // value of the previous group
+                values = _mm_add_epi32(values, _mm_slli_si128::<4>(values));
+                values = _mm_add_epi32(values, _mm_slli_si128::<8>(values));
+                values = _mm_add_epi32(values, prev);
+                prev = _mm_shuffle_epi32::<0xFF>(values);
+            }
+            _mm_storeu_si128(output.as_mut_ptr().add(group * GROUP_SIZE).cast(), values);
+        }
+        data_pos += LENGTHS[control] as usize;
+        group += 1;
+    }
+
+    (group, data_pos)
+}
+
+/// Decodes full groups of `output` two at a time, one 256-bit shuffle
+/// covering both control bytes, then hands the rest to [`decode_groups`].
+/// The two groups' data windows go in the two 128-bit halves, which
+/// `_mm256_shuffle_epi8` treats separately, so the SSSE3 masks apply
+/// unchanged. Returns the number of groups decoded and the number of data
+/// bytes they took.
+///
+/// # Safety
+/// The CPU must support AVX2.
+#[target_feature(enable = "avx2")]
+pub(super) unsafe fn decode_groups_avx2(
+    control: &[u8],
+    data: &[u8],
+    output: &mut [u32],
+    base: Option<u32>,
+) -> (usize, usize) {
+    let full_groups = (output.len() / GROUP_SIZE).min(control.len());
+    let mut prev = _mm256_set1_epi32(base.unwrap_or(0) as i32);
+    let mut data_pos = 0;
+    let mut group = 0;
+
+    while group + 2 <= full_groups {
+        let first = control[group] as usize;
+        let second = control[group + 1] as usize;
+        let second_pos = data_pos + LENGTHS[first] as usize;
+        if second_pos + 16 > data.len() {
+            break;
+        }
+        // SAFETY: the data loads read 16 bytes at `data_pos` and at
+        // `second_pos`, both inside `data`, and the store writes the 8 values
+        // of two full groups of `output`
+        unsafe {
+            let shuffle = _mm256_loadu2_m128i(
+                SHUFFLES[second].as_ptr().cast(),
+                SHUFFLES[first].as_ptr().cast(),
+            );
+            let bytes = _mm256_loadu2_m128i(
+                data.as_ptr().add(second_pos).cast(),
+                data.as_ptr().add(data_pos).cast(),
+            );
+            let mut values = _mm256_shuffle_epi8(bytes, shuffle);
+            if base.is_some() {
+                // Prefix sum within each group, then carry the first group's
+                // last value into the second and the previous total into both
+                values = _mm256_add_epi32(values, _mm256_slli_si256::<4>(values));
+                values = _mm256_add_epi32(values, _mm256_slli_si256::<8>(values));
+                let first_last = _mm256_permutevar8x32_epi32(values, _mm256_set1_epi32(3));
+                let carry = _mm256_blend_epi32::<0xF0>(_mm256_setzero_si256(), first_last);
+                values = _mm256_add_epi32(values, _mm256_add_epi32(carry, prev));
+                prev = _mm256_permutevar8x32_epi32(values, _mm256_set1_epi32(7));
+            }
+            _mm256_storeu_si256(output.as_mut_ptr().add(group * GROUP_SIZE).cast(), values);
+        }
+        data_pos = second_pos + LENGTHS[second] as usize;
+        group += 2;
+    }
+
+    let base = match (base, group) {
+        (Some(base), 0) => Some(base),
+        (Some(_), _) => Some(output[group * GROUP_SIZE - 1]),
+        (None, _) => None,
+    };
+    // SAFETY: AVX2 support implies SSSE3
+    let (more_groups, more_bytes) = unsafe {
+        decode_groups(
+            &control[group..],
+            &data[data_pos..],
+            &mut output[group * GROUP_SIZE..],
+            base,
+        )
+    };
+    (group + more_groups, data_pos + more_bytes)
+}
@@ -0,0 +1,381 @@
+use crate::bit_io::{BitReader, BitWriter};
+use crate::codec::{IntegerCodec, count_field};
+use crate::error::DecodeError;
+use crate::stream;
+
+/// Parameter-free (or fixed-parameter) codes for integers of unknown range.
+///
+/// Elias gamma and delta are defined for positive integers, so every value
+/// `n` is coded as `n + 1` and zero is accepted. Exp-Golomb of order 0 is
+/// the same code as gamma.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum UniversalCode {
+    /// `floor(log2 x)` in unary, then the bits of `x` below its leading one.
+    #[default]
+    Gamma,
+    /// The bit length of `x` in gamma, then the bits of `x` below its
+    /// leading one. Shorter than gamma from about 32 upwards.
+    Delta,
+    /// `n >> k` in order-0 Exp-Golomb, then the low `k` bits of `n`. Suits
+    /// values that are rarely below `2^k`. `k` is at most 63.
+    ExpGolomb(u8),
+}
+
+impl UniversalCode {
+    fn tag(self) -> u8 {
+        match self {
+            UniversalCode::Gamma => 0,
+            UniversalCode::Delta => 1,
+            UniversalCode::ExpGolomb(k) => 0x80 | k,
+        }
+    }
+
+    fn from_tag(tag: u8) -> Option<Self> {
+        match tag {
+            0 => Some(UniversalCode::Gamma),
+            1 => Some(UniversalCode::Delta),
+            _ if tag & 0xC0 == 0x80 => Some(UniversalCode::ExpGolomb(tag & 0x3F)),
+            _ => None,
+        }
+    }
+}
+
+/// Writes `value` with the Elias gamma code.
+pub fn write_gamma(writer: &mut BitWriter, value: u64) {
+    write_positive_gamma(writer, value as u128 + 1);
+}
+
+/// Reads a value written by [`write_gamma`].
+pub fn read_gamma(reader: &mut BitReader) -> Result<u64, DecodeError> {
+    to_u64(read_positive_gamma(reader)? - 1)
+}
+
+/// Writes `value` with the Elias delta code.
+pub fn write_delta(writer: &mut BitWriter, value: u64) {
+    let x = value as u128 + 1;
+    let bits = 127 - x.leading_zeros();
+    write_positive_gamma(writer, bits as u128 + 1);
+    writer.write_bits(x as u64, bits);
+}
+
+/// Reads a value written by [`write_delta`].
+pub fn read_delta(reader: &mut BitReader) -> Result<u64, DecodeError> {
+    let bits = read_positive_gamma(reader)? - 1;
+    if bits > 64 {
+        return Err(DecodeError::Overflow);
+    }
+    let low = reader
+        .read_bits(bits as u32)
+        .ok_or(DecodeError::Truncated)?;
+    to_u64(((1u128 << bits) | low as u128) - 1)
+}
+
+/// Writes `value` with the Exp-Golomb code of order `k`.
+///
+/// # Panics
+/// Panics if `k` is above 63.
+pub fn write_exp_golomb(writer: &mut BitWriter, value: u64, k: u8) {
+    assert!(k <= 63, "Exp-Golomb order must be at most 63");
+    write_positive_gamma(writer, (value >> k) as u128 + 1);
+    writer.write_bits(value, k as u32);
+}
+
+/// Reads a value written by [`write_exp_golomb`] with the same `k`.
+pub fn read_exp_golomb(reader: &mut BitReader, k: u8) -> Result<u64, DecodeError> {
+    let high = read_positive_gamma(reader)? - 1;
+    let low = reader.read_bits(k as u32).ok_or(DecodeError::Truncated)?;
+    to_u64((high << k) | low as u128)
+}
+
+/// Writes `x >= 1`, up to `2^64`: the position of its leading one in unary,
+/// then the bits below it.
+fn write_positive_gamma(writer: &mut BitWriter, x: u128) {
+    let bits = 127 - x.leading_zeros();
+    writer.write_unary(bits as u64);
+    writer.write_bits(x as u64, bits);
+}
+
+fn read_positive_gamma(reader: &mut BitReader) -> Result<u128, DecodeError> {
+    let bits = reader.read_unary().ok_or(DecodeError::Truncated)?;
+    if bits > 64 {
+        return Err(DecodeError::Overflow);
+    }
+    let low = reader
+        .read_bits(bits as u32)
+        .ok_or(DecodeError::Truncated)?;
+    Ok((1u128 << bits) | low as u128)
+}
+
+fn to_u64(value: u128) -> Result<u64, DecodeError> {
+    u64::try_from(value).map_err(|_| DecodeError::Overflow)
+}
+
+fn write_value(writer: &mut BitWriter, value: u64, code: UniversalCode) {
+    match code {
+        UniversalCode::Gamma => write_gamma(writer, value),
+        UniversalCode::Delta => write_delta(writer, value),
+        UniversalCode::ExpGolomb(k) => write_exp_golomb(writer, value, k),
+    }
+}
+
+fn read_value(reader: &mut BitReader, code: UniversalCode) -> Result<u64, DecodeError> {
+    match code {
+        UniversalCode::Gamma => read_gamma(reader),
+        UniversalCode::Delta => read_delta(reader),
+        UniversalCode::ExpGolomb(k) => read_exp_golomb(reader, k),
+    }
+}
+
+/// Compresses u32 numbers with `code`.
+///
+/// The stream starts with a header like the Rice stream: the code (8 bits)
+/// and the value count (32 bits), MSB first, followed by the codes.
+///
+/// # Panics
+/// Panics if `code` is Exp-Golomb with an order above 63.
+pub fn compress(numbers: &[u32], code: UniversalCode) -> Vec<u8> {
+    let mut writer = write_header(numbers.len(), code);
+    for &n in numbers {
+        write_value(&mut writer, n as u64, code);
+    }
+    writer.finish()
+}
+
+/// Compresses u64 numbers with `code`, see [`compress`].
+pub fn compress_u64(numbers: &[u64], code: UniversalCode) -> Vec<u8> {
+    let mut writer = write_header(numbers.len(), code);
+    for &n in numbers {
+        write_value(&mut writer, n, code);
+    }
+    writer.finish()
+}
+
+/// Decodes a stream produced by [`compress`]. Values above `u32::MAX` are
+/// rejected with [`DecodeError::Overflow`].
+pub fn decompress(encoded: &[u8]) -> Result<Vec<u32>, DecodeError> {
+    let mut numbers = Vec::new();
+    decode_append(encoded, &mut numbers)?;
+    Ok(numbers)
+}
+
+/// Decodes a stream produced by [`compress_u64`] (or [`compress`]).
+pub fn decompress_u64(encoded: &[u8]) -> Result<Vec<u64>, DecodeError> {
+    let mut reader = BitReader::new(encoded);
+    let (code, count) = read_header(&mut reader)?;
+
+    let mut numbers = Vec::with_capacity(count.min(encoded.len() * 8));
+    for _ in 0..count {
+        numbers.push(read_value(&mut reader, code)?);
+    }
+
+    check_consumed(&reader, encoded)?;
+    Ok(numbers)
+}
+
+/// Decodes a stream produced by [`compress`], appending the values to
+/// `output`. Returns the number of values appended.
+pub fn decode_append(encoded: &[u8], output: &mut Vec<u32>) -> Result<usize, DecodeError> {
+    let mut reader = BitReader::new(encoded);
+    let (code, count) = read_header(&mut reader)?;
+
+    // Every value takes at least one bit, so a corrupt count cannot make us
+    // reserve more than the input could possibly hold
+    output.reserve(count.min(encoded.len() * 8));
+    for _ in 0..count {
+        output.push(read_u32(&mut reader, code)?);
+    }
+
+    check_consumed(&reader, encoded)?;
+    Ok(count)
+}
+
+/// Decodes a stream produced by [`compress`] into `output` without
+/// allocating, and returns the number of values written. Fails with
+/// [`DecodeError::OutputTooSmall`] if the header announces more values than
+/// `output` can hold.
+pub fn decode_into(encoded: &[u8], output: &mut [u32]) -> Result<usize, DecodeError> {
+    let mut reader = BitReader::new(encoded);
+    let (code, count) = read_header(&mut reader)?;
+
+    let output = output.get_mut(..count).ok_or(DecodeError::OutputTooSmall)?;
+    for n in output {
+        *n = read_u32(&mut reader, code)?;
+    }
+
+    check_consumed(&reader, encoded)?;
+    Ok(count)
+}
+
+fn read_u32(reader: &mut BitReader, code: UniversalCode) -> Result<u32, DecodeError> {
+    u32::try_from(read_value(reader, code)?).map_err(|_| DecodeError::Overflow)
+}
+
+fn write_header(count: usize, code: UniversalCode) -> BitWriter {
+    if let UniversalCode::ExpGolomb(k) = code {
+        assert!(k <= 63, "Exp-Golomb order must be at most 63");
+    }
+    let mut writer = BitWriter::new();
+    writer.write_bits(code.tag() as u64, 8);
+    writer.write_bits(count_field(count) as u64, 32);
+    writer
+}
+
+/// Reads the code and the number of values.
+fn read_header(reader: &mut BitReader) -> Result<(UniversalCode, usize), DecodeError> {
+    let tag = reader.read_bits(8).ok_or(DecodeError::Truncated)? as u8;
+    let count = reader.read_bits(32).ok_or(DecodeError::Truncated)? as usize;
+    let code = UniversalCode::from_tag(tag).ok_or(DecodeError::InvalidHeader)?;
+    Ok((code, count))
+}
+
+/// Rejects input left over after the last value.
+fn check_consumed(reader: &BitReader, encoded: &[u8]) -> Result<(), DecodeError> {
+    if reader.bytes_consumed() != encoded.len() {
+        return Err(DecodeError::TrailingBytes);
+    }
+    Ok(())
+}
+
+/// Universal codes as an [`IntegerCodec`], using the [`compress`] stream.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Universal {
+    pub code: UniversalCode,
+}
+
+impl IntegerCodec for Universal {
+    type Value = u32;
+
+    fn encode(&self, input: &[u32], output: &mut Vec<u8>) {
+        output.extend_from_slice(&compress(input, self.code));
+    }
+
+    fn decode(&self, input: &[u8], output: &mut Vec<u32>) -> Result<usize, DecodeError> {
+        decode_append(input, output)
+    }
+}
+
+/// Streaming universal-code encoder over a [`std::io::Write`], see [`stream::Encoder`].
+pub type Encoder<W> = stream::Encoder<W, Universal>;
+
+/// Streaming universal-code decoder over a [`std::io::Read`], see [`stream::Decoder`].
+pub type Decoder<R> = stream::Decoder<R, Universal>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CODES: [UniversalCode; 5] = [
+        UniversalCode::Gamma,
+        UniversalCode::Delta,
+        UniversalCode::ExpGolomb(0),
+        UniversalCode::ExpGolomb(3),
+        UniversalCode::ExpGolomb(63),
+    ];
+
+    fn code_bits(value: u64, code: UniversalCode) -> String {
+        let mut writer = BitWriter::new();
+        write_value(&mut writer, value, code);
+        let len = writer.bit_len();
+        let bytes = writer.finish();
+        bytes
+            .iter()
+            .map(|byte| format!("{byte:08b}"))
+            .collect::<String>()[..len]
+            .to_string()
+    }
+
+    #[test]
+    fn test_reference_codewords() {
+        // Codes of x = value + 1
+        assert_eq!(code_bits(0, UniversalCode::Gamma), "1");
+        assert_eq!(code_bits(1, UniversalCode::Gamma), "010");
+        assert_eq!(code_bits(8, UniversalCode::Gamma), "0001001");
+        assert_eq!(code_bits(0, UniversalCode::Delta), "1");
+        assert_eq!(code_bits(1, UniversalCode::Delta), "0100");
+        assert_eq!(code_bits(16, UniversalCode::Delta), "001010001");
+        assert_eq!(code_bits(0, UniversalCode::ExpGolomb(2)), "100");
+        assert_eq!(code_bits(9, UniversalCode::ExpGolomb(2)), "01101");
+        assert_eq!(
+            code_bits(7, UniversalCode::ExpGolomb(0)),
+            code_bits(7, UniversalCode::Gamma)
+        );
+    }
+
+    #[test]
+    fn test_roundtrip_u32() {
+        let numbers: Vec<u32> = (0..2000u32)
+            .map(|i| i.wrapping_mul(2_654_435_761) >> (i % 32))
+            .chain([0, 1, u32::MAX])
+            .collect();
+        for code in CODES {
+            let encoded = compress(&numbers, code);
+            assert_eq!(decompress(&encoded).unwrap(), numbers, "{code:?}");
+        }
+    }
+
+    #[test]
+    fn test_roundtrip_u64_extremes() {
+        let numbers = vec![0, 1, u64::MAX - 1, u64::MAX, 1 << 63, 12345];
+        for code in CODES {
+            let encoded = compress_u64(&numbers, code);
+            assert_eq!(decompress_u64(&encoded).unwrap(), numbers, "{code:?}");
+        }
+        assert_eq!(
+            decompress(&compress_u64(&numbers, UniversalCode::Delta)),
+            Err(DecodeError::Overflow)
+        );
+    }
+
+    #[test]
+    fn test_delta_beats_gamma_on_large_values() {
+        let numbers: Vec<u32> = (0..100).map(|i| 1_000_000 + i).collect();
+        let gamma = compress(&numbers, UniversalCode::Gamma);
+        let delta = compress(&numbers, UniversalCode::Delta);
+        assert!(delta.len() < gamma.len());
+    }
+
+    #[test]
+    fn test_rejects_malformed_streams() {
+        assert_eq!(decompress(&[]), Err(DecodeError::Truncated));
+
+        let numbers = vec![100, 200, 300, 400];
+        let encoded = compress(&numbers, UniversalCode::Delta);
+        assert_eq!(
+            decompress(&encoded[..encoded.len() - 2]),
+            Err(DecodeError::Truncated)
+        );
+
+        let mut extra = encoded.clone();
+        extra.push(0);
+        assert_eq!(decompress(&extra), Err(DecodeError::TrailingBytes));
+
+        let mut bad_tag = encoded.clone();
+        bad_tag[0] = 7;
+        assert_eq!(decompress(&bad_tag), Err(DecodeError::InvalidHeader));
+
+        // A unary length past 64 bits cannot be a u64
+        let mut writer = BitWriter::new();
+        writer.write_bits(0, 8);
+        writer.write_bits(1, 32);
+        writer.write_unary(65);
+        writer.write_bits(0, 65 - 32);
+        writer.write_bits(0, 32);
+        assert_eq!(decompress_u64(&writer.finish()), Err(DecodeError::Overflow));
+    }
+
+    #[test]
+    fn test_decode_into_caller_buffer() {
+        let numbers = vec![3, 17, 0, 250, 9];
+        let encoded = compress(&numbers, UniversalCode::ExpGolomb(2));
+
+        let mut output = [0u32; 8];
+        assert_eq!(decode_into(&encoded, &mut output), Ok(5));
+        assert_eq!(&output[..5], &numbers[..]);
+
+        let mut short = [0u32; 4];
+        assert_eq!(
+            decode_into(&encoded, &mut short),
+            Err(DecodeError::OutputTooSmall)
+        );
+    }
+}
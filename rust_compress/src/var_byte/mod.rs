@@ -1,58 +1,267 @@
+#[cfg(target_arch = "x86_64")]
+mod simd;
 
+use crate::codec::IntegerCodec;
+use crate::error::DecodeError;
+use crate::stream;
+use crate::zigzag;
+
+/// Encodes u32 values, using up to 5 bytes per value.
+///
+/// The byte layout is unsigned LEB128 (as used by protobuf, DWARF and
+/// WebAssembly), so the u16, u32 and u64 entry points read each other's
+/// output as long as the values fit.
 pub fn compress(numbers: &[u32]) -> Vec<u8> {
     let mut result = Vec::new();
     
     for &num in numbers {
-        let mut n = num;
-        
-        // Extract 7-bit chunks and write in little-endian order
-        loop {
-            let mut byte = (n & 0x7F) as u8;
-            n >>= 7;
-            
-            if n != 0 {
-                // More bytes follow, set MSB = 1
-                byte |= 0x80;
-            }
-            // else MSB = 0 (last byte)
-            
-            result.push(byte);
-            
-            if n == 0 {
-                break;
-            }
-        }
+        write_value(num as u64, &mut result);
     }
     
     result
 }
 
+/// Encodes u64 values, using up to 10 bytes per value.
+pub fn compress_u64(numbers: &[u64]) -> Vec<u8> {
+    let mut result = Vec::new();
+    for &num in numbers {
+        write_value(num, &mut result);
+    }
+    result
+}
+
+/// Encodes u16 values, using up to 3 bytes per value.
+pub fn compress_u16(numbers: &[u16]) -> Vec<u8> {
+    let mut result = Vec::new();
+    for &num in numbers {
+        write_value(num as u64, &mut result);
+    }
+    result
+}
+
+fn write_value(num: u64, result: &mut Vec<u8>) {
+    let mut n = num;
+
+    // Extract 7-bit chunks and write in little-endian order
+    loop {
+        let mut byte = (n & 0x7F) as u8;
+        n >>= 7;
+
+        if n != 0 {
+            // More bytes follow, set MSB = 1
+            byte |= 0x80;
+        }
+        // else MSB = 0 (last byte)
+
+        result.push(byte);
+
+        if n == 0 {
+            break;
+        }
+    }
+}
+
+
+pub fn decompress(encoded: &[u8]) -> Result<Vec<u32>, DecodeError> {
+    let mut result = Vec::new();
+    decode_append(encoded, &mut result)?;
+    Ok(result)
+}
+
+/// Decodes `encoded`, appending the values to `output`. Returns the number
+/// of values appended; on error `output` may hold a decoded prefix.
+///
+/// On x86_64 CPUs with SSE4.1 the values are decoded with Masked VByte
+/// shuffles, up to eight values per 16-byte load, and with AVX2 runs of
+/// single-byte values sixteen at a time; otherwise, and for the values the
+/// shuffles do not cover, byte by byte. Both give the same
+/// values and errors.
+pub fn decode_append(encoded: &[u8], output: &mut Vec<u32>) -> Result<usize, DecodeError> {
+    let start = output.len();
+    #[cfg(target_arch = "x86_64")]
+    if is_x86_feature_detected!("sse4.1") {
+        // Every value ends with the one byte that has no continuation bit
+        let count = encoded.iter().filter(|&&byte| byte & 0x80 == 0).count();
+        output.resize(start + count, 0);
+        let avx2 = is_x86_feature_detected!("avx2");
+        // SAFETY: SSE4.1 support was just checked, and AVX2 is only used
+        // when detected
+        let result = unsafe { simd::decode_into(encoded, &mut output[start..], avx2) };
+        if result.is_err() {
+            output.truncate(start);
+        }
+        return result;
+    }
+    decode_with(encoded, u32::BITS, |value| {
+        output.push(value as u32);
+        Ok(())
+    })?;
+    Ok(output.len() - start)
+}
+
+/// Decodes `encoded` into `output` without allocating, and returns the
+/// number of values written. Fails with [`DecodeError::OutputTooSmall`] if
+/// `encoded` holds more values than `output`.
+pub fn decode_into(encoded: &[u8], output: &mut [u32]) -> Result<usize, DecodeError> {
+    #[cfg(target_arch = "x86_64")]
+    if is_x86_feature_detected!("sse4.1") {
+        let avx2 = is_x86_feature_detected!("avx2");
+        // SAFETY: SSE4.1 support was just checked, and AVX2 is only used
+        // when detected
+        return unsafe { simd::decode_into(encoded, output, avx2) };
+    }
+    let mut written = 0;
+    decode_with(encoded, u32::BITS, |value| {
+        *output.get_mut(written).ok_or(DecodeError::OutputTooSmall)? = value as u32;
+        written += 1;
+        Ok(())
+    })?;
+    Ok(written)
+}
+
+/// Decodes a stream produced by [`compress_u64`] (or any unsigned LEB128
+/// stream without over-long encodings).
+pub fn decompress_u64(encoded: &[u8]) -> Result<Vec<u64>, DecodeError> {
+    let mut result = Vec::new();
+    decode_with(encoded, u64::BITS, |value| {
+        result.push(value);
+        Ok(())
+    })?;
+    Ok(result)
+}
+
+/// Decodes a stream produced by [`compress_u16`]. Values above `u16::MAX`
+/// are rejected with [`DecodeError::Overflow`].
+pub fn decompress_u16(encoded: &[u8]) -> Result<Vec<u16>, DecodeError> {
+    let mut result = Vec::new();
+    decode_with(encoded, u16::BITS, |value| {
+        result.push(value as u16);
+        Ok(())
+    })?;
+    Ok(result)
+}
+
+/// Encodes signed values ZigZag-mapped, so small negative values take as
+/// few bytes as small positive ones.
+pub fn compress_i32(numbers: &[i32]) -> Vec<u8> {
+    let mut result = Vec::new();
+    for &num in numbers {
+        write_value(zigzag::encode_i32(num) as u64, &mut result);
+    }
+    result
+}
+
+/// Decodes a stream produced by [`compress_i32`].
+pub fn decompress_i32(encoded: &[u8]) -> Result<Vec<i32>, DecodeError> {
+    let mut result = Vec::new();
+    decode_with(encoded, u32::BITS, |value| {
+        result.push(zigzag::decode_i32(value as u32));
+        Ok(())
+    })?;
+    Ok(result)
+}
+
+/// Encodes signed values ZigZag-mapped, see [`compress_i32`].
+pub fn compress_i64(numbers: &[i64]) -> Vec<u8> {
+    let mut result = Vec::new();
+    for &num in numbers {
+        write_value(zigzag::encode_i64(num), &mut result);
+    }
+    result
+}
 
-pub fn decompress(encoded: &[u8]) -> Vec<u32> {
+/// Decodes a stream produced by [`compress_i64`].
+pub fn decompress_i64(encoded: &[u8]) -> Result<Vec<i64>, DecodeError> {
     let mut result = Vec::new();
-    let mut current_num: u32 = 0;
-    let mut shift=0;
-    for &byte in encoded {
+    decode_with(encoded, u64::BITS, |value| {
+        result.push(zigzag::decode_i64(value));
+        Ok(())
+    })?;
+    Ok(result)
+}
+
+/// Decodes `encoded` as values of at most `bits` bits, handing each one to
+/// `emit` in order.
+fn decode_with(
+    encoded: &[u8],
+    bits: u32,
+    mut emit: impl FnMut(u64) -> Result<(), DecodeError>,
+) -> Result<(), DecodeError> {
+    let mut pos = 0;
+    while pos < encoded.len() {
+        let (value, len) = read_value(&encoded[pos..], bits)?;
+        emit(value)?;
+        pos += len;
+    }
+    Ok(())
+}
+
+/// Decodes the value of at most `bits` bits at the start of `encoded`, and
+/// returns it with the number of bytes it took.
+fn read_value(encoded: &[u8], bits: u32) -> Result<(u64, usize), DecodeError> {
+    let mut current_num: u64 = 0;
+    let mut shift = 0;
+    for (i, &byte) in encoded.iter().enumerate() {
         // Extract the lower 7 bits
-        let value = (byte & 0x7F) as u32;
+        let value = (byte & 0x7F) as u64;
+
+        // Only `bits - shift` bits of the value are left for this byte
+        if shift >= bits || (bits - shift < 7 && value >> (bits - shift) != 0) {
+            return Err(DecodeError::Overflow);
+        }
         
         // Add to current number
-        current_num = (current_num) | value<< (7*shift);
-        shift+=1;
+        current_num |= value << shift;
+        shift += 7;
         
         // Check if this is the last byte (MSB = 0)
         if (byte & 0x80) == 0 {
-            result.push(current_num);
-            current_num = 0;
-            shift=0;
+            // A zero final group past the first byte only pads the value
+            if value == 0 && shift > 7 {
+                return Err(DecodeError::NonCanonical);
+            }
+            return Ok((current_num, i + 1));
         }
     }
-    
-    result
+
+    // The last byte still had its continuation bit set
+    Err(DecodeError::Truncated)
 }
 
+/// Variable-byte coding as an [`IntegerCodec`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct VarByte;
+
+impl IntegerCodec for VarByte {
+    type Value = u32;
+
+    fn encode(&self, input: &[u32], output: &mut Vec<u8>) {
+        output.extend_from_slice(&compress(input));
+    }
+
+    fn decode(&self, input: &[u8], output: &mut Vec<u32>) -> Result<usize, DecodeError> {
+        decode_append(input, output)
+    }
+
+    fn encoded_len(&self, input: &[u32]) -> usize {
+        // Each byte carries 7 bits of the value; zero still takes one byte.
+        input
+            .iter()
+            .map(|&n| ((32 - n.leading_zeros()).max(1) as usize).div_ceil(7))
+            .sum()
+    }
+}
+
+/// Streaming variable-byte encoder over a [`std::io::Write`], see [`stream::Encoder`].
+pub type Encoder<W> = stream::Encoder<W, VarByte>;
+
+/// Streaming variable-byte decoder over a [`std::io::Read`], see [`stream::Decoder`].
+pub type Decoder<R> = stream::Decoder<R, VarByte>;
+
 #[cfg(test)]
 mod tests {
+    use rand::Rng;
+
     use super::*;
 
     #[test]
@@ -60,7 +269,7 @@ mod tests {
         // 267 = 2 * 128 + 11
         let numbers = vec![267];
         let encoded = compress(&numbers);        
-        let decoded = decompress(&encoded);
+        let decoded = decompress(&encoded).unwrap();
         assert_eq!(decoded, numbers);
     }
 
@@ -68,7 +277,7 @@ mod tests {
     fn test_small_numbers() {
         let numbers = vec![0, 1, 5, 127];
         let encoded = compress(&numbers);
-        let decoded = decompress(&encoded);
+        let decoded = decompress(&encoded).unwrap();
         assert_eq!(decoded, numbers);
     }
 
@@ -77,7 +286,7 @@ mod tests {
         // 128 = 1 * 128 + 0
         let numbers = vec![128];
         let encoded = compress(&numbers);        
-        let decoded = decompress(&encoded);
+        let decoded = decompress(&encoded).unwrap();
         assert_eq!(decoded, numbers);
     }
 
@@ -85,7 +294,7 @@ mod tests {
     fn test_large_numbers() {
         let numbers = vec![16384, 2097151, u32::MAX];
         let encoded = compress(&numbers);
-        let decoded = decompress(&encoded);
+        let decoded = decompress(&encoded).unwrap();
         assert_eq!(decoded, numbers);
     }
 
@@ -93,7 +302,199 @@ mod tests {
     fn test_mixed() {
         let numbers = vec![5, 267, 128, 1000000, 42];
         let encoded = compress(&numbers);
-        let decoded = decompress(&encoded);
+        let decoded = decompress(&encoded).unwrap();
         assert_eq!(decoded, numbers);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_truncated_input() {
+        let encoded = compress(&[300]);
+        assert_eq!(decompress(&encoded[..1]), Err(DecodeError::Truncated));
+    }
+
+    #[test]
+    fn test_overflowing_input() {
+        // Six continuation groups cannot fit in a u32
+        let encoded = [0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x01];
+        assert_eq!(decompress(&encoded), Err(DecodeError::Overflow));
+
+        // Five bytes, but the fifth carries more than 4 significant bits
+        let encoded = [0xFF, 0xFF, 0xFF, 0xFF, 0x1F];
+        assert_eq!(decompress(&encoded), Err(DecodeError::Overflow));
+    }
+
+    #[test]
+    fn test_decode_into_caller_buffer() {
+        let numbers = vec![1, 300, 70_000, u32::MAX];
+        let encoded = compress(&numbers);
+
+        let mut output = [0u32; 6];
+        assert_eq!(decode_into(&encoded, &mut output), Ok(4));
+        assert_eq!(&output[..4], &numbers[..]);
+
+        let mut short = [0u32; 3];
+        assert_eq!(decode_into(&encoded, &mut short), Err(DecodeError::OutputTooSmall));
+
+        let mut appended = vec![9];
+        assert_eq!(decode_append(&encoded, &mut appended), Ok(4));
+        assert_eq!(appended, vec![9, 1, 300, 70_000, u32::MAX]);
+    }
+
+    #[test]
+    fn test_leb128_reference_vectors() {
+        // Examples from the DWARF specification and protobuf documentation
+        assert_eq!(compress_u64(&[624_485]), vec![0xE5, 0x8E, 0x26]);
+        assert_eq!(compress_u64(&[150]), vec![0x96, 0x01]);
+        assert_eq!(compress_u16(&[u16::MAX]), vec![0xFF, 0xFF, 0x03]);
+
+        let mut max = vec![0xFF; 9];
+        max.push(0x01);
+        assert_eq!(compress_u64(&[u64::MAX]), max);
+        assert_eq!(decompress_u64(&max), Ok(vec![u64::MAX]));
+    }
+
+    #[test]
+    fn test_u64_and_u16_roundtrip() {
+        let wide = vec![0, 127, 128, 1 << 32, (1 << 56) + 3, u64::MAX];
+        assert_eq!(decompress_u64(&compress_u64(&wide)), Ok(wide));
+
+        let narrow = vec![0, 1, 127, 128, 16_383, 16_384, u16::MAX];
+        assert_eq!(decompress_u16(&compress_u16(&narrow)), Ok(narrow));
+    }
+
+    #[test]
+    fn test_widths_interoperate() {
+        let numbers = vec![0, 300, 70_000, u32::MAX];
+        let encoded = compress(&numbers);
+        let wide: Vec<u64> = numbers.iter().map(|&n| n as u64).collect();
+        assert_eq!(decompress_u64(&encoded), Ok(wide.clone()));
+        assert_eq!(compress_u64(&wide), encoded);
+
+        assert_eq!(decompress(&compress_u16(&[5, u16::MAX])), Ok(vec![5, 65_535]));
+        assert_eq!(decompress_u16(&compress(&[70_000])), Err(DecodeError::Overflow));
+        assert_eq!(decompress(&compress_u64(&[1 << 32])), Err(DecodeError::Overflow));
+    }
+
+    #[test]
+    fn test_rejects_overlong_encodings() {
+        assert_eq!(decompress(&[0x80, 0x00]), Err(DecodeError::NonCanonical));
+        assert_eq!(decompress_u64(&[0x81, 0x80, 0x00]), Err(DecodeError::NonCanonical));
+        assert_eq!(decompress_u16(&[0xFF, 0x00]), Err(DecodeError::NonCanonical));
+
+        // Eleven bytes never fit a u64, even if the extra groups are zero
+        let mut encoded = vec![0x80; 10];
+        encoded.push(0x00);
+        assert_eq!(decompress_u64(&encoded), Err(DecodeError::Overflow));
+        assert_eq!(decompress_u64(&[0x00]), Ok(vec![0]));
+    }
+
+    #[test]
+    fn test_signed_roundtrip() {
+        let numbers = vec![0, -1, 1, -64, 63, i32::MIN, i32::MAX];
+        let encoded = compress_i32(&numbers);
+        // Everything in -64..=63 takes a single byte
+        assert_eq!(&encoded[..5], &[0, 1, 2, 127, 126]);
+        assert_eq!(decompress_i32(&encoded), Ok(numbers));
+
+        let wide = vec![-3, i64::MIN, i64::MAX, 1 << 40];
+        assert_eq!(decompress_i64(&compress_i64(&wide)), Ok(wide));
+    }
+
+    fn decode_scalar(encoded: &[u8]) -> Result<Vec<u32>, DecodeError> {
+        let mut result = Vec::new();
+        decode_with(encoded, u32::BITS, |value| {
+            result.push(value as u32);
+            Ok(())
+        })?;
+        Ok(result)
+    }
+
+    #[test]
+    fn test_dispatch_matches_scalar() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..200 {
+            let len = rng.gen_range(0..300);
+            // Mostly short values, with runs of single bytes and some long ones
+            let numbers: Vec<u32> = (0..len)
+                .map(|_| match rng.gen_range(0..10) {
+                    0..=3 => rng.gen_range(0..128),
+                    4..=6 => rng.gen_range(0..1 << 14),
+                    7 | 8 => rng.gen_range(0..1 << 21),
+                    _ => rng.r#gen(),
+                })
+                .collect();
+            let encoded = compress(&numbers);
+            assert_eq!(decode_scalar(&encoded), Ok(numbers.clone()));
+            assert_eq!(decompress(&encoded), Ok(numbers.clone()));
+
+            let mut output = vec![0; len + 3];
+            assert_eq!(decode_into(&encoded, &mut output), Ok(len));
+            assert_eq!(&output[..len], &numbers[..]);
+        }
+    }
+
+    #[test]
+    fn test_every_kernel_matches_scalar() {
+        let has_avx2 = is_x86_feature_detected!("avx2");
+        let has_sse41 = is_x86_feature_detected!("sse4.1");
+        let mut rng = rand::thread_rng();
+        for round in 0..200 {
+            let len = rng.gen_range(0..300);
+            // Long runs of single bytes reach the AVX2 sixteen-value path
+            let numbers: Vec<u32> = (0..len)
+                .map(|_| match rng.gen_range(0..10) {
+                    0..=5 => rng.gen_range(0..128),
+                    6 | 7 => rng.gen_range(0..1 << 14),
+                    8 => rng.gen_range(0..1 << 21),
+                    _ => rng.r#gen(),
+                })
+                .collect();
+            let mut encoded = compress(&numbers);
+            if round % 4 == 0 {
+                encoded.extend_from_slice(&[0x85, 0x00]);
+                encoded.extend_from_slice(&compress(&numbers));
+            }
+            let expected = decode_scalar(&encoded);
+
+            for (avx2, supported) in [(false, has_sse41), (true, has_avx2 && has_sse41)] {
+                if !supported {
+                    continue;
+                }
+                let mut output = vec![0; len];
+                // SAFETY: the kernel's features were just checked
+                let result = unsafe { simd::decode_into(&encoded, &mut output, avx2) };
+                match &expected {
+                    Ok(numbers) => {
+                        assert_eq!(result, Ok(len), "avx2 {avx2}");
+                        assert_eq!(&output, numbers, "avx2 {avx2}");
+                    }
+                    Err(error) => assert_eq!(result, Err(*error), "avx2 {avx2}"),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_dispatch_reports_scalar_errors() {
+        let numbers: Vec<u32> = (0..100).map(|i| i * 37).collect();
+        let mut encoded = compress(&numbers);
+
+        // A truncated last value behind plenty of valid ones
+        encoded.push(0x80);
+        assert_eq!(decompress(&encoded), Err(DecodeError::Truncated));
+        assert_eq!(decode_scalar(&encoded), Err(DecodeError::Truncated));
+
+        // An over-long value in the middle of a window
+        let mut overlong = compress(&numbers[..10]);
+        overlong.extend_from_slice(&[0x85, 0x00]);
+        overlong.extend_from_slice(&compress(&numbers));
+        assert_eq!(decompress(&overlong), Err(DecodeError::NonCanonical));
+        assert_eq!(decode_scalar(&overlong), Err(DecodeError::NonCanonical));
+
+        let mut output = vec![0; 50];
+        assert_eq!(
+            decode_into(&compress(&numbers), &mut output),
+            Err(DecodeError::OutputTooSmall)
+        );
+    }
+}
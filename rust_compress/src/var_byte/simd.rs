@@ -0,0 +1,284 @@
+use std::arch::x86_64::*;
+
+use super::read_value;
+use crate::error::DecodeError;
+
+/// Bytes loaded per step.
+const WINDOW: usize = 16;
+/// Continuation bits looked up per step.
+const MASK_BITS: usize = 12;
+
+/// Decoding plan for a window whose first `MASK_BITS` continuation bits
+/// form the table index: how many complete values to take, how many bytes
+/// they span, and the shuffle spreading them over lanes of `lane_bytes`.
+#[derive(Debug, Clone, Copy)]
+struct MaskEntry {
+    shuffle: [u8; 16],
+    values: u8,
+    consumed: u8,
+    lane_bytes: u8,
+}
+
+static MASK_TABLE: [MaskEntry; 1 << MASK_BITS] = build_mask_table();
+
+/// Byte lengths of the complete values at the start of a window with
+/// continuation bits `mask`, stopping before the first value longer than
+/// `max_len` or after `max_values` values. Returns the number of values.
+const fn parse_mask(
+    mask: usize,
+    max_len: usize,
+    max_values: usize,
+    lengths: &mut [u8; 6],
+) -> usize {
+    let mut start = 0;
+    let mut count = 0;
+    while count < max_values {
+        let mut end = start;
+        while end < MASK_BITS && (mask >> end) & 1 == 1 {
+            end += 1;
+        }
+        if end >= MASK_BITS || end + 1 - start > max_len {
+            break;
+        }
+        lengths[count] = (end + 1 - start) as u8;
+        count += 1;
+        start = end + 1;
+    }
+    count
+}
+
+const fn build_mask_table() -> [MaskEntry; 1 << MASK_BITS] {
+    let mut table = [MaskEntry {
+        shuffle: [0x80; 16],
+        values: 0,
+        consumed: 0,
+        lane_bytes: 0,
+    }; 1 << MASK_BITS];
+    let mut mask = 0;
+    while mask < 1 << MASK_BITS {
+        // Up to six values of 1-2 bytes in u16 lanes, or up to four values
+        // of 1-3 bytes in u32 lanes, whichever covers more values
+        let mut narrow = [0; 6];
+        let narrow_count = parse_mask(mask, 2, 6, &mut narrow);
+        let mut wide = [0; 6];
+        let wide_count = parse_mask(mask, 3, 4, &mut wide);
+        let (lengths, count, lane_bytes) = if narrow_count >= wide_count {
+            (narrow, narrow_count, 2)
+        } else {
+            (wide, wide_count, 4)
+        };
+
+        let mut offset = 0;
+        let mut i = 0;
+        while i < count {
+            let mut byte = 0;
+            while byte < lengths[i] {
+                table[mask].shuffle[i * lane_bytes + byte as usize] = offset + byte;
+                byte += 1;
+            }
+            offset += lengths[i];
+            i += 1;
+        }
+        table[mask].values = count as u8;
+        table[mask].consumed = offset;
+        table[mask].lane_bytes = lane_bytes as u8;
+        mask += 1;
+    }
+    table
+}
+
+/// Decodes `encoded` into `output` like the scalar decoder, handing the
+/// values the shuffles leave (4-5 byte values, over-long encodings, the last
+/// few bytes) to [`read_value`] one at a time. With `avx2`, windows are
+/// loaded 32 bytes at a time by [`decode_prefix_avx2`].
+///
+/// # Safety
+/// The CPU must support SSE4.1, and AVX2 too if `avx2` is set.
+pub(super) unsafe fn decode_into(
+    encoded: &[u8],
+    output: &mut [u32],
+    avx2: bool,
+) -> Result<usize, DecodeError> {
+    let mut pos = 0;
+    let mut written = 0;
+    while pos < encoded.len() {
+        let (rest, room) = (&encoded[pos..], &mut output[written..]);
+        // SAFETY: SSE4.1 support, and AVX2 support when `avx2` is set, are
+        // guaranteed by the caller
+        let (consumed, values) = unsafe {
+            if avx2 {
+                decode_prefix_avx2(rest, room)
+            } else {
+                decode_prefix(rest, room)
+            }
+        };
+        pos += consumed;
+        written += values;
+        if pos == encoded.len() {
+            break;
+        }
+
+        let (value, len) = read_value(&encoded[pos..], u32::BITS)?;
+        *output.get_mut(written).ok_or(DecodeError::OutputTooSmall)? = value as u32;
+        written += 1;
+        pos += len;
+    }
+    Ok(written)
+}
+
+/// Decodes complete values from the start of `encoded` while a whole window
+/// can be loaded and `output` has room for eight more values. Returns the
+/// bytes consumed and the values written.
+#[target_feature(enable = "sse4.1")]
+unsafe fn decode_prefix(encoded: &[u8], output: &mut [u32]) -> (usize, usize) {
+    let mut pos = 0;
+    let mut written = 0;
+    while pos + WINDOW <= encoded.len() && written + 8 <= output.len() {
+        // SAFETY: the window lies inside `encoded`
+        let bytes = unsafe { _mm_loadu_si128(encoded.as_ptr().add(pos).cast()) };
+        let mask = _mm_movemask_epi8(bytes) as u32;
+
+        if mask & 0xFF == 0 {
+            // Eight single-byte values
+            let low = _mm_cvtepu8_epi32(bytes);
+            let high = _mm_cvtepu8_epi32(_mm_srli_si128::<4>(bytes));
+            // SAFETY: `output` has room for eight values
+            unsafe {
+                store(output, written, low);
+                store(output, written + 4, high);
+            }
+            pos += 8;
+            written += 8;
+            continue;
+        }
+
+        // SAFETY: `output` has room for eight values
+        let Some((consumed, values)) = (unsafe { table_step(bytes, mask, output, written) }) else {
+            break;
+        };
+        pos += consumed;
+        written += values;
+    }
+    (pos, written)
+}
+
+/// Like [`decode_prefix`], but loads 32 bytes per step so a run of sixteen
+/// single-byte values is widened with two 256-bit conversions. Other windows
+/// take the SSE4.1 table step, and the last bytes before the end of a
+/// 32-byte window are left to [`decode_prefix`].
+#[target_feature(enable = "avx2")]
+unsafe fn decode_prefix_avx2(encoded: &[u8], output: &mut [u32]) -> (usize, usize) {
+    let mut pos = 0;
+    let mut written = 0;
+    while pos + 2 * WINDOW <= encoded.len() && written + 16 <= output.len() {
+        // SAFETY: the window lies inside `encoded`
+        let bytes = unsafe { _mm256_loadu_si256(encoded.as_ptr().add(pos).cast()) };
+        let mask = _mm256_movemask_epi8(bytes) as u32;
+        let low = _mm256_castsi256_si128(bytes);
+
+        if mask & 0xFFFF == 0 {
+            // Sixteen single-byte values
+            // SAFETY: `output` has room for sixteen values
+            unsafe {
+                store256(output, written, _mm256_cvtepu8_epi32(low));
+                store256(output, written + 8, _mm256_cvtepu8_epi32(_mm_srli_si128::<8>(low)));
+            }
+            pos += 16;
+            written += 16;
+            continue;
+        }
+        if mask & 0xFF == 0 {
+            // Eight single-byte values
+            // SAFETY: `output` has room for eight values
+            unsafe { store256(output, written, _mm256_cvtepu8_epi32(low)) };
+            pos += 8;
+            written += 8;
+            continue;
+        }
+
+        // SAFETY: AVX2 implies SSE4.1, and `output` has room for eight values
+        let Some((consumed, values)) = (unsafe { table_step(low, mask, output, written) }) else {
+            return (pos, written);
+        };
+        pos += consumed;
+        written += values;
+    }
+    // SAFETY: AVX2 implies SSE4.1
+    let (consumed, values) = unsafe { decode_prefix(&encoded[pos..], &mut output[written..]) };
+    (pos + consumed, written + values)
+}
+
+/// Decodes the values of the table entry for the window `bytes`, whose
+/// continuation bits start `mask`, into `output` at `written`. Returns the
+/// bytes consumed and the values written, or `None` if the window starts
+/// with a value the table does not cover or with an over-long encoding.
+///
+/// # Safety
+/// `output` must have room for eight values at `written`.
+#[target_feature(enable = "sse4.1")]
+unsafe fn table_step(
+    bytes: __m128i,
+    mask: u32,
+    output: &mut [u32],
+    written: usize,
+) -> Option<(usize, usize)> {
+    let entry = &MASK_TABLE[(mask & ((1 << MASK_BITS) - 1)) as usize];
+    // A zero last byte of a multi-byte value is over-long; the scalar
+    // decoder reports it
+    let zeros = _mm_movemask_epi8(_mm_cmpeq_epi8(bytes, _mm_setzero_si128())) as u32;
+    let multi_byte_ends = !mask & (mask << 1);
+    let consumed_bits = (1 << entry.consumed) - 1;
+    if entry.values == 0 || zeros & multi_byte_ends & consumed_bits != 0 {
+        return None;
+    }
+
+    // SAFETY: the shuffle is 16 bytes long
+    let shuffle = unsafe { _mm_loadu_si128(entry.shuffle.as_ptr().cast()) };
+    let lanes = _mm_shuffle_epi8(bytes, shuffle);
+    if entry.lane_bytes == 2 {
+        let low = _mm_and_si128(lanes, _mm_set1_epi16(0x7F));
+        let high = _mm_srli_epi16::<1>(_mm_and_si128(lanes, _mm_set1_epi16(0x7F00)));
+        let values = _mm_or_si128(low, high);
+        // SAFETY: `output` has room for eight values
+        unsafe {
+            store(output, written, _mm_cvtepu16_epi32(values));
+            store(
+                output,
+                written + 4,
+                _mm_cvtepu16_epi32(_mm_srli_si128::<8>(values)),
+            );
+        }
+    } else {
+        let byte0 = _mm_and_si128(lanes, _mm_set1_epi32(0x7F));
+        let byte1 = _mm_srli_epi32::<1>(_mm_and_si128(lanes, _mm_set1_epi32(0x7F00)));
+        let byte2 = _mm_srli_epi32::<2>(_mm_and_si128(lanes, _mm_set1_epi32(0x7F_0000)));
+        // SAFETY: `output` has room for eight values
+        unsafe {
+            store(
+                output,
+                written,
+                _mm_or_si128(_mm_or_si128(byte0, byte1), byte2),
+            )
+        };
+    }
+    Some((entry.consumed as usize, entry.values as usize))
+}
+
+/// Stores four lanes at `output[index..index + 4]`.
+///
+/// # Safety
+/// `index + 4` must not exceed `output.len()`.
+unsafe fn store(output: &mut [u32], index: usize, values: __m128i) {
+    // SAFETY: guaranteed by the caller
+    unsafe { _mm_storeu_si128(output.as_mut_ptr().add(index).cast(), values) };
+}
+
+/// Stores eight lanes at `output[index..index + 8]`.
+///
+/// # Safety
+/// The CPU must support AVX, and `index + 8` must not exceed `output.len()`.
+#[target_feature(enable = "avx")]
+unsafe fn store256(output: &mut [u32], index: usize, values: __m256i) {
+    // SAFETY: guaranteed by the caller
+    unsafe { _mm256_storeu_si256(output.as_mut_ptr().add(index).cast(), values) };
+}
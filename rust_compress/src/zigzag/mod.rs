@@ -0,0 +1,49 @@
+/// Maps a signed value to an unsigned one so that small magnitudes stay
+/// small: 0, -1, 1, -2, 2, ... become 0, 1, 2, 3, 4, ...
+pub fn encode_i32(value: i32) -> u32 {
+    ((value << 1) ^ (value >> 31)) as u32
+}
+
+/// Inverse of [`encode_i32`].
+pub fn decode_i32(value: u32) -> i32 {
+    ((value >> 1) as i32) ^ -((value & 1) as i32)
+}
+
+/// Maps a signed value to an unsigned one, see [`encode_i32`].
+pub fn encode_i64(value: i64) -> u64 {
+    ((value << 1) ^ (value >> 63)) as u64
+}
+
+/// Inverse of [`encode_i64`].
+pub fn decode_i64(value: u64) -> i64 {
+    ((value >> 1) as i64) ^ -((value & 1) as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_small_magnitudes_map_to_small_values() {
+        let signed = [0, -1, 1, -2, 2, -64, 64];
+        let expected = [0, 1, 2, 3, 4, 127, 128];
+        for (&value, &mapped) in signed.iter().zip(&expected) {
+            assert_eq!(encode_i32(value), mapped);
+            assert_eq!(encode_i64(value as i64), mapped as u64);
+        }
+    }
+
+    #[test]
+    fn test_extremes_roundtrip() {
+        for value in [i32::MIN, i32::MIN + 1, i32::MAX - 1, i32::MAX] {
+            assert_eq!(decode_i32(encode_i32(value)), value);
+        }
+        assert_eq!(encode_i32(i32::MIN), u32::MAX);
+        assert_eq!(encode_i32(i32::MAX), u32::MAX - 1);
+
+        for value in [i64::MIN, i64::MIN + 1, i64::MAX - 1, i64::MAX] {
+            assert_eq!(decode_i64(encode_i64(value)), value);
+        }
+        assert_eq!(encode_i64(i64::MIN), u64::MAX);
+    }
+}